@@ -166,25 +166,35 @@ impl KetoClient {
             last_error.unwrap_or_else(|| "none".to_string())
         )))
     }
-    /// Expand a relation to see all subjects that have it (tree of subject_ids and subject_sets).
-    pub async fn expand(
-        &self,
-        namespace: &str,
-        object: &str,
-        relation: &str,
-        max_depth: Option<u32>,
-    ) -> Result<serde_json::Value> {
-        let mut q = format!(
-            "namespace={}&object={}&relation={}",
-            namespace, object, relation
-        );
-        if let Some(d) = max_depth {
-            q.push_str(&format!("&max_depth={}", d));
+    /// List relation tuples with optional filters. `namespace` is required.
+    /// Returns a single page; follow `next_page_token` (or use `list_all_relation_tuples`) for the rest.
+    pub async fn list_relation_tuples(&self, p: ListParams) -> Result<serde_json::Value> {
+        let mut url = Url::parse(&format!("{}/relation-tuples", self.read_url))?;
+        {
+            let mut q = url.query_pairs_mut();
+            q.append_pair("namespace", &p.namespace);
+            if let Some(o) = &p.object {
+                q.append_pair("object", o);
+            }
+            if let Some(r) = &p.relation {
+                q.append_pair("relation", r);
+            }
+            if let Some(s) = &p.subject_id {
+                q.append_pair("subject_id", s);
+            }
+            if let Some(ss) = &p.subject_set {
+                q.append_pair("subject_set", ss);
+            }
+            if let Some(n) = p.page_size {
+                q.append_pair("page_size", &n.to_string());
+            }
+            if let Some(t) = &p.page_token {
+                q.append_pair("page_token", t);
+            }
         }
-        let url = format!("{}/relation-tuples/expand?{}", self.read_url, q);
 
         let req = Request::new_with_init(
-            &url,
+            url.as_str(),
             RequestInit::new()
                 .with_method(Method::Get)
                 .with_headers(Self::headers()?),
@@ -196,73 +206,95 @@ impl KetoClient {
 
         if code != 200 {
             return Err(Error::RustError(format!(
-                "Keto expand error ({}): {}",
+                "Keto list error ({}): {}",
                 code, text
             )));
         }
 
-        serde_json::from_str(&text).map_err(|e| Error::RustError(format!("Keto expand json: {}", e)))
+        serde_json::from_str(&text).map_err(|e| Error::RustError(format!("Keto list json: {}", e)))
     }
 
-    /// List relation tuples with optional filters. `namespace` is required.
-    pub async fn list_relation_tuples(&self, p: ListParams) -> Result<serde_json::Value> {
-        let mut q = format!("namespace={}", p.namespace);
-        if let Some(o) = &p.object {
-            q.push_str(&format!("&object={}", o));
-        }
-        if let Some(r) = &p.relation {
-            q.push_str(&format!("&relation={}", r));
-        }
-        if let Some(s) = &p.subject_id {
-            q.push_str(&format!("&subject_id={}", s));
-        }
-        if let Some(ss) = &p.subject_set {
-            q.push_str(&format!("&subject_set={}", ss));
-        }
-        if let Some(n) = p.page_size {
-            q.push_str(&format!("&page_size={}", n));
-        }
-        if let Some(t) = &p.page_token {
-            q.push_str(&format!("&page_token={}", t));
+    /// List every tuple matching the filters, following `next_page_token` until Keto reports no more pages.
+    pub async fn list_all_relation_tuples(&self, p: ListParams) -> Result<Vec<serde_json::Value>> {
+        let mut tuples = Vec::new();
+        let mut page_token = p.page_token.clone();
+        loop {
+            let page = self
+                .list_relation_tuples(ListParams {
+                    page_token: page_token.clone(),
+                    ..p.clone()
+                })
+                .await?;
+
+            if let Some(arr) = page.get("relation_tuples").and_then(|v| v.as_array()) {
+                tuples.extend(arr.iter().cloned());
+            }
+
+            match page.get("next_page_token").and_then(|t| t.as_str()) {
+                Some(t) if !t.is_empty() && page_token.as_deref() != Some(t) => {
+                    page_token = Some(t.to_string());
+                }
+                _ => break,
+            }
         }
+        Ok(tuples)
+    }
 
-        let url = format!("{}/relation-tuples?{}", self.read_url, q);
+    /// Create a relation tuple via `PUT /relation-tuples` on the Write API. Idempotent if tuple exists.
+    pub async fn create_relation_tuple(
+        &self,
+        namespace: &str,
+        object: &str,
+        relation: &str,
+        subject_id: &str,
+    ) -> Result<()> {
+        let url = format!("{}/relation-tuples", self.write_url);
+        let body = serde_json::json!({
+            "namespace": namespace,
+            "object": object,
+            "relation": relation,
+            "subject_id": subject_id,
+        });
 
         let req = Request::new_with_init(
             &url,
             RequestInit::new()
-                .with_method(Method::Get)
-                .with_headers(Self::headers()?),
+                .with_method(Method::Put)
+                .with_headers(Self::headers()?)
+                .with_body(Some(body.to_string().into())),
         )?;
 
         let mut resp = Fetch::Request(req).send().await?;
         let code = resp.status_code();
-        let text = resp.text().await?;
-
-        if code != 200 {
+        if code != 201 && code != 200 && code != 409 {
+            let text = resp.text().await?;
             return Err(Error::RustError(format!(
-                "Keto list error ({}): {}",
+                "Keto create tuple error ({}): {}",
                 code, text
             )));
         }
-
-        serde_json::from_str(&text).map_err(|e| Error::RustError(format!("Keto list json: {}", e)))
+        Ok(())
     }
 
-    /// Create a relation tuple via `PUT /relation-tuples` on the Write API. Idempotent if tuple exists.
-    pub async fn create_relation_tuple(
+    /// Create a tuple whose subject is a subject set (`namespace:object#relation`), so every subject
+    /// holding that relation inherits this one. Idempotent if tuple exists.
+    pub async fn create_relation_tuple_with_subject_set(
         &self,
         namespace: &str,
         object: &str,
         relation: &str,
-        subject_id: &str,
+        subject_set: &SubjectSet,
     ) -> Result<()> {
         let url = format!("{}/relation-tuples", self.write_url);
         let body = serde_json::json!({
             "namespace": namespace,
             "object": object,
             "relation": relation,
-            "subject_id": subject_id,
+            "subject_set": {
+                "namespace": subject_set.namespace,
+                "object": subject_set.object,
+                "relation": subject_set.relation,
+            },
         });
 
         let req = Request::new_with_init(
@@ -285,6 +317,42 @@ impl KetoClient {
         Ok(())
     }
 
+    /// Delete a tuple whose subject is a subject set via `DELETE /relation-tuples` on the Write API.
+    pub async fn delete_relation_tuple_with_subject_set(
+        &self,
+        namespace: &str,
+        object: &str,
+        relation: &str,
+        subject_set: &SubjectSet,
+    ) -> Result<()> {
+        let mut url = Url::parse(&format!("{}/relation-tuples", self.write_url))?;
+        url.query_pairs_mut()
+            .append_pair("namespace", namespace)
+            .append_pair("object", object)
+            .append_pair("relation", relation)
+            .append_pair("subject_set.namespace", &subject_set.namespace)
+            .append_pair("subject_set.object", &subject_set.object)
+            .append_pair("subject_set.relation", &subject_set.relation);
+
+        let req = Request::new_with_init(
+            url.as_str(),
+            RequestInit::new()
+                .with_method(Method::Delete)
+                .with_headers(Self::headers()?),
+        )?;
+
+        let mut resp = Fetch::Request(req).send().await?;
+        let code = resp.status_code();
+        if code != 200 && code != 204 {
+            let text = resp.text().await?;
+            return Err(Error::RustError(format!(
+                "Keto delete tuple error ({}): {}",
+                code, text
+            )));
+        }
+        Ok(())
+    }
+
     /// Delete relation tuples matching the filters via `DELETE /relation-tuples` on the Write API.
     pub async fn delete_relation_tuple(
         &self,
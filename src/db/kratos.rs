@@ -66,4 +66,26 @@ impl KratosClient {
             last_error.unwrap_or_else(|| "none".to_string())
         )))
     }
+
+    /// Email address of an identity: `traits.email`, falling back to the first verifiable address.
+    pub async fn get_email(&self, id: &str) -> Result<Option<String>> {
+        let json = self.get_identity(id).await?;
+        let traits_email = json
+            .get("traits")
+            .and_then(|t| t.get("email"))
+            .and_then(|e| e.as_str())
+            .map(|s| s.to_string());
+        if traits_email.is_some() {
+            return Ok(traits_email);
+        }
+        Ok(json
+            .get("verifiable_addresses")
+            .and_then(|v| v.as_array())
+            .and_then(|arr| {
+                arr.iter()
+                    .filter_map(|it| it.get("value").and_then(|v| v.as_str()))
+                    .next()
+            })
+            .map(|s| s.to_string()))
+    }
 }
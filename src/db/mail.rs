@@ -0,0 +1,77 @@
+//! Minimal HTTP mail API client for Cloudflare Workers.
+//!
+//! Workers cannot speak SMTP directly, so mail goes through an HTTP relay. The request body follows
+//! Mailpit's send API (`POST /api/v1/send`), which lets local runs deliver into the Mailpit container
+//! from `docker-compose.yml`; point `MAIL_API_URL` at any relay accepting the same JSON in production.
+
+use crate::utils::context::AppContext;
+use worker::*;
+
+pub struct MailClient {
+    /// Send endpoint (e.g. `http://localhost:8025/api/v1/send`).
+    pub api_url: String,
+    /// Optional bearer token for the relay.
+    pub api_key: Option<String>,
+    pub from_address: String,
+    pub from_name: String,
+}
+
+/// A plain-text message to one recipient.
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+}
+
+impl MailClient {
+    /// Build client from env. Expects `MAIL_API_URL` and `MAIL_FROM`; `MAIL_API_KEY` and
+    /// `MAIL_FROM_NAME` are optional (vars or secrets).
+    pub fn from_env(ctx: &AppContext) -> Result<Self> {
+        let read = |name: &str| {
+            ctx.env
+                .var(name)
+                .or_else(|_| ctx.env.secret(name))
+                .map(|v| v.to_string())
+        };
+        Ok(Self {
+            api_url: read("MAIL_API_URL")?,
+            api_key: read("MAIL_API_KEY").ok().filter(|k| !k.is_empty()),
+            from_address: read("MAIL_FROM")?,
+            from_name: read("MAIL_FROM_NAME").unwrap_or_default(),
+        })
+    }
+
+    pub async fn send(&self, mail: &Mail) -> Result<()> {
+        let body = serde_json::json!({
+            "From": { "Email": self.from_address, "Name": self.from_name },
+            "To": [{ "Email": mail.to }],
+            "Subject": mail.subject,
+            "Text": mail.text,
+        });
+
+        let headers = Headers::new();
+        headers.set("Content-Type", "application/json")?;
+        if let Some(key) = &self.api_key {
+            headers.set("Authorization", &format!("Bearer {}", key))?;
+        }
+
+        let req = Request::new_with_init(
+            &self.api_url,
+            RequestInit::new()
+                .with_method(Method::Post)
+                .with_headers(headers)
+                .with_body(Some(body.to_string().into())),
+        )?;
+
+        let mut resp = Fetch::Request(req).send().await?;
+        let code = resp.status_code();
+        if !(200..300).contains(&code) {
+            let text = resp.text().await?;
+            return Err(Error::RustError(format!(
+                "Mail send error ({}): {}",
+                code, text
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 pub mod kratos;
 pub mod keto;
+pub mod mail;
 pub mod supabase;
 
 pub use kratos::KratosClient;
 pub use keto::{CheckParams, KetoClient, ListParams, SubjectSet};
+pub use mail::{Mail, MailClient};
 pub use supabase::SupabaseClient;
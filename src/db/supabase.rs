@@ -6,6 +6,45 @@ pub struct SupabaseClient {
     pub api_key: String,
 }
 
+/// Percent-encodes a user-supplied value for use inside a PostgREST query string.
+/// Only RFC 3986 unreserved characters are left as-is, so `&` and `=` cannot start another
+/// parameter. PostgREST decodes the value before it parses lists, so inside `in.(...)` or
+/// `or=(...)` a `,` or `)` still ends the element; use `encode_list_value` there.
+pub fn encode_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Encodes one element of a PostgREST list (`in.(...)`, `or=(...)`). The element is double-quoted,
+/// with `"` and `\` escaped, so `,`, `.` and parentheses in it are read literally.
+pub fn encode_list_value(value: &str) -> String {
+    let quoted = format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+    encode_value(&quoted)
+}
+
+/// Escapes `LIKE` wildcards so a search term only ever matches literally. PostgREST treats `*`
+/// as `%`, so it is dropped rather than escaped.
+pub fn escape_like(term: &str) -> String {
+    let mut out = String::with_capacity(term.len());
+    for c in term.chars() {
+        match c {
+            '\\' | '%' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '*' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
 impl SupabaseClient {
     pub fn from_env(ctx: &AppContext) -> Result<Self> {
         let base_url = ctx.env.var("DB_API_URL")?.to_string();
@@ -65,8 +104,70 @@ impl SupabaseClient {
         resp.json().await
     }
 
-    pub async fn patch(&self, table: &str, id: i64, body: serde_json::Value) -> Result<serde_json::Value> {
-        let url = format!("{}/rest/v1/{}?id=eq.{}", self.base_url, table, id);
+    /// Insert `body`, or update the columns it contains on the row that already has the same
+    /// `on_conflict` key, in one statement.
+    pub async fn upsert(
+        &self,
+        table: &str,
+        on_conflict: &str,
+        body: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let url = format!("{}/rest/v1/{}?on_conflict={}", self.base_url, table, on_conflict);
+        let headers = self.get_headers()?;
+        headers.set("Prefer", "return=representation,resolution=merge-duplicates")?;
+
+        let req = Request::new_with_init(
+            &url,
+            RequestInit::new()
+                .with_method(Method::Post)
+                .with_headers(headers)
+                .with_body(Some(body.to_string().into())),
+        )?;
+
+        let mut resp = Fetch::Request(req).send().await?;
+        if resp.status_code() != 201 && resp.status_code() != 200 {
+            let error_text = resp.text().await?;
+            return Err(Error::RustError(format!("Supabase error ({}): {}", resp.status_code(), error_text)));
+        }
+        resp.json().await
+    }
+
+    /// Call a Postgres function through PostgREST (`POST /rest/v1/rpc/<function>`).
+    pub async fn rpc(&self, function: &str, args: serde_json::Value) -> Result<serde_json::Value> {
+        self.rpc_query(function, "", args).await
+    }
+
+    /// Call a function returning rows, with PostgREST `query` params (`select`, filters, `order`,
+    /// `limit`) applied to its result.
+    pub async fn rpc_query(
+        &self,
+        function: &str,
+        query: &str,
+        args: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let url = format!("{}/rest/v1/rpc/{}?{}", self.base_url, function, query);
+        let headers = self.get_headers()?;
+
+        let req = Request::new_with_init(
+            &url,
+            RequestInit::new()
+                .with_method(Method::Post)
+                .with_headers(headers)
+                .with_body(Some(args.to_string().into())),
+        )?;
+
+        let mut resp = Fetch::Request(req).send().await?;
+        if resp.status_code() != 200 {
+            let error_text = resp.text().await?;
+            return Err(Error::RustError(format!("Supabase error ({}): {}", resp.status_code(), error_text)));
+        }
+        resp.json().await
+    }
+
+    /// PATCH every row matching the PostgREST `filters` (e.g. `id=eq.1&deleted_at=is.null`).
+    /// Returns the updated rows; an empty array means nothing matched.
+    pub async fn patch_where(&self, table: &str, filters: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let url = format!("{}/rest/v1/{}?{}", self.base_url, table, filters);
         let headers = self.get_headers()?;
         headers.set("Prefer", "return=representation")?;
         
@@ -87,7 +188,12 @@ impl SupabaseClient {
     }
 
     pub async fn delete(&self, table: &str, id: i64) -> Result<()> {
-        let url = format!("{}/rest/v1/{}?id=eq.{}", self.base_url, table, id);
+        self.delete_where(table, &format!("id=eq.{}", id)).await
+    }
+
+    /// DELETE every row matching the PostgREST `filters`.
+    pub async fn delete_where(&self, table: &str, filters: &str) -> Result<()> {
+        let url = format!("{}/rest/v1/{}?{}", self.base_url, table, filters);
         let headers = self.get_headers()?;
         
         let req = Request::new_with_init(
@@ -105,3 +211,79 @@ impl SupabaseClient {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_postgrest_reserved_characters() {
+        let cases = [
+            ("plain-text_1.0~", "plain-text_1.0~"),
+            ("a,b", "a%2Cb"),
+            ("(x)", "%28x%29"),
+            ("a*b", "a%2Ab"),
+            ("100%", "100%25"),
+            ("a&b=c", "a%26b%3Dc"),
+            ("\"quoted\"", "%22quoted%22"),
+            ("a b", "a%20b"),
+            ("a:b", "a%3Ab"),
+            ("é", "%C3%A9"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(encode_value(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn dots_stay_literal() {
+        // `.` is unreserved in RFC 3986, so it is sent as-is. PostgREST splits `op.value` at the
+        // first dot only, so dots inside the value are still read literally.
+        assert_eq!(encode_value("v1.2.3"), "v1.2.3");
+    }
+
+    #[test]
+    fn quotes_list_elements() {
+        let cases = [
+            ("abc", "%22abc%22"),
+            ("a,b", "%22a%2Cb%22"),
+            ("x)", "%22x%29%22"),
+            ("say \"hi\"", "%22say%20%5C%22hi%5C%22%22"),
+            ("C:\\", "%22C%3A%5C%5C%22"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(encode_list_value(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        let cases = [
+            ("rent", "rent"),
+            ("100%", "100\\%"),
+            ("snake_case", "snake\\_case"),
+            ("C:\\temp", "C:\\\\temp"),
+            ("a,b.(c)", "a,b.(c)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(escape_like(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn like_drops_asterisks_on_purpose() {
+        // PostgREST turns every `*` in an `ilike` pattern into `%`, and there is no way to escape
+        // it, so a literal `*` cannot be matched; dropping it keeps the search from matching
+        // everything.
+        assert_eq!(escape_like("*"), "");
+        assert_eq!(escape_like("a*b*"), "ab");
+        assert_eq!(escape_like("**%**"), "\\%");
+    }
+
+    #[test]
+    fn search_pattern_is_fully_encoded() {
+        let pattern = format!("*{}*", escape_like("50%, (off)*"));
+        assert_eq!(pattern, "*50\\%, (off)*");
+        assert_eq!(encode_value(&pattern), "%2A50%5C%25%2C%20%28off%29%2A");
+    }
+}
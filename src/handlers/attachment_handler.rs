@@ -0,0 +1,128 @@
+use crate::middleware::{auth, cors};
+use crate::models::validate_file_name;
+use crate::repositories::AttachmentRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Parses an integer route parameter. `None` when missing or not an integer.
+fn id_param(ctx: &RouteContext<()>, name: &str) -> Option<i64> {
+    ctx.param(name).and_then(|s| s.parse().ok())
+}
+
+pub async fn list_attachments(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match AttachmentRepo::list(&app, &user_id, todo_id).await {
+        Ok(attachments) => cors::add_headers(Response::from_json(&attachments)?),
+        Err(e) => errors::from_repo_error("list_attachments", e),
+    }
+}
+
+/// `POST /api/todos/:id/attachments?name=<file name>` with the raw file as body. The
+/// `Content-Type` header is stored and served back on download.
+pub async fn upload_attachment(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let url = req.url()?;
+    let name = url
+        .query_pairs()
+        .find(|(k, _)| k == "name")
+        .map(|(_, v)| v.trim().to_string())
+        .unwrap_or_default();
+    if let Err(msg) = validate_file_name(&name) {
+        return errors::json_error(&msg, 400);
+    }
+    let content_type = req
+        .headers()
+        .get("Content-Type")?
+        .filter(|c| !c.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string());
+
+    // Reject oversized uploads before reading the body when the client announces its size.
+    let max = AttachmentRepo::max_file_bytes(&app);
+    let declared: Option<u64> = req
+        .headers()
+        .get("Content-Length")?
+        .and_then(|l| l.parse().ok());
+    if declared.is_some_and(|len| len > max) {
+        return errors::json_error(&format!("Attachments must be at most {} bytes", max), 413);
+    }
+    let content = req.bytes().await?;
+
+    match AttachmentRepo::upload(&app, &user_id, todo_id, &name, &content_type, content).await {
+        Ok(attachment) => cors::add_headers(Response::from_json(&attachment)?.with_status(201)),
+        Err(e) => errors::from_repo_error("upload_attachment", e),
+    }
+}
+
+pub async fn download_attachment(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, id) = match (id_param(&ctx, "id"), id_param(&ctx, "attachment_id")) {
+        (Some(todo_id), Some(id)) => (todo_id, id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match AttachmentRepo::download(&app, &user_id, todo_id, id).await {
+        Ok((attachment, content)) => {
+            let headers = Headers::new();
+            headers.set("Content-Type", &attachment.content_type)?;
+            headers.set(
+                "Content-Disposition",
+                &format!("attachment; filename=\"{}\"", attachment.file_name),
+            )?;
+            headers.set("ETag", &format!("\"{}\"", attachment.checksum))?;
+            cors::add_headers(Response::from_bytes(content)?.with_headers(headers))
+        }
+        Err(e) => errors::from_repo_error("download_attachment", e),
+    }
+}
+
+pub async fn delete_attachment(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, id) = match (id_param(&ctx, "id"), id_param(&ctx, "attachment_id")) {
+        (Some(todo_id), Some(id)) => (todo_id, id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match AttachmentRepo::delete(&app, &user_id, todo_id, id).await {
+        Ok(()) => cors::add_headers(Response::ok("deleted")?),
+        Err(e) => errors::from_repo_error("delete_attachment", e),
+    }
+}
@@ -0,0 +1,70 @@
+use crate::middleware::{auth, cors};
+use crate::models::{to_ical, CalendarFeed};
+use crate::repositories::{CalendarRepo, TodoRepo};
+use crate::utils::{context::AppContext, errors, time};
+use worker::*;
+
+/// Public URL of a feed: `PUBLIC_API_URL` if set (the address calendar apps can reach, e.g. the
+/// Oathkeeper proxy), otherwise the origin of this request.
+fn feed_url(req: &Request, app: &AppContext, token: &str) -> Result<String> {
+    let base = match app.env.var("PUBLIC_API_URL") {
+        Ok(v) => v.to_string(),
+        Err(_) => req.url()?.origin().ascii_serialization(),
+    };
+    Ok(format!("{}/ical/{}.ics", base.trim_end_matches('/'), token))
+}
+
+/// `POST /api/calendar/feed`: create the caller's feed URL, revoking any previous one.
+pub async fn rotate_feed(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match CalendarRepo::rotate(&app, &user_id).await {
+        Ok(token) => {
+            let feed = CalendarFeed {
+                url: feed_url(&req, &app, &token)?,
+            };
+            cors::add_headers(Response::from_json(&feed)?.with_status(201))
+        }
+        Err(e) => errors::from_repo_error("rotate_feed", e),
+    }
+}
+
+/// `DELETE /api/calendar/feed`: turn the caller's feed off.
+pub async fn revoke_feed(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match CalendarRepo::revoke(&app, &user_id).await {
+        Ok(()) => cors::add_headers(Response::ok("revoked")?),
+        Err(e) => errors::from_repo_error("revoke_feed", e),
+    }
+}
+
+/// `GET /ical/<token>.ics`: the todos of the token's user as VTODOs. Public: the token is the
+/// only credential, and `X-User-Id` is ignored.
+pub async fn feed(ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let token = match ctx.param("file").and_then(|f| f.strip_suffix(".ics")) {
+        Some(t) => t,
+        None => return errors::json_error("Feed not found", 404),
+    };
+    let user_id = match CalendarRepo::user_for_token(&app, token).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return errors::json_error("Feed not found", 404),
+        Err(e) => return errors::from_repo_error("calendar_feed", e),
+    };
+
+    match TodoRepo::list_all(&app, &user_id).await {
+        Ok(todos) => {
+            let headers = Headers::new();
+            headers.set("Content-Type", "text/calendar; charset=utf-8")?;
+            headers.set("Cache-Control", "private, max-age=300")?;
+            cors::add_headers(Response::ok(to_ical(&todos, time::now()))?.with_headers(headers))
+        }
+        Err(e) => errors::from_repo_error("calendar_feed", e),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::middleware::{auth, cors};
+use crate::models::CommentBody;
+use crate::repositories::CommentRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+/// Parses an integer route parameter. `None` when missing or not an integer.
+fn id_param(ctx: &RouteContext<()>, name: &str) -> Option<i64> {
+    ctx.param(name).and_then(|s| s.parse().ok())
+}
+
+pub async fn list_comments(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match CommentRepo::list(&app, &user_id, todo_id).await {
+        Ok(comments) => cors::add_headers(Response::from_json(&comments)?),
+        Err(e) => errors::from_repo_error("list_comments", e),
+    }
+}
+
+pub async fn create_comment(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: CommentBody = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match CommentRepo::create(&app, &user_id, todo_id, body).await {
+        Ok(comment) => cors::add_headers(Response::from_json(&comment)?.with_status(201)),
+        Err(e) => errors::from_repo_error("create_comment", e),
+    }
+}
+
+pub async fn update_comment(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, id) = match (id_param(&ctx, "id"), id_param(&ctx, "comment_id")) {
+        (Some(todo_id), Some(id)) => (todo_id, id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: CommentBody = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+    let is_admin = auth::is_admin(&app, &user_id).await.unwrap_or(false);
+
+    match CommentRepo::update(&app, &user_id, is_admin, todo_id, id, body).await {
+        Ok(comment) => cors::add_headers(Response::from_json(&comment)?),
+        Err(e) => errors::from_repo_error("update_comment", e),
+    }
+}
+
+pub async fn delete_comment(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, id) = match (id_param(&ctx, "id"), id_param(&ctx, "comment_id")) {
+        (Some(todo_id), Some(id)) => (todo_id, id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+    let is_admin = auth::is_admin(&app, &user_id).await.unwrap_or(false);
+
+    match CommentRepo::delete(&app, &user_id, is_admin, todo_id, id).await {
+        Ok(()) => cors::add_headers(Response::ok("deleted")?),
+        Err(e) => errors::from_repo_error("delete_comment", e),
+    }
+}
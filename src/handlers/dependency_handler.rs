@@ -0,0 +1,73 @@
+use crate::middleware::{auth, cors};
+use crate::models::AddDependency;
+use crate::repositories::DependencyRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+fn id_param(ctx: &RouteContext<()>, name: &str) -> Option<i64> {
+    ctx.param(name).and_then(|s| s.parse().ok())
+}
+
+pub async fn dependency_graph(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match DependencyRepo::graph(&app, &user_id, todo_id).await {
+        Ok(graph) => cors::add_headers(Response::from_json(&graph)?),
+        Err(e) => errors::from_repo_error("dependency_graph", e),
+    }
+}
+
+pub async fn add_dependency(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: AddDependency = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+
+    match DependencyRepo::add(&app, &user_id, todo_id, body.blocker_id).await {
+        Ok(edge) => cors::add_headers(Response::from_json(&edge)?.with_status(201)),
+        Err(e) => errors::from_repo_error("add_dependency", e),
+    }
+}
+
+pub async fn remove_dependency(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, blocker_id) = match (id_param(&ctx, "id"), id_param(&ctx, "blocker_id")) {
+        (Some(todo_id), Some(blocker_id)) => (todo_id, blocker_id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match DependencyRepo::remove(&app, &user_id, todo_id, blocker_id).await {
+        Ok(()) => cors::add_headers(Response::ok("deleted")?),
+        Err(e) => errors::from_repo_error("remove_dependency", e),
+    }
+}
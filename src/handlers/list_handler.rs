@@ -0,0 +1,171 @@
+use crate::middleware::{auth, cors};
+use crate::models::{AddListMember, CreateList, UpdateList};
+use crate::repositories::ListRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+/// Parses the `:id` route parameter. `None` when missing or not an integer.
+fn list_id(ctx: &RouteContext<()>) -> Option<i64> {
+    ctx.param("id").and_then(|s| s.parse().ok())
+}
+
+pub async fn list_lists(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match ListRepo::list(&app, &user_id).await {
+        Ok(lists) => cors::add_headers(Response::from_json(&lists)?),
+        Err(e) => errors::from_repo_error("list_lists", e),
+    }
+}
+
+pub async fn create_list(mut req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let body: CreateList = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match ListRepo::create(&app, &user_id, body).await {
+        Ok(list) => cors::add_headers(Response::from_json(&list)?.with_status(201)),
+        Err(e) => errors::from_repo_error("create_list", e),
+    }
+}
+
+pub async fn get_list(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match list_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match ListRepo::get(&app, &user_id, id).await {
+        Ok(list) => cors::add_headers(Response::from_json(&list)?),
+        Err(e) => errors::from_repo_error("get_list", e),
+    }
+}
+
+pub async fn update_list(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match list_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: UpdateList = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match ListRepo::update(&app, &user_id, id, body).await {
+        Ok(list) => cors::add_headers(Response::from_json(&list)?),
+        Err(e) => errors::from_repo_error("update_list", e),
+    }
+}
+
+pub async fn delete_list(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match list_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match ListRepo::delete(&app, &user_id, id).await {
+        Ok(()) => cors::add_headers(Response::ok("deleted")?),
+        Err(e) => errors::from_repo_error("delete_list", e),
+    }
+}
+
+pub async fn list_members(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match list_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match ListRepo::members(&app, &user_id, id).await {
+        Ok(members) => cors::add_headers(Response::from_json(&members)?),
+        Err(e) => errors::from_repo_error("list_members", e),
+    }
+}
+
+pub async fn add_member(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match list_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: AddListMember = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.user_id.trim().is_empty() {
+        return errors::json_error("user_id is required", 400);
+    }
+
+    match ListRepo::add_member(&app, &user_id, id, body.user_id.trim()).await {
+        Ok(member) => cors::add_headers(Response::from_json(&member)?.with_status(201)),
+        Err(e) => errors::from_repo_error("add_member", e),
+    }
+}
+
+pub async fn remove_member(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match list_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let member_id = match ctx.param("user_id") {
+        Some(m) => m.to_string(),
+        None => return errors::json_error("Missing user_id parameter", 400),
+    };
+
+    match ListRepo::remove_member(&app, &user_id, id, &member_id).await {
+        Ok(()) => cors::add_headers(Response::ok("removed")?),
+        Err(e) => errors::from_repo_error("remove_member", e),
+    }
+}
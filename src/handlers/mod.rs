@@ -1,3 +1,12 @@
 pub mod health;
 pub mod user_handler;
 pub mod todo_handler;
+pub mod tag_handler;
+pub mod list_handler;
+pub mod comment_handler;
+pub mod attachment_handler;
+pub mod transfer_handler;
+pub mod calendar_handler;
+pub mod settings_handler;
+pub mod dependency_handler;
+pub mod time_handler;
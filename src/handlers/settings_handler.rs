@@ -0,0 +1,41 @@
+use crate::middleware::{auth, cors};
+use crate::models::UpdateSettings;
+use crate::repositories::SettingsRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+/// `GET /api/settings`: the caller's settings, with defaults filled in.
+pub async fn get_settings(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match SettingsRepo::get(&app, &user_id).await {
+        Ok(settings) => cors::add_headers(Response::from_json(&settings)?),
+        Err(e) => errors::from_repo_error("get_settings", e),
+    }
+}
+
+/// `PATCH /api/settings`: change the fields present in the body.
+pub async fn update_settings(mut req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let input: UpdateSettings = match req.json().await {
+        Ok(i) => i,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if input.is_empty() {
+        return errors::json_error("No fields to update", 400);
+    }
+    if let Err(msg) = input.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match SettingsRepo::update(&app, &user_id, input).await {
+        Ok(settings) => cors::add_headers(Response::from_json(&settings)?),
+        Err(e) => errors::from_repo_error("update_settings", e),
+    }
+}
@@ -0,0 +1,147 @@
+use crate::middleware::{auth, cors};
+use crate::models::{AttachTag, CreateTag, UpdateTag};
+use crate::repositories::TagRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+/// Parses an integer route parameter. `None` when missing or not an integer.
+fn id_param(ctx: &RouteContext<()>, name: &str) -> Option<i64> {
+    ctx.param(name).and_then(|s| s.parse().ok())
+}
+
+pub async fn list_tags(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match TagRepo::list(&app, &user_id).await {
+        Ok(tags) => cors::add_headers(Response::from_json(&tags)?),
+        Err(e) => errors::from_repo_error("list_tags", e),
+    }
+}
+
+pub async fn create_tag(mut req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let body: CreateTag = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match TagRepo::create(&app, &user_id, body).await {
+        Ok(tag) => cors::add_headers(Response::from_json(&tag)?.with_status(201)),
+        Err(e) => errors::from_repo_error("create_tag", e),
+    }
+}
+
+pub async fn update_tag(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: UpdateTag = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.is_empty() {
+        return errors::json_error("No fields to update", 400);
+    }
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match TagRepo::update(&app, &user_id, id, body).await {
+        Ok(tag) => cors::add_headers(Response::from_json(&tag)?),
+        Err(e) => errors::from_repo_error("update_tag", e),
+    }
+}
+
+pub async fn delete_tag(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TagRepo::delete(&app, &user_id, id).await {
+        Ok(()) => cors::add_headers(Response::ok("deleted")?),
+        Err(e) => errors::from_repo_error("delete_tag", e),
+    }
+}
+
+pub async fn list_todo_tags(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TagRepo::list_for_todo(&app, &user_id, todo_id).await {
+        Ok(tags) => cors::add_headers(Response::from_json(&tags)?),
+        Err(e) => errors::from_repo_error("list_todo_tags", e),
+    }
+}
+
+pub async fn attach_tag(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: AttachTag = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+
+    match TagRepo::attach(&app, &user_id, todo_id, body.tag_id).await {
+        Ok(()) => cors::add_headers(Response::ok("attached")?),
+        Err(e) => errors::from_repo_error("attach_tag", e),
+    }
+}
+
+pub async fn detach_tag(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, tag_id) = match (id_param(&ctx, "id"), id_param(&ctx, "tag_id")) {
+        (Some(todo_id), Some(tag_id)) => (todo_id, tag_id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TagRepo::detach(&app, &user_id, todo_id, tag_id).await {
+        Ok(()) => cors::add_headers(Response::ok("detached")?),
+        Err(e) => errors::from_repo_error("detach_tag", e),
+    }
+}
@@ -0,0 +1,127 @@
+use crate::middleware::{auth, cors};
+use crate::models::{TimeReportQuery, UpdateTimeEntry};
+use crate::repositories::TimeRepo;
+use crate::utils::{context::AppContext, errors, time};
+use worker::*;
+
+fn id_param(ctx: &RouteContext<()>, name: &str) -> Option<i64> {
+    ctx.param(name).and_then(|s| s.parse().ok())
+}
+
+pub async fn start_timer(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TimeRepo::start(&app, &user_id, todo_id).await {
+        Ok(entry) => cors::add_headers(Response::from_json(&entry)?),
+        Err(e) => errors::from_repo_error("start_timer", e),
+    }
+}
+
+pub async fn stop_timer(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TimeRepo::stop(&app, &user_id, todo_id).await {
+        Ok(entry) => cors::add_headers(Response::from_json(&entry)?),
+        Err(e) => errors::from_repo_error("stop_timer", e),
+    }
+}
+
+pub async fn list_time_entries(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let todo_id = match id_param(&ctx, "id") {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TimeRepo::list(&app, &user_id, todo_id).await {
+        Ok(entries) => cors::add_headers(Response::from_json(&entries)?),
+        Err(e) => errors::from_repo_error("list_time_entries", e),
+    }
+}
+
+pub async fn update_time_entry(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, id) = match (id_param(&ctx, "id"), id_param(&ctx, "entry_id")) {
+        (Some(todo_id), Some(id)) => (todo_id, id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: UpdateTimeEntry = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.is_empty() {
+        return errors::json_error("No fields to update", 400);
+    }
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match TimeRepo::update(&app, &user_id, todo_id, id, body).await {
+        Ok(entry) => cors::add_headers(Response::from_json(&entry)?),
+        Err(e) => errors::from_repo_error("update_time_entry", e),
+    }
+}
+
+pub async fn delete_time_entry(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let (todo_id, id) = match (id_param(&ctx, "id"), id_param(&ctx, "entry_id")) {
+        (Some(todo_id), Some(id)) => (todo_id, id),
+        _ => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TimeRepo::delete(&app, &user_id, todo_id, id).await {
+        Ok(()) => cors::add_headers(Response::ok("deleted")?),
+        Err(e) => errors::from_repo_error("delete_time_entry", e),
+    }
+}
+
+pub async fn time_report(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let query = match TimeReportQuery::from_url(&req.url()?, time::now().date_naive()) {
+        Ok(q) => q,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+
+    match TimeRepo::report(&app, &user_id, &query).await {
+        Ok(report) => cors::add_headers(Response::from_json(&report)?),
+        Err(e) => errors::from_repo_error("time_report", e),
+    }
+}
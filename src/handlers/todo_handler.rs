@@ -1,24 +1,88 @@
 use crate::db::KratosClient;
-use crate::models::{CreateTodo, UpdateTodo};
-use crate::repositories::TodoRepo;
 use crate::middleware::{auth, cors, logging};
-use crate::utils::{context::AppContext, errors};
+use crate::models::{
+    AssignTodo, BatchRequest, CreateTag, CreateTodo, ListTodosQuery, MoveTodo, QuickAdd,
+    QuickAddRequest, QuickAddResult, Recurrence, ShareTodo, StatsQuery, Todo, UnshareTodo,
+    UpdateSeries, UpdateTodo, UpdateTodoOptions,
+};
+use crate::repositories::{ActivityRepo, SettingsRepo, TagRepo, TodoRepo};
+use crate::utils::{context::AppContext, errors, etag, time};
 use std::collections::HashMap;
 use worker::*;
 
+/// Parses the `:id` route parameter. `None` when missing or not an integer.
+fn todo_id(ctx: &RouteContext<()>) -> Option<i64> {
+    ctx.param("id").and_then(|s| s.parse().ok())
+}
+
+/// JSON response for a single todo, with its version as `ETag`.
+fn todo_response(todo: &Todo) -> Result<Response> {
+    let mut res = Response::from_json(todo)?;
+    res.headers_mut().set("ETag", &etag::format(todo.version))?;
+    cors::add_headers(res)
+}
+
+/// Version required by the `If-Match` header; the error is a message for a 412 response.
+fn if_match(req: &Request) -> std::result::Result<Option<i64>, String> {
+    let header = req.headers().get("If-Match").ok().flatten();
+    etag::parse_if_match(header.as_deref())
+}
+
 pub async fn list_todos(req: Request, app: AppContext) -> Result<Response> {
     let user_id = match auth::get_user_id(&req) {
         Some(u) => u,
         None => return errors::json_error("Missing X-User-Id", 401),
     };
-    let todos = match TodoRepo::list(&app, &user_id).await {
-        Ok(t) => t,
-        Err(e) => {
-            logging::log_error(&format!("list_todos: {}", e));
-            return errors::json_server_error("Internal server error");
-        }
+    let query = match ListTodosQuery::from_url(&req.url()?) {
+        Ok(q) => q,
+        Err(msg) => return errors::json_error(&msg, 400),
     };
-    cors::add_headers(Response::from_json(&todos)?)
+    let res = if query.tree {
+        TodoRepo::list_tree(&app, &user_id, &query)
+            .await
+            .and_then(|page| Response::from_json(&page))
+    } else {
+        TodoRepo::list(&app, &user_id, &query)
+            .await
+            .and_then(|page| Response::from_json(&page))
+    };
+    match res {
+        Ok(res) => cors::add_headers(res),
+        Err(e) => errors::from_repo_error("list_todos", e),
+    }
+}
+
+pub async fn todo_stats(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let today = time::now().date_naive();
+    let query = match StatsQuery::from_url(&req.url()?, today) {
+        Ok(q) => q,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+
+    match TodoRepo::stats(&app, &user_id, &query, today).await {
+        Ok(stats) => cors::add_headers(Response::from_json(&stats)?),
+        Err(e) => errors::from_repo_error("todo_stats", e),
+    }
+}
+
+pub async fn get_todo(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::get(&app, &user_id, id).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("get_todo", e),
+    }
 }
 
 pub async fn create_todo(mut req: Request, app: AppContext) -> Result<Response> {
@@ -31,18 +95,105 @@ pub async fn create_todo(mut req: Request, app: AppContext) -> Result<Response>
         .await
         .map_err(|_| Error::RustError("Invalid JSON".into()))?;
 
-    if body.title.trim().is_empty() {
-        return errors::json_error("Title is required", 400);
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
     }
 
-    let todo = match TodoRepo::create(&app, &user_id, body.title).await {
+    let todo = match TodoRepo::create(&app, &user_id, body).await {
         Ok(t) => t,
-        Err(e) => {
-            logging::log_error(&format!("create_todo: {}", e));
-            return errors::json_server_error("Internal server error");
+        Err(e) => return errors::from_repo_error("create_todo", e),
+    };
+    Ok(todo_response(&todo)?.with_status(201))
+}
+
+/// Parses a line like `Pay rent tomorrow 9am #finance !high every month` in the caller's time
+/// zone. Returns the parse for the client to confirm, or creates the todo when `save` is set.
+pub async fn quick_add_todo(mut req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let body: QuickAddRequest = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    let tz = match SettingsRepo::get(&app, &user_id).await {
+        Ok(settings) => settings.tz(),
+        Err(e) => return errors::from_repo_error("quick_add_todo", e),
+    };
+    let parsed = match QuickAdd::parse(&body.text, time::now().with_timezone(&tz)) {
+        Ok(p) => p,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+    let input = parsed.to_create();
+    if let Err(msg) = input.validate() {
+        return errors::json_error(&msg, 400);
+    }
+    if !body.save {
+        let result = QuickAddResult {
+            parsed,
+            todo: None,
+            warning: None,
+        };
+        return cors::add_headers(Response::from_json(&result)?);
+    }
+
+    // Tags are resolved first, so a failure here leaves nothing behind for a retry to duplicate.
+    let tag_ids = match resolve_tags_by_name(&app, &user_id, &parsed.tags).await {
+        Ok(ids) => ids,
+        Err(e) => return errors::from_repo_error("quick_add_todo", e),
+    };
+    let todo = match TodoRepo::create(&app, &user_id, input).await {
+        Ok(t) => t,
+        Err(e) => return errors::from_repo_error("quick_add_todo", e),
+    };
+    let mut warning = None;
+    for tag_id in tag_ids {
+        if let Err(e) = TagRepo::attach(&app, &user_id, todo.id, tag_id).await {
+            logging::log_error(&format!("quick_add_todo: attach tag {}: {}", tag_id, e));
+            warning = Some("The todo was created but not all tags could be attached".to_string());
         }
+    }
+    let result = QuickAddResult {
+        parsed,
+        todo: Some(todo),
+        warning,
     };
-    cors::add_headers(Response::from_json(&todo)?.with_status(201))
+    cors::add_headers(Response::from_json(&result)?.with_status(201))
+}
+
+/// Ids of the caller's tags with these names (ignoring case), creating those that are missing.
+async fn resolve_tags_by_name(
+    app: &AppContext,
+    user_id: &str,
+    names: &[String],
+) -> Result<Vec<i64>> {
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    let existing = TagRepo::list(app, user_id).await?;
+    let mut ids = Vec::with_capacity(names.len());
+    for name in names {
+        let found = existing
+            .iter()
+            .find(|t| t.name.to_lowercase() == name.to_lowercase());
+        let tag_id = match found {
+            Some(tag) => tag.id,
+            None => {
+                let input = CreateTag {
+                    name: name.clone(),
+                    color: None,
+                };
+                TagRepo::create(app, user_id, input).await?.id
+            }
+        };
+        ids.push(tag_id);
+    }
+    Ok(ids)
 }
 
 pub async fn update_todo(
@@ -60,20 +211,28 @@ pub async fn update_todo(
         .parse()
         .map_err(|_| Error::RustError("Invalid id parameter".into()))?;
 
-    let body: UpdateTodo = req.json().await?;
-    match TodoRepo::update(&app, &user_id, id, body.completed).await {
-        Ok(todo) => cors::add_headers(Response::from_json(&todo)?),
-        Err(e) => {
-            let msg = format!("{}", e);
-            if msg.contains("Forbidden") {
-                errors::forbidden()
-            } else if msg.contains("Todo not found") {
-                errors::json_error("Todo not found", 404)
-            } else {
-                logging::log_error(&format!("update_todo: {}", e));
-                errors::json_server_error("Internal server error")
-            }
-        }
+    let body: UpdateTodo = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.is_empty() {
+        return errors::json_error("No fields to update", 400);
+    }
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+    let mut options = match UpdateTodoOptions::from_url(&req.url()?) {
+        Ok(o) => o,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+    options.if_match = match if_match(&req) {
+        Ok(v) => v,
+        Err(msg) => return errors::json_error(&msg, 412),
+    };
+
+    match TodoRepo::update(&app, &user_id, id, body, options).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("update_todo", e),
     }
 }
 
@@ -96,19 +255,357 @@ pub async fn delete_todo(req: Request, ctx: RouteContext<()>, app: AppContext) -
         }
     };
 
-    match TodoRepo::delete(&app, &user_id, id).await {
+    let if_match = match if_match(&req) {
+        Ok(v) => v,
+        Err(msg) => return errors::json_error(&msg, 412),
+    };
+
+    match TodoRepo::delete(&app, &user_id, id, if_match).await {
         Ok(()) => cors::add_headers(Response::ok("deleted")?),
-        Err(e) => {
-            let msg = format!("{}", e);
-            if msg.contains("Forbidden") {
-                errors::forbidden()
-            } else if msg.contains("Todo not found") {
-                errors::json_error("Todo not found", 404)
-            } else {
-                logging::log_error(&format!("delete_todo: {}", e));
-                errors::json_server_error("Internal server error")
-            }
-        }
+        Err(e) => errors::from_repo_error("delete_todo", e),
+    }
+}
+
+pub async fn batch_todos(mut req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    let body: BatchRequest = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match TodoRepo::batch(&app, &user_id, body.operations).await {
+        Ok(results) => cors::add_headers(Response::from_json(
+            &serde_json::json!({ "results": results }),
+        )?),
+        Err(e) => errors::from_repo_error("batch_todos", e),
+    }
+}
+
+pub async fn list_children(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::list_children(&app, &user_id, id).await {
+        Ok(todos) => cors::add_headers(Response::from_json(&todos)?),
+        Err(e) => errors::from_repo_error("list_children", e),
+    }
+}
+
+pub async fn create_child(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: CreateTodo = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate() {
+        return errors::json_error(&msg, 400);
+    }
+
+    match TodoRepo::create_child(&app, &user_id, id, body).await {
+        Ok(todo) => Ok(todo_response(&todo)?.with_status(201)),
+        Err(e) => errors::from_repo_error("create_child", e),
+    }
+}
+
+pub async fn list_trash(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match TodoRepo::list_trash(&app, &user_id).await {
+        Ok(todos) => cors::add_headers(Response::from_json(&todos)?),
+        Err(e) => errors::from_repo_error("list_trash", e),
+    }
+}
+
+pub async fn restore_todo(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::restore(&app, &user_id, id).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("restore_todo", e),
+    }
+}
+
+pub async fn list_archive(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+
+    match TodoRepo::list_archive(&app, &user_id).await {
+        Ok(todos) => cors::add_headers(Response::from_json(&todos)?),
+        Err(e) => errors::from_repo_error("list_archive", e),
+    }
+}
+
+pub async fn unarchive_todo(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::unarchive(&app, &user_id, id).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("unarchive_todo", e),
+    }
+}
+
+pub async fn move_todo(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    let body: MoveTodo = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if let Err(msg) = body.validate(id) {
+        return errors::json_error(&msg, 400);
+    }
+
+    match TodoRepo::move_todo(&app, &user_id, id, body).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("move_todo", e),
+    }
+}
+
+pub async fn list_activity(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match ActivityRepo::list(&app, &user_id, id).await {
+        Ok(activity) => cors::add_headers(Response::from_json(&activity)?),
+        Err(e) => errors::from_repo_error("list_activity", e),
+    }
+}
+
+pub async fn list_series(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::list_series(&app, &user_id, id).await {
+        Ok(todos) => cors::add_headers(Response::from_json(&todos)?),
+        Err(e) => errors::from_repo_error("list_series", e),
+    }
+}
+
+pub async fn update_series(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    let body: UpdateSeries = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    let rule = match Recurrence::parse(&body.rrule) {
+        Ok(r) => r,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+
+    match TodoRepo::update_series(&app, &user_id, id, &rule).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("update_series", e),
+    }
+}
+
+pub async fn stop_series(req: Request, ctx: RouteContext<()>, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::stop_series(&app, &user_id, id).await {
+        Ok(todo) => todo_response(&todo),
+        Err(e) => errors::from_repo_error("stop_series", e),
+    }
+}
+
+pub async fn share_todo(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: ShareTodo = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.user_id.trim().is_empty() {
+        return errors::json_error("user_id is required", 400);
+    }
+
+    match TodoRepo::share(&app, &user_id, id, body.user_id.trim(), body.relation).await {
+        Ok(share) => cors::add_headers(Response::from_json(&share)?.with_status(201)),
+        Err(e) => errors::from_repo_error("share_todo", e),
+    }
+}
+
+pub async fn unshare_todo(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: UnshareTodo = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.user_id.trim().is_empty() {
+        return errors::json_error("user_id is required", 400);
+    }
+
+    match TodoRepo::unshare(&app, &user_id, id, body.user_id.trim(), body.relation).await {
+        Ok(()) => cors::add_headers(Response::ok("unshared")?),
+        Err(e) => errors::from_repo_error("unshare_todo", e),
+    }
+}
+
+pub async fn assign_todo(
+    mut req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+    let body: AssignTodo = match req.json().await {
+        Ok(v) => v,
+        Err(_) => return errors::json_error("Invalid JSON", 400),
+    };
+    if body.user_id.trim().is_empty() {
+        return errors::json_error("user_id is required", 400);
+    }
+
+    match TodoRepo::assign(&app, &user_id, id, body.user_id.trim()).await {
+        Ok(assignee) => cors::add_headers(Response::from_json(&assignee)?),
+        Err(e) => errors::from_repo_error("assign_todo", e),
+    }
+}
+
+pub async fn unassign_todo(
+    req: Request,
+    ctx: RouteContext<()>,
+    app: AppContext,
+) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let id = match todo_id(&ctx) {
+        Some(id) => id,
+        None => return errors::json_error("Invalid id parameter", 400),
+    };
+
+    match TodoRepo::unassign(&app, &user_id, id).await {
+        Ok(()) => cors::add_headers(Response::ok("unassigned")?),
+        Err(e) => errors::from_repo_error("unassign_todo", e),
     }
 }
 
@@ -141,26 +638,8 @@ pub async fn admin_list_todos(req: Request, app: AppContext) -> Result<Response>
             let email = if let Some(cached) = email_cache.get(&owner_id) {
                 cached.clone()
             } else {
-                let email = match kratos.get_identity(&owner_id).await {
-                    Ok(json) => {
-                        let traits_email = json
-                            .get("traits")
-                            .and_then(|t| t.get("email"))
-                            .and_then(|e| e.as_str())
-                            .map(|s| s.to_string());
-                        if traits_email.is_some() {
-                            traits_email
-                        } else {
-                            json.get("verifiable_addresses")
-                                .and_then(|v| v.as_array())
-                                .and_then(|arr| {
-                                    arr.iter()
-                                        .filter_map(|it| it.get("value").and_then(|v| v.as_str()))
-                                        .next()
-                                })
-                                .map(|s| s.to_string())
-                        }
-                    }
+                let email = match kratos.get_email(&owner_id).await {
+                    Ok(email) => email,
                     Err(e) => {
                         logging::log_error(&format!("kratos identity: {}", e));
                         None
@@ -205,7 +684,7 @@ pub async fn admin_delete_todo(
         }
     };
 
-    match TodoRepo::delete_any(&app, id).await {
+    match TodoRepo::delete_any(&app, &user_id, id).await {
         Ok(()) => cors::add_headers(Response::ok("deleted")?),
         Err(e) => {
             logging::log_error(&format!("admin_delete_todo: {}", e));
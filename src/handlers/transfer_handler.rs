@@ -0,0 +1,53 @@
+use crate::middleware::{auth, cors};
+use crate::models::{TodoRecord, TransferFormat};
+use crate::repositories::TransferRepo;
+use crate::utils::{context::AppContext, errors};
+use worker::*;
+
+/// `GET /api/todos/export?format=json|csv`: every todo the caller owns, as a download.
+pub async fn export_todos(req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let format = match TransferFormat::from_url(&req.url()?) {
+        Ok(f) => f,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+
+    match TransferRepo::export(&app, &user_id, format).await {
+        Ok(body) => {
+            let headers = Headers::new();
+            headers.set("Content-Type", format.content_type())?;
+            headers.set(
+                "Content-Disposition",
+                &format!("attachment; filename=\"todos.{}\"", format.extension()),
+            )?;
+            cors::add_headers(Response::from_stream(body)?.with_headers(headers))
+        }
+        Err(e) => errors::from_repo_error("export_todos", e),
+    }
+}
+
+/// `POST /api/todos/import?format=json|csv` with an export file as body. Responds with a report
+/// of what happened to each row.
+pub async fn import_todos(mut req: Request, app: AppContext) -> Result<Response> {
+    let user_id = match auth::get_user_id(&req) {
+        Some(u) => u,
+        None => return errors::json_error("Missing X-User-Id", 401),
+    };
+    let format = match TransferFormat::from_url(&req.url()?) {
+        Ok(f) => f,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+    let body = req.bytes().await?;
+    let rows = match TodoRecord::parse_file(format, &body) {
+        Ok(rows) => rows,
+        Err(msg) => return errors::json_error(&msg, 400),
+    };
+
+    match TransferRepo::import(&app, &user_id, rows).await {
+        Ok(report) => cors::add_headers(Response::from_json(&report)?),
+        Err(e) => errors::from_repo_error("import_todos", e),
+    }
+}
@@ -0,0 +1,58 @@
+use crate::middleware::logging;
+use crate::repositories::{SettingsRepo, TodoRepo};
+use crate::utils::context::AppContext;
+use futures::future::join_all;
+use std::collections::HashMap;
+
+/// Completed todos scheduled per batch; owners are looked up in Keto one todo at a time.
+const SCHEDULE_BATCH_SIZE: u32 = 200;
+
+/// Batches per run, so a large backlog is spread over several days instead of one long run.
+const MAX_BATCHES: usize = 10;
+
+/// Resolve when each newly completed todo is archived, from its owner's `archive_after_days`.
+/// Returns how many todos were scheduled.
+async fn schedule_batch(ctx: &AppContext) -> worker::Result<usize> {
+    let ids = TodoRepo::unscheduled_archives(ctx, SCHEDULE_BATCH_SIZE).await?;
+    let owners = join_all(ids.iter().map(|&id| TodoRepo::owner_ids(ctx, id))).await;
+
+    let mut by_owner: HashMap<Option<String>, Vec<i64>> = HashMap::new();
+    for (&id, owner) in ids.iter().zip(owners) {
+        match owner {
+            Ok(owners) => by_owner
+                .entry(owners.into_iter().next())
+                .or_default()
+                .push(id),
+            Err(e) => logging::log_error(&format!("archive: owner of todo {}: {}", id, e)),
+        }
+    }
+
+    let users = by_owner.keys().flatten().cloned().collect::<Vec<_>>();
+    let days = SettingsRepo::archive_after_days_for(ctx, &users).await?;
+    let mut scheduled = 0;
+    for (owner, ids) in by_owner {
+        // A todo without an owner is not visible to anyone; it is never archived.
+        let after = owner.and_then(|o| days.get(&o).copied().flatten());
+        TodoRepo::schedule_archive(ctx, &ids, after).await?;
+        scheduled += ids.len();
+    }
+    Ok(scheduled)
+}
+
+/// Schedule newly completed todos, then archive those whose time has come.
+pub async fn archive_completed(ctx: &AppContext) {
+    for _ in 0..MAX_BATCHES {
+        match schedule_batch(ctx).await {
+            Ok(n) if n < SCHEDULE_BATCH_SIZE as usize => break,
+            Ok(_) => {}
+            Err(e) => {
+                logging::log_error(&format!("archive: schedule: {}", e));
+                break;
+            }
+        }
+    }
+    match TodoRepo::archive_due(ctx).await {
+        Ok(n) => logging::log_info(&format!("archive: {} todos archived", n)),
+        Err(e) => logging::log_error(&format!("archive: {}", e)),
+    }
+}
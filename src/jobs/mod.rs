@@ -0,0 +1,27 @@
+//! Scheduled (cron) jobs. Each cron expression listed under `[triggers]` in `wrangler.toml`
+//! maps to the jobs it runs here.
+
+pub mod archive;
+pub mod positions;
+pub mod reminders;
+pub mod trash;
+
+use crate::middleware::logging;
+use crate::utils::context::AppContext;
+
+/// Once a day at 03:00 UTC.
+pub const DAILY: &str = "0 3 * * *";
+/// Every five minutes; bounds how late a reminder can arrive.
+pub const EVERY_FIVE_MINUTES: &str = "*/5 * * * *";
+
+pub async fn run(cron: &str, ctx: &AppContext) {
+    match cron {
+        DAILY => {
+            trash::purge(ctx).await;
+            archive::archive_completed(ctx).await;
+            positions::rebalance(ctx).await;
+        }
+        EVERY_FIVE_MINUTES => reminders::send_due(ctx).await,
+        other => logging::log_error(&format!("scheduled: no jobs for cron '{}'", other)),
+    }
+}
@@ -0,0 +1,16 @@
+use crate::middleware::logging;
+use crate::repositories::TodoRepo;
+use crate::utils::context::AppContext;
+
+/// Longest position key tolerated before the todo gets a shorter one. Keys grow by about one
+/// character every six moves into the same gap.
+const MAX_KEY_LEN: usize = 32;
+
+/// Shorten the position keys that moves have made too long.
+pub async fn rebalance(ctx: &AppContext) {
+    match TodoRepo::rebalance_positions(ctx, MAX_KEY_LEN).await {
+        Ok(Some(n)) => logging::log_info(&format!("rebalance positions: {} todos rewritten", n)),
+        Ok(None) => {}
+        Err(e) => logging::log_error(&format!("rebalance positions: {}", e)),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::db::{KratosClient, Mail, MailClient};
+use crate::middleware::logging;
+use crate::models::Todo;
+use crate::repositories::TodoRepo;
+use crate::utils::{context::AppContext, time};
+
+/// Max reminders sent per run; the rest go out on the next run.
+const BATCH_SIZE: u32 = 100;
+
+/// Email the owner of every todo whose reminder is due. Each reminder is claimed before it is sent
+/// and released again if sending fails. Every delivery is recorded, so the retry only emails the
+/// owners that did not get it yet.
+pub async fn send_due(ctx: &AppContext) {
+    let mail = match MailClient::from_env(ctx) {
+        Ok(m) => m,
+        Err(e) => {
+            logging::log_error(&format!("reminders: mail not configured: {}", e));
+            return;
+        }
+    };
+    let kratos = match KratosClient::from_env(ctx) {
+        Ok(k) => k,
+        Err(e) => {
+            logging::log_error(&format!("reminders: {}", e));
+            return;
+        }
+    };
+
+    let now = time::now_rfc3339();
+    let todos = match TodoRepo::due_reminders(ctx, &now, BATCH_SIZE).await {
+        Ok(t) => t,
+        Err(e) => {
+            logging::log_error(&format!("reminders: {}", e));
+            return;
+        }
+    };
+
+    let mut sent = 0;
+    for todo in &todos {
+        match TodoRepo::claim_reminder(ctx, todo.id, &now).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                logging::log_error(&format!("reminders: claim todo {}: {}", todo.id, e));
+                continue;
+            }
+        }
+        match send_one(ctx, &mail, &kratos, todo).await {
+            Ok(n) => sent += n,
+            Err(e) => {
+                logging::log_error(&format!("reminders: todo {}: {}", todo.id, e));
+                if let Err(e) = TodoRepo::release_reminder(ctx, todo.id).await {
+                    logging::log_error(&format!("reminders: release todo {}: {}", todo.id, e));
+                }
+            }
+        }
+    }
+    logging::log_info(&format!("reminders: {} emails sent", sent));
+}
+
+/// Send the reminder to each owner that has an email address and has not received it yet.
+/// Returns how many were sent.
+async fn send_one(
+    ctx: &AppContext,
+    mail: &MailClient,
+    kratos: &KratosClient,
+    todo: &Todo,
+) -> worker::Result<usize> {
+    let Some(remind_at) = todo.remind_at.as_deref() else {
+        return Ok(0);
+    };
+    let delivered = TodoRepo::reminder_recipients(ctx, todo.id, remind_at).await?;
+    let mut sent = 0;
+    for owner_id in TodoRepo::owner_ids(ctx, todo.id).await? {
+        if delivered.contains(&owner_id) {
+            continue;
+        }
+        let Some(email) = kratos.get_email(&owner_id).await? else {
+            continue;
+        };
+        mail.send(&reminder_mail(email, todo)).await?;
+        sent += 1;
+        // The email is out; failing to record it only risks a duplicate if a later owner fails.
+        if let Err(e) =
+            TodoRepo::record_reminder_recipient(ctx, todo.id, remind_at, &owner_id).await
+        {
+            logging::log_error(&format!("reminders: record todo {}: {}", todo.id, e));
+        }
+    }
+    Ok(sent)
+}
+
+fn reminder_mail(to: String, todo: &Todo) -> Mail {
+    let mut text = format!("Reminder: {}\n", todo.title);
+    if let Some(due) = &todo.due_date {
+        text.push_str(&format!("Due: {}\n", due));
+    }
+    if let Some(description) = &todo.description {
+        text.push_str(&format!("\n{}\n", description));
+    }
+    Mail {
+        to,
+        subject: format!("Reminder: {}", todo.title),
+        text,
+    }
+}
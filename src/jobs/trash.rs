@@ -0,0 +1,24 @@
+use crate::middleware::logging;
+use crate::repositories::TodoRepo;
+use crate::utils::{context::AppContext, time};
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Days a trashed todo is kept before it is purged. Read from `TRASH_RETENTION_DAYS`.
+fn retention_days(ctx: &AppContext) -> i64 {
+    ctx.env
+        .var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.to_string().parse().ok())
+        .filter(|d: &i64| *d >= 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// Permanently delete todos that have been in the trash longer than the retention period.
+pub async fn purge(ctx: &AppContext) {
+    let cutoff = time::days_ago_rfc3339(retention_days(ctx));
+    match TodoRepo::purge_deleted(ctx, &cutoff).await {
+        Ok(n) => logging::log_info(&format!("purge trash: {} todos removed", n)),
+        Err(e) => logging::log_error(&format!("purge trash: {}", e)),
+    }
+}
@@ -4,6 +4,7 @@ mod repositories;
 mod handlers;
 mod middleware;
 mod utils;
+mod jobs;
 
 use crate::middleware::cors;
 use worker::*;
@@ -15,18 +16,25 @@ async fn fetch(
     env: Env,
     _ctx: Context,
 ) -> Result<Response> {
-    crate::middleware::logging::log_request(&req);
+    let app_ctx = AppContext::new(env.clone());
+    crate::middleware::logging::log_request(&app_ctx, &req);
 
     // Handle CORS preflight (OPTIONS) requests
     if req.method() == Method::Options {
         return cors::handle_preflight();
     }
 
-    let app_ctx = AppContext::new(env.clone());
     let env_for_router = env;
 
-    Router::new()
+    let response = Router::new()
         .get("/health", handlers::health::health_check)
+        .get_async("/ical/:file", {
+            let app_ctx = app_ctx.clone();
+            move |_, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::calendar_handler::feed(ctx, app_ctx).await }
+            }
+        })
         .get_async("/users", {
             let app_ctx = app_ctx.clone();
             move |req, _| {
@@ -55,6 +63,97 @@ async fn fetch(
                 async move { handlers::todo_handler::create_todo(req, app_ctx).await }
             }
         })
+        .post_async("/api/todos/batch", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::batch_todos(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/settings", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::settings_handler::get_settings(req, app_ctx).await }
+            }
+        })
+        .patch_async("/api/settings", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::settings_handler::update_settings(req, app_ctx).await }
+            }
+        })
+        .post_async("/api/calendar/feed", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::calendar_handler::rotate_feed(req, app_ctx).await }
+            }
+        })
+        .delete_async("/api/calendar/feed", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::calendar_handler::revoke_feed(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/export", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::transfer_handler::export_todos(req, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/import", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::transfer_handler::import_todos(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/stats", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::todo_stats(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/time-report", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::time_handler::time_report(req, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/quick", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::quick_add_todo(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/archive", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::list_archive(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/trash", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::list_trash(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::get_todo(req, ctx, app_ctx).await }
+            }
+        })
         .patch_async("/api/todos/:id", {
             let app_ctx = app_ctx.clone();
             move |req, ctx| {
@@ -69,6 +168,314 @@ async fn fetch(
                 async move { handlers::todo_handler::delete_todo(req, ctx, app_ctx).await }
             }
         })
+        .get_async("/api/todos/:id/children", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::list_children(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/children", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::create_child(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/move", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::move_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/unarchive", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::unarchive_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/restore", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::restore_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/activity", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::list_activity(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/series", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::list_series(req, ctx, app_ctx).await }
+            }
+        })
+        .patch_async("/api/todos/:id/series", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::update_series(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/series", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::stop_series(req, ctx, app_ctx).await }
+            }
+        })
+        .put_async("/api/todos/:id/assignee", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::assign_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/assignee", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::unassign_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/shares", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::share_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/shares", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::todo_handler::unshare_todo(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/comments", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::comment_handler::list_comments(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/comments", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::comment_handler::create_comment(req, ctx, app_ctx).await }
+            }
+        })
+        .patch_async("/api/todos/:id/comments/:comment_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::comment_handler::update_comment(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/comments/:comment_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::comment_handler::delete_comment(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/attachments", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::attachment_handler::list_attachments(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/attachments", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::attachment_handler::upload_attachment(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/attachments/:attachment_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::attachment_handler::download_attachment(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/attachments/:attachment_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::attachment_handler::delete_attachment(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/dependencies", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::dependency_handler::dependency_graph(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/dependencies", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::dependency_handler::add_dependency(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/dependencies/:blocker_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::dependency_handler::remove_dependency(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/timer/start", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::time_handler::start_timer(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/timer/stop", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::time_handler::stop_timer(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/time-entries", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::time_handler::list_time_entries(req, ctx, app_ctx).await }
+            }
+        })
+        .patch_async("/api/todos/:id/time-entries/:entry_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::time_handler::update_time_entry(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/time-entries/:entry_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::time_handler::delete_time_entry(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/todos/:id/tags", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::list_todo_tags(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/todos/:id/tags", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::attach_tag(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/todos/:id/tags/:tag_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::detach_tag(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/tags", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::list_tags(req, app_ctx).await }
+            }
+        })
+        .post_async("/api/tags", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::create_tag(req, app_ctx).await }
+            }
+        })
+        .patch_async("/api/tags/:id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::update_tag(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/tags/:id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::tag_handler::delete_tag(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/lists", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::list_lists(req, app_ctx).await }
+            }
+        })
+        .post_async("/api/lists", {
+            let app_ctx = app_ctx.clone();
+            move |req, _| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::create_list(req, app_ctx).await }
+            }
+        })
+        .get_async("/api/lists/:id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::get_list(req, ctx, app_ctx).await }
+            }
+        })
+        .patch_async("/api/lists/:id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::update_list(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/lists/:id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::delete_list(req, ctx, app_ctx).await }
+            }
+        })
+        .get_async("/api/lists/:id/members", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::list_members(req, ctx, app_ctx).await }
+            }
+        })
+        .post_async("/api/lists/:id/members", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::add_member(req, ctx, app_ctx).await }
+            }
+        })
+        .delete_async("/api/lists/:id/members/:user_id", {
+            let app_ctx = app_ctx.clone();
+            move |req, ctx| {
+                let app_ctx = app_ctx.clone();
+                async move { handlers::list_handler::remove_member(req, ctx, app_ctx).await }
+            }
+        })
         .get_async("/api/admin/todos", {
             let app_ctx = app_ctx.clone();
             move |req, _| {
@@ -84,5 +491,13 @@ async fn fetch(
             }
         })
         .run(req, env_for_router)
-        .await
+        .await;
+    crate::middleware::logging::log_response(&app_ctx, &response);
+    response
+}
+
+#[event(scheduled)]
+async fn scheduled(event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    let app_ctx = AppContext::new(env);
+    jobs::run(&event.cron(), &app_ctx).await;
 }
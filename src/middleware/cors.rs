@@ -4,8 +4,9 @@ pub fn get_headers() -> Result<Headers> {
     let headers = Headers::new();
     headers.set("Access-Control-Allow-Origin", "http://localhost:5173")?;
     headers.set("Access-Control-Allow-Credentials", "true")?;
-    headers.set("Access-Control-Allow-Methods", "GET, POST, PATCH, DELETE, OPTIONS")?;
-    headers.set("Access-Control-Allow-Headers", "X-User-Id,Content-Type, Authorization")?;
+    headers.set("Access-Control-Allow-Methods", "GET, POST, PUT, PATCH, DELETE, OPTIONS")?;
+    headers.set("Access-Control-Allow-Headers", "X-User-Id,Content-Type, Authorization, If-Match")?;
+    headers.set("Access-Control-Expose-Headers", "ETag")?;
     headers.set("Access-Control-Max-Age", "86400")?; // 24 hours
     Ok(headers)
 }
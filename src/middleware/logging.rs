@@ -1,9 +1,24 @@
+use crate::utils::context::AppContext;
 use worker::*;
 
-pub fn log_request(req: &Request) {
-    console_log!("{} {}", req.method(), req.path());
+pub fn log_request(app: &AppContext, req: &Request) {
+    console_log!("[{}] {} {}", app.request_id, req.method(), req.path());
+}
+
+/// Status and duration of the request `app` was created for.
+pub fn log_response(app: &AppContext, response: &Result<Response>) {
+    let status = match response {
+        Ok(resp) => resp.status_code().to_string(),
+        Err(e) => format!("error: {}", e),
+    };
+    let elapsed = Date::now().as_millis().saturating_sub(app.start_time);
+    console_log!("[{}] {} in {} ms", app.request_id, status, elapsed);
 }
 
 pub fn log_error(msg: &str) {
     console_log!("[ERROR] {}", msg);
 }
+
+pub fn log_info(msg: &str) {
+    console_log!("[INFO] {}", msg);
+}
@@ -0,0 +1,91 @@
+use crate::models::Todo;
+use serde::{Deserialize, Serialize};
+
+/// What happened to a todo.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    Created,
+    Updated,
+    Moved,
+    Deleted,
+    Restored,
+    Purged,
+    AdminDeleted,
+    Shared,
+    Unshared,
+    Assigned,
+    Unassigned,
+    Archived,
+    Unarchived,
+    BlockerAdded,
+    BlockerRemoved,
+}
+
+/// One entry of `GET /api/todos/:id/activity`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Activity {
+    pub id: i64,
+    pub todo_id: i64,
+    /// Kratos identity id of the user who made the change; `None` for scheduled jobs.
+    pub actor_id: Option<String>,
+    pub action: ActivityAction,
+    /// Changed fields, each as `{ "from": old, "to": new }`.
+    pub changes: serde_json::Map<String, serde_json::Value>,
+    pub created_at: String,
+}
+
+/// An activity row to insert.
+#[derive(Serialize)]
+pub struct NewActivity {
+    pub todo_id: i64,
+    pub actor_id: Option<String>,
+    pub action: ActivityAction,
+    pub changes: serde_json::Map<String, serde_json::Value>,
+}
+
+impl NewActivity {
+    pub fn new(todo_id: i64, actor_id: Option<&str>, action: ActivityAction) -> Self {
+        NewActivity {
+            todo_id,
+            actor_id: actor_id.map(str::to_string),
+            action,
+            changes: serde_json::Map::new(),
+        }
+    }
+
+    /// Record `field` going from `from` to `to`.
+    pub fn change(mut self, field: &str, from: impl Serialize, to: impl Serialize) -> Self {
+        self.changes.insert(
+            field.to_string(),
+            serde_json::json!({ "from": from, "to": to }),
+        );
+        self
+    }
+
+    /// Record every field that differs between two versions of a todo. `None` stands for a todo
+    /// that does not exist yet (or any more), so every set field counts as changed.
+    pub fn diff(mut self, before: Option<&Todo>, after: Option<&Todo>) -> Self {
+        let fields = |todo: Option<&Todo>| match todo.map(serde_json::to_value) {
+            Some(Ok(serde_json::Value::Object(map))) => map,
+            _ => serde_json::Map::new(),
+        };
+        let before = fields(before);
+        let after = fields(after);
+        let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            if matches!(key.as_str(), "id" | "created_at" | "version") {
+                continue;
+            }
+            let from = before.get(key).unwrap_or(&serde_json::Value::Null);
+            let to = after.get(key).unwrap_or(&serde_json::Value::Null);
+            if from != to {
+                self.changes
+                    .insert(key.clone(), serde_json::json!({ "from": from, "to": to }));
+            }
+        }
+        self
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+const MAX_FILE_NAME_LEN: usize = 255;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: i64,
+    pub todo_id: i64,
+    pub file_name: String,
+    pub content_type: String,
+    /// Size in bytes.
+    pub size: i64,
+    /// Hex SHA-256 of the content.
+    pub checksum: String,
+    /// Kratos identity id of the uploader; uploads count towards their quota.
+    pub uploaded_by: String,
+    pub created_at: String,
+    /// R2 object key; internal.
+    #[serde(skip_serializing)]
+    pub object_key: String,
+}
+
+/// Checks the `?name=` of an upload. The name ends up in a `Content-Disposition` header, so path
+/// separators, quotes and control characters are rejected. The error is a message suitable for a
+/// 400 response.
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("name is required".into());
+    }
+    if name.chars().count() > MAX_FILE_NAME_LEN {
+        return Err(format!(
+            "name must be at most {} characters",
+            MAX_FILE_NAME_LEN
+        ));
+    }
+    if name
+        .chars()
+        .any(|c| c.is_control() || matches!(c, '/' | '\\' | '"'))
+    {
+        return Err("name must not contain /, \\, \" or control characters".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use crate::models::{Priority, Todo};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Right-hand side of every VTODO `UID`, so ids stay unique across calendars.
+const UID_DOMAIN: &str = "todo-backend";
+
+/// RFC 5545 limit on a content line, in octets (without the CRLF).
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Response of `POST /api/calendar/feed`. The URL holds the only copy of the token.
+#[derive(Serialize)]
+pub struct CalendarFeed {
+    pub url: String,
+}
+
+/// Escapes a TEXT value (RFC 5545 3.3.11).
+fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Appends a content line, folded at 75 octets without splitting a UTF-8 character.
+fn push_line(out: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space of a continuation counts towards its length.
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn format_utc(t: DateTime<Utc>) -> String {
+    t.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// `NAME:value` in UTC for an RFC 3339 timestamp; `None` if the value is not one.
+fn date_property(name: &str, value: &str) -> Option<String> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| format!("{}:{}", name, format_utc(t.with_timezone(&Utc))))
+}
+
+/// iCalendar PRIORITY: 1 is highest, 9 lowest.
+fn ical_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::High => 1,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+/// A VCALENDAR with one VTODO per todo. `now` is used as `DTSTAMP`.
+pub fn to_ical(todos: &[Todo], now: DateTime<Utc>) -> String {
+    let stamp = format_utc(now);
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//todo-backend//Todos//EN",
+        "CALSCALE:GREGORIAN",
+        "X-WR-CALNAME:Todos",
+    ] {
+        push_line(&mut out, line);
+    }
+
+    for todo in todos {
+        push_line(&mut out, "BEGIN:VTODO");
+        push_line(&mut out, &format!("UID:todo-{}@{}", todo.id, UID_DOMAIN));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        if let Some(created) = date_property("CREATED", &todo.created_at) {
+            push_line(&mut out, &created);
+        }
+        push_line(&mut out, &format!("SEQUENCE:{}", (todo.version - 1).max(0)));
+        push_line(&mut out, &format!("SUMMARY:{}", escape_text(&todo.title)));
+        if let Some(description) = &todo.description {
+            push_line(
+                &mut out,
+                &format!("DESCRIPTION:{}", escape_text(description)),
+            );
+        }
+        if let Some(due) = todo
+            .due_date
+            .as_deref()
+            .and_then(|d| date_property("DUE", d))
+        {
+            push_line(&mut out, &due);
+        }
+        if let Some(priority) = todo.priority {
+            push_line(&mut out, &format!("PRIORITY:{}", ical_priority(priority)));
+        }
+        if todo.completed {
+            push_line(&mut out, "STATUS:COMPLETED");
+            push_line(&mut out, "PERCENT-COMPLETE:100");
+        } else {
+            push_line(&mut out, "STATUS:NEEDS-ACTION");
+        }
+        if let Some(parent_id) = todo.parent_id {
+            push_line(
+                &mut out,
+                &format!("RELATED-TO:todo-{}@{}", parent_id, UID_DOMAIN),
+            );
+        }
+        push_line(&mut out, "END:VTODO");
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Content lines of `out` with the folding undone, checking each physical line's length.
+    fn unfold(out: &str) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for physical in out.strip_suffix("\r\n").unwrap().split("\r\n") {
+            assert!(
+                physical.len() <= MAX_LINE_OCTETS,
+                "{} octets: {:?}",
+                physical.len(),
+                physical
+            );
+            match physical.strip_prefix(' ') {
+                Some(rest) => lines.last_mut().unwrap().push_str(rest),
+                None => lines.push(physical.to_string()),
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn escapes_text() {
+        let cases = [
+            ("plain", "plain"),
+            ("a, b; c", "a\\, b\\; c"),
+            ("C:\\temp", "C:\\\\temp"),
+            ("one\ntwo", "one\\ntwo"),
+            ("one\r\ntwo", "one\\ntwo"),
+            ("\\n is not a newline", "\\\\n is not a newline"),
+            ("café: ok", "café: ok"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(escape_text(input), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        let mut out = String::new();
+        let line = format!("SUMMARY:{}", "x".repeat(MAX_LINE_OCTETS - 8));
+        push_line(&mut out, &line);
+        assert_eq!(out, format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn folds_ascii_at_75_octets() {
+        let mut out = String::new();
+        let line = format!("SUMMARY:{}", "x".repeat(200));
+        push_line(&mut out, &line);
+        let physical: Vec<&str> = out.trim_end().split("\r\n").collect();
+        assert_eq!(physical[0].len(), MAX_LINE_OCTETS);
+        assert_eq!(physical[1].len(), MAX_LINE_OCTETS);
+        assert!(physical[1].starts_with(' '));
+        assert_eq!(unfold(&out), vec![line]);
+    }
+
+    #[test]
+    fn folds_multibyte_text_between_characters() {
+        // 2-, 3- and 4-octet characters, so folds land in the middle of where a byte count would.
+        for text in ["é".repeat(100), "日本語".repeat(40), "🦀".repeat(50)] {
+            let mut out = String::new();
+            let line = format!("SUMMARY:{}", text);
+            push_line(&mut out, &line);
+            // Each physical line stays within 75 octets, and unfolding restores every character.
+            assert!(out.contains("\r\n "));
+            assert_eq!(unfold(&out), vec![line]);
+        }
+    }
+}
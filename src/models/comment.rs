@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+const MAX_COMMENT_LEN: usize = 5_000;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: i64,
+    pub todo_id: i64,
+    /// Kratos identity id of the user who wrote the comment.
+    pub author_id: String,
+    pub body: String,
+    pub created_at: String,
+    /// Set when the comment was edited.
+    pub updated_at: Option<String>,
+}
+
+/// Body of `POST /api/todos/:id/comments` and `PATCH /api/todos/:id/comments/:comment_id`.
+#[derive(Deserialize)]
+pub struct CommentBody {
+    pub body: String,
+}
+
+impl CommentBody {
+    /// The error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        let body = self.body.trim();
+        if body.is_empty() {
+            return Err("Body is required".into());
+        }
+        if body.chars().count() > MAX_COMMENT_LEN {
+            return Err(format!(
+                "Body must be at most {} characters",
+                MAX_COMMENT_LEN
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use crate::models::Todo;
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /api/todos/:id/dependencies`.
+#[derive(Deserialize)]
+pub struct AddDependency {
+    /// The todo that has to be completed first.
+    pub blocker_id: i64,
+}
+
+/// `todo_id` is blocked by `blocker_id`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct DependencyEdge {
+    pub todo_id: i64,
+    pub blocker_id: i64,
+}
+
+/// Response of `GET /api/todos/:id/dependencies`: everything the todo transitively waits on or
+/// holds up, limited to the todos the caller can read.
+#[derive(Serialize)]
+pub struct DependencyGraph {
+    pub todo_id: i64,
+    /// Whether any direct blocker is still open, counting ones the caller cannot read.
+    pub blocked: bool,
+    pub nodes: Vec<Todo>,
+    pub edges: Vec<DependencyEdge>,
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+const MAX_LIST_NAME_LEN: usize = 100;
+
+/// A named list (project) of todos. Access lives in the `lists` Keto namespace.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TodoList {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateList {
+    pub name: String,
+}
+
+impl CreateList {
+    /// Checks every field; the error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UpdateList {
+    pub name: String,
+}
+
+impl UpdateList {
+    /// Checks every field; the error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ListRole {
+    Owner,
+    Member,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ListMember {
+    pub user_id: String,
+    pub role: ListRole,
+}
+
+#[derive(Deserialize)]
+pub struct AddListMember {
+    pub user_id: String,
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Name is required".into());
+    }
+    if name.chars().count() > MAX_LIST_NAME_LEN {
+        return Err(format!(
+            "Name must be at most {} characters",
+            MAX_LIST_NAME_LEN
+        ));
+    }
+    Ok(())
+}
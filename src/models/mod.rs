@@ -1,5 +1,31 @@
 pub mod user;
 pub mod todo;
+pub mod tag;
+pub mod list;
+pub mod recurrence;
+pub mod comment;
+pub mod attachment;
+pub mod activity;
+pub mod transfer;
+pub mod calendar;
+pub mod settings;
+pub mod stats;
+pub mod dependency;
+pub mod time_entry;
+pub mod quick_add;
 
 pub use user::*;
 pub use todo::*;
+pub use tag::*;
+pub use list::*;
+pub use recurrence::*;
+pub use comment::*;
+pub use attachment::*;
+pub use activity::*;
+pub use transfer::*;
+pub use calendar::*;
+pub use settings::*;
+pub use stats::*;
+pub use dependency::*;
+pub use time_entry::*;
+pub use quick_add::*;
@@ -0,0 +1,975 @@
+use crate::models::{CreateTodo, Priority, Recurrence, Todo};
+use crate::utils::time;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveTime, SecondsFormat, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const MAX_TEXT_LEN: usize = 500;
+/// Largest `N` in `in N days` or `every N weeks`.
+const MAX_AMOUNT: u32 = 1000;
+/// Days searched for the first occurrence of a rule like `every 31st`.
+const MAX_OCCURRENCE_SCAN: u64 = 400;
+
+/// Body of `POST /api/todos/quick`.
+#[derive(Deserialize)]
+pub struct QuickAddRequest {
+    pub text: String,
+    /// Create the todo (and any missing tags) instead of only returning the parse.
+    #[serde(default)]
+    pub save: bool,
+}
+
+impl QuickAddRequest {
+    /// The error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.text.trim().is_empty() {
+            return Err("Text is required".into());
+        }
+        if self.text.chars().count() > MAX_TEXT_LEN {
+            return Err(format!("Text must be at most {} characters", MAX_TEXT_LEN));
+        }
+        Ok(())
+    }
+}
+
+/// What quick-add read from a line of text. `due_date` is an RFC 3339 timestamp in the user's time
+/// zone, at midnight when only a date was given; `rrule` is in the form `Recurrence` stores.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct QuickAdd {
+    pub title: String,
+    pub due_date: Option<String>,
+    pub priority: Option<Priority>,
+    /// Tag names without `#`, in the order typed, without duplicates (ignoring case).
+    pub tags: Vec<String>,
+    pub rrule: Option<String>,
+}
+
+/// Response of `POST /api/todos/quick`. `todo` is set when the request asked to save; `warning`
+/// when it was saved but not all of its tags could be attached.
+#[derive(Serialize)]
+pub struct QuickAddResult {
+    pub parsed: QuickAdd,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo: Option<Todo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// One word of the input, or a quoted phrase that is always part of the title.
+struct Token {
+    text: String,
+    /// Lowercase, without trailing punctuation; what keywords are matched against.
+    word: String,
+    quoted: bool,
+}
+
+impl Token {
+    fn ends_list_item(&self) -> bool {
+        self.text.ends_with(',')
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (i, part) in text.split('"').enumerate() {
+        // Odd parts were inside quotes; an unclosed quote runs to the end.
+        if i % 2 == 1 {
+            if !part.trim().is_empty() {
+                tokens.push(Token {
+                    text: part.trim().to_string(),
+                    word: String::new(),
+                    quoted: true,
+                });
+            }
+            continue;
+        }
+        for text in part.split_whitespace() {
+            tokens.push(Token {
+                text: text.to_string(),
+                word: text.trim_end_matches([',', '.', ';']).to_lowercase(),
+                quoted: false,
+            });
+        }
+    }
+    tokens
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+fn unit(word: &str) -> Option<Unit> {
+    match word {
+        "day" | "days" => Some(Unit::Day),
+        "week" | "weeks" => Some(Unit::Week),
+        "month" | "months" => Some(Unit::Month),
+        "year" | "years" => Some(Unit::Year),
+        _ => None,
+    }
+}
+
+/// Full weekday names (also plural, as in `every mondays`); the short forms only when `short` is
+/// allowed, since words like `sun` or `wed` are common in titles.
+fn weekday(word: &str, short: bool) -> Option<Weekday> {
+    let full = match word.trim_end_matches('s') {
+        "monday" => Some(Weekday::Mon),
+        "tuesday" => Some(Weekday::Tue),
+        "wednesday" => Some(Weekday::Wed),
+        "thursday" => Some(Weekday::Thu),
+        "friday" => Some(Weekday::Fri),
+        "saturday" => Some(Weekday::Sat),
+        "sunday" => Some(Weekday::Sun),
+        _ => None,
+    };
+    if full.is_some() || !short {
+        return full;
+    }
+    match word {
+        "mon" => Some(Weekday::Mon),
+        "tue" | "tues" => Some(Weekday::Tue),
+        "wed" => Some(Weekday::Wed),
+        "thu" | "thur" | "thurs" => Some(Weekday::Thu),
+        "fri" => Some(Weekday::Fri),
+        "sat" => Some(Weekday::Sat),
+        "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn month(word: &str) -> Option<u32> {
+    let month = match word {
+        "january" | "jan" => 1,
+        "february" | "feb" => 2,
+        "march" | "mar" => 3,
+        "april" | "apr" => 4,
+        "may" => 5,
+        "june" | "jun" => 6,
+        "july" | "jul" => 7,
+        "august" | "aug" => 8,
+        "september" | "sep" | "sept" => 9,
+        "october" | "oct" => 10,
+        "november" | "nov" => 11,
+        "december" | "dec" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+/// `5`, `5th`, `21st`: a day of the month. With `ordinal`, the suffix is required.
+fn day_of_month(word: &str, ordinal: bool) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "st" | "nd" | "rd" | "th") && (ordinal || !suffix.is_empty()) {
+        return None;
+    }
+    let day = amount(digits)?;
+    (1..=31).contains(&day).then_some(day)
+}
+
+/// A positive whole number up to `MAX_AMOUNT`; `a`/`an` count as one.
+fn amount(word: &str) -> Option<u32> {
+    if matches!(word, "a" | "an") {
+        return Some(1);
+    }
+    if word.is_empty() || word.len() > 4 || !word.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    word.parse().ok().filter(|n| (1..=MAX_AMOUNT).contains(n))
+}
+
+fn year(word: &str) -> Option<i32> {
+    if word.len() != 4 {
+        return None;
+    }
+    word.parse().ok().filter(|y| (2000..=2100).contains(y))
+}
+
+/// The first `day` strictly after `today`.
+fn next_weekday(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let ahead = (day.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today + Days::new(if ahead == 0 { 7 } else { ahead as u64 })
+}
+
+fn add(date: NaiveDate, amount: u32, unit: Unit) -> Option<NaiveDate> {
+    match unit {
+        Unit::Day => date.checked_add_days(Days::new(amount as u64)),
+        Unit::Week => date.checked_add_days(Days::new(amount as u64 * 7)),
+        Unit::Month => date.checked_add_months(Months::new(amount)),
+        Unit::Year => date.checked_add_months(Months::new(amount.checked_mul(12)?)),
+    }
+}
+
+/// `month`/`day` in `year`, or in the following years if it has passed or does not exist
+/// (February 29).
+fn upcoming(today: NaiveDate, month: u32, day: u32, year: Option<i32>) -> Option<NaiveDate> {
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year, month, day);
+    }
+    (today.year()..=today.year() + 4)
+        .filter_map(|y| NaiveDate::from_ymd_opt(y, month, day))
+        .find(|d| *d >= today)
+}
+
+/// A recurrence phrase, before it is checked and written out by `Recurrence`.
+#[derive(Clone, PartialEq, Debug)]
+struct Rule {
+    unit: Unit,
+    interval: u32,
+    by_day: Vec<Weekday>,
+    by_month_day: Option<u32>,
+}
+
+impl Rule {
+    fn every(unit: Unit, interval: u32) -> Self {
+        Rule {
+            unit,
+            interval,
+            by_day: Vec::new(),
+            by_month_day: None,
+        }
+    }
+
+    fn weekly_on(days: Vec<Weekday>) -> Self {
+        Rule {
+            by_day: days,
+            ..Rule::every(Unit::Week, 1)
+        }
+    }
+
+    fn monthly_on(day: u32) -> Self {
+        Rule {
+            by_month_day: Some(day),
+            ..Rule::every(Unit::Month, 1)
+        }
+    }
+
+    fn to_rrule(&self) -> Result<String, String> {
+        let freq = match self.unit {
+            Unit::Day => "DAILY",
+            Unit::Week => "WEEKLY",
+            Unit::Month => "MONTHLY",
+            Unit::Year => "YEARLY",
+        };
+        let mut rrule = format!("FREQ={};INTERVAL={}", freq, self.interval);
+        if !self.by_day.is_empty() {
+            let days = self
+                .by_day
+                .iter()
+                .map(|d| d.to_string()[..2].to_uppercase())
+                .collect::<Vec<_>>();
+            rrule.push_str(&format!(";BYDAY={}", days.join(",")));
+        }
+        if let Some(day) = self.by_month_day {
+            rrule.push_str(&format!(";BYMONTHDAY={}", day));
+        }
+        Ok(Recurrence::parse(&rrule)?.to_string())
+    }
+
+    /// The first day on or after `from` the rule falls on.
+    fn first_on_or_after(&self, from: NaiveDate) -> NaiveDate {
+        let matches = |d: &NaiveDate| {
+            (self.by_day.is_empty() || self.by_day.contains(&d.weekday()))
+                && self.by_month_day.is_none_or(|day| d.day() == day)
+        };
+        (0..MAX_OCCURRENCE_SCAN)
+            .map(|i| from + Days::new(i))
+            .find(matches)
+            .unwrap_or(from)
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    today: NaiveDate,
+}
+
+impl Parser<'_> {
+    /// Keyword at `i`; empty past the end and for quoted phrases, so it never matches.
+    fn word(&self, i: usize) -> &str {
+        match self.tokens.get(i) {
+            Some(t) if !t.quoted => &t.word,
+            _ => "",
+        }
+    }
+
+    /// A date phrase at `i` and the number of tokens it takes.
+    fn date(&self, i: usize) -> Option<(NaiveDate, usize)> {
+        match self.word(i) {
+            "on" | "by" | "due" | "this" => self.bare_date(i + 1, true).map(|(d, n)| (d, n + 1)),
+            "next" => {
+                let date = match self.word(i + 1) {
+                    "week" => next_weekday(self.today, Weekday::Mon),
+                    "month" => self.today.with_day(1)? + Months::new(1),
+                    "year" => NaiveDate::from_ymd_opt(self.today.year() + 1, 1, 1)?,
+                    word => next_weekday(self.today, weekday(word, true)?),
+                };
+                Some((date, 2))
+            }
+            "in" => {
+                let amount = amount(self.word(i + 1))?;
+                let unit = unit(self.word(i + 2))?;
+                Some((add(self.today, amount, unit)?, 3))
+            }
+            _ => self.bare_date(i, false),
+        }
+    }
+
+    fn bare_date(&self, i: usize, short: bool) -> Option<(NaiveDate, usize)> {
+        let word = self.word(i);
+        match word {
+            "today" => return Some((self.today, 1)),
+            "tomorrow" | "tmrw" => return Some((self.today + Days::new(1), 1)),
+            _ => {}
+        }
+        if let Some(day) = weekday(word, short) {
+            return Some((next_weekday(self.today, day), 1));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+            return Some((date, 1));
+        }
+        // `nov 5`, `november 5th, 2027`, `5 nov`, `5th of november`
+        let (month, day, used) = match (month(word), day_of_month(self.word(i + 1), false)) {
+            (Some(month), Some(day)) => (month, day, 2),
+            _ => {
+                let day = day_of_month(word, false)?;
+                match (self.word(i + 1), self.word(i + 2)) {
+                    ("of", next) if month(next).is_some() => (month(next)?, day, 3),
+                    (next, _) => (month(next)?, day, 2),
+                }
+            }
+        };
+        match year(self.word(i + used)) {
+            Some(year) => Some((upcoming(self.today, month, day, Some(year))?, used + 1)),
+            None => Some((upcoming(self.today, month, day, None)?, used)),
+        }
+    }
+
+    /// A time of day at `i` and the number of tokens it takes.
+    fn time(&self, i: usize) -> Option<(NaiveTime, usize)> {
+        match self.word(i) {
+            "at" | "@" => self.bare_time(i + 1, true).map(|(t, n)| (t, n + 1)),
+            _ => self.bare_time(i, false),
+        }
+    }
+
+    /// `9am`, `9:30 pm`, `21:00`, `noon`; a bare hour like `9` only after `at`.
+    fn bare_time(&self, i: usize, bare_hour: bool) -> Option<(NaiveTime, usize)> {
+        let word = self.word(i);
+        if word == "noon" {
+            return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1));
+        }
+        let (clock, meridiem, used) = if let Some(clock) = word.strip_suffix("am") {
+            (clock, Some(false), 1)
+        } else if let Some(clock) = word.strip_suffix("pm") {
+            (clock, Some(true), 1)
+        } else {
+            match self.word(i + 1) {
+                "am" => (word, Some(false), 2),
+                "pm" => (word, Some(true), 2),
+                _ => (word, None, 1),
+            }
+        };
+
+        let (hour, minute) = match clock.split_once(':') {
+            Some((h, m)) if m.len() == 2 => (h, m.parse::<u32>().ok()?),
+            Some(_) => return None,
+            None if meridiem.is_some() || bare_hour => (clock, 0),
+            None => return None,
+        };
+        if hour.is_empty() || hour.len() > 2 || !hour.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let hour: u32 = hour.parse().ok()?;
+        let hour = match meridiem {
+            Some(pm) if (1..=12).contains(&hour) => hour % 12 + if pm { 12 } else { 0 },
+            Some(_) => return None,
+            None => hour,
+        };
+        Some((NaiveTime::from_hms_opt(hour, minute, 0)?, used))
+    }
+
+    /// A recurrence phrase at `i` and the number of tokens it takes.
+    fn rule(&self, i: usize) -> Option<(Rule, usize)> {
+        match self.word(i) {
+            "daily" => return Some((Rule::every(Unit::Day, 1), 1)),
+            "weekly" => return Some((Rule::every(Unit::Week, 1), 1)),
+            "monthly" => return Some((Rule::every(Unit::Month, 1), 1)),
+            "yearly" | "annually" => return Some((Rule::every(Unit::Year, 1), 1)),
+            "every" => {}
+            _ => return None,
+        }
+
+        let word = self.word(i + 1);
+        if let Some(unit) = unit(word) {
+            // `every month on the 15th`
+            if unit == Unit::Month && self.word(i + 2) == "on" && self.word(i + 3) == "the" {
+                if let Some(day) = day_of_month(self.word(i + 4), true) {
+                    return Some((Rule::monthly_on(day), 5));
+                }
+            }
+            return Some((Rule::every(unit, 1), 2));
+        }
+        if word == "other" {
+            return Some((Rule::every(unit(self.word(i + 2))?, 2), 3));
+        }
+        match word {
+            "weekday" | "weekdays" => {
+                let days = vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ];
+                return Some((Rule::weekly_on(days), 2));
+            }
+            "weekend" | "weekends" => {
+                return Some((Rule::weekly_on(vec![Weekday::Sat, Weekday::Sun]), 2));
+            }
+            _ => {}
+        }
+        if let Some(day) = day_of_month(word, true) {
+            return Some((Rule::monthly_on(day), 2));
+        }
+        if let (Some(n), Some(unit)) = (amount(word), unit(self.word(i + 2))) {
+            if word.bytes().all(|b| b.is_ascii_digit()) {
+                return Some((Rule::every(unit, n), 3));
+            }
+        }
+
+        // `every mon, wed and fri`
+        let mut days = vec![weekday(word, true)?];
+        let mut last = i + 1;
+        loop {
+            let next = if self.tokens[last].ends_list_item() {
+                last + 1
+            } else if matches!(self.word(last + 1), "and" | "&") {
+                last + 2
+            } else {
+                break;
+            };
+            match weekday(self.word(next), true) {
+                Some(day) => {
+                    if !days.contains(&day) {
+                        days.push(day);
+                    }
+                    last = next;
+                }
+                None => break,
+            }
+        }
+        days.sort_by_key(|d| d.num_days_from_monday());
+        Some((Rule::weekly_on(days), last + 1 - i))
+    }
+}
+
+/// `#name`: a tag, if the name has a letter (so `#1` stays in the title).
+fn tag(text: &str) -> Option<&str> {
+    let name = text.strip_prefix('#')?.trim_end_matches([',', '.', ';']);
+    let valid = name.chars().count() <= 50
+        && name.chars().any(char::is_alphabetic)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '/');
+    valid.then_some(name)
+}
+
+/// `!high`, `!medium`, `!low` (or `!1` to `!3`, `!med`).
+fn priority(word: &str) -> Option<Priority> {
+    match word.strip_prefix('!')? {
+        "high" | "1" => Some(Priority::High),
+        "medium" | "med" | "2" => Some(Priority::Medium),
+        "low" | "3" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+impl QuickAdd {
+    /// Reads `text` as of `now`, in the time zone of `now`. Recognised phrases are taken out and
+    /// the remaining words, in order, are the title; for each kind of phrase only the first is
+    /// used, so `Meet today or tomorrow` is due today and titled `Meet or tomorrow`. Text in
+    /// double quotes is always kept in the title. The error is a message for a 400 response.
+    ///
+    /// - Dates: `today`, `tomorrow`, weekday names (the next one after today), `next week|month|
+    ///   year`, `in 3 days|weeks|months|years`, `2026-11-05`, `nov 5`, `5 november 2027`. Short
+    ///   weekday names (`fri`) need a prefix: `on`, `by`, `due`, `this` or `next`.
+    /// - Times: `9am`, `9:30 pm`, `21:00`, `noon`, `at 9`. A time without a date is today, or
+    ///   tomorrow once it has passed.
+    /// - Recurrence: `daily`, `weekly`, `monthly`, `yearly`, `every day|week|month|year`,
+    ///   `every other week`, `every 3 days`, `every weekday`, `every weekend`, `every mon and
+    ///   thu`, `every 15th`, `every month on the 15th`. Without a date the todo is due on the
+    ///   first occurrence.
+    /// - `#tag` and `!high|!medium|!low`.
+    pub fn parse(text: &str, now: DateTime<Tz>) -> Result<Self, String> {
+        let tokens = tokenize(text);
+        let today = now.date_naive();
+        let parser = Parser {
+            tokens: &tokens,
+            today,
+        };
+
+        let mut title = Vec::new();
+        let mut tags: Vec<String> = Vec::new();
+        let mut priority_found = None;
+        let mut date = None;
+        let mut time = None;
+        let mut rule: Option<Rule> = None;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.quoted {
+                title.push(token.text.as_str());
+                i += 1;
+                continue;
+            }
+            if let Some(name) = tag(&token.text) {
+                if !tags.iter().any(|t| t.to_lowercase() == name.to_lowercase()) {
+                    tags.push(name.to_string());
+                }
+                i += 1;
+                continue;
+            }
+            if priority_found.is_none() {
+                if let Some(p) = priority(&token.word) {
+                    priority_found = Some(p);
+                    i += 1;
+                    continue;
+                }
+            }
+            if rule.is_none() {
+                if let Some((r, used)) = parser.rule(i) {
+                    rule = Some(r);
+                    i += used;
+                    continue;
+                }
+            }
+            if date.is_none() {
+                if let Some((d, used)) = parser.date(i) {
+                    date = Some(d);
+                    i += used;
+                    continue;
+                }
+            }
+            if time.is_none() {
+                if let Some((t, used)) = parser.time(i) {
+                    time = Some(t);
+                    i += used;
+                    continue;
+                }
+            }
+            title.push(token.text.as_str());
+            i += 1;
+        }
+
+        let title = title.join(" ");
+        if title.trim().is_empty() {
+            return Err("Title is required".into());
+        }
+        let rrule = rule.as_ref().map(Rule::to_rrule).transpose()?;
+
+        let explicit_date = date.is_some();
+        let mut date = date.or_else(|| match (&rule, time) {
+            (Some(rule), _) => Some(rule.first_on_or_after(today)),
+            (None, Some(_)) => Some(today),
+            (None, None) => None,
+        });
+        if let (Some(d), Some(t), false) = (date, time, explicit_date) {
+            if d.and_time(t) <= now.naive_local() {
+                let tomorrow = d + Days::new(1);
+                date = Some(match &rule {
+                    Some(rule) => rule.first_on_or_after(tomorrow),
+                    None => tomorrow,
+                });
+            }
+        }
+
+        let due_date = match (date, time) {
+            (Some(d), Some(t)) => Some(local_timestamp(now.timezone(), d, t)?),
+            (Some(d), None) => Some(local_timestamp(now.timezone(), d, NaiveTime::MIN)?),
+            (None, _) => None,
+        };
+        Ok(QuickAdd {
+            title,
+            due_date,
+            priority: priority_found,
+            tags,
+            rrule,
+        })
+    }
+
+    /// Body for `TodoRepo::create`.
+    pub fn to_create(&self) -> CreateTodo {
+        CreateTodo {
+            parent_id: None,
+            title: self.title.clone(),
+            list_id: None,
+            description: None,
+            due_date: self.due_date.clone(),
+            priority: self.priority,
+            remind_at: None,
+            rrule: self.rrule.clone(),
+            series_id: None,
+            position: None,
+            completed: false,
+        }
+    }
+}
+
+/// RFC 3339 timestamp of a wall-clock time in `tz` (see `time::local_datetime` for DST gaps).
+fn local_timestamp(tz: Tz, date: NaiveDate, time: NaiveTime) -> Result<String, String> {
+    time::local_datetime(tz, date, time)
+        .map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, false))
+        .ok_or_else(|| "The due time does not exist in your time zone".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDateTime, TimeZone};
+    use chrono_tz::{America::Santiago, Europe::Berlin, UTC};
+
+    struct Case {
+        text: &'static str,
+        title: &'static str,
+        due_date: Option<&'static str>,
+        priority: Option<Priority>,
+        tags: &'static [&'static str],
+        rrule: Option<&'static str>,
+    }
+
+    const fn case(text: &'static str, title: &'static str) -> Case {
+        Case {
+            text,
+            title,
+            due_date: None,
+            priority: None,
+            tags: &[],
+            rrule: None,
+        }
+    }
+
+    impl Case {
+        const fn due(self, due_date: &'static str) -> Self {
+            Case {
+                due_date: Some(due_date),
+                ..self
+            }
+        }
+
+        const fn priority(self, priority: Priority) -> Self {
+            Case {
+                priority: Some(priority),
+                ..self
+            }
+        }
+
+        const fn tags(self, tags: &'static [&'static str]) -> Self {
+            Case { tags, ..self }
+        }
+
+        const fn rrule(self, rrule: &'static str) -> Self {
+            Case {
+                rrule: Some(rrule),
+                ..self
+            }
+        }
+    }
+
+    /// `local` (`YYYY-MM-DD HH:MM`) as a wall-clock time in `tz`.
+    fn at(tz: Tz, local: &str) -> DateTime<Tz> {
+        let local = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").unwrap();
+        tz.from_local_datetime(&local).earliest().unwrap()
+    }
+
+    fn check(now: DateTime<Tz>, cases: &[Case]) {
+        for c in cases {
+            let parsed = QuickAdd::parse(c.text, now)
+                .unwrap_or_else(|e| panic!("{:?} failed to parse: {}", c.text, e));
+            let expected = QuickAdd {
+                title: c.title.to_string(),
+                due_date: c.due_date.map(String::from),
+                priority: c.priority,
+                tags: c.tags.iter().map(|t| t.to_string()).collect(),
+                rrule: c.rrule.map(String::from),
+            };
+            assert_eq!(parsed, expected, "input {:?}", c.text);
+        }
+    }
+
+    // Saturday 2026-10-17, 10:00.
+    fn saturday_morning() -> DateTime<Tz> {
+        at(UTC, "2026-10-17 10:00")
+    }
+
+    #[test]
+    fn full_example() {
+        check(
+            saturday_morning(),
+            &[case(
+                "Pay rent tomorrow 9am #finance !high every month",
+                "Pay rent",
+            )
+            .due("2026-10-18T09:00:00+00:00")
+            .priority(Priority::High)
+            .tags(&["finance"])
+            .rrule("FREQ=MONTHLY")],
+        );
+    }
+
+    #[test]
+    fn plain_titles() {
+        check(
+            saturday_morning(),
+            &[
+                case("Buy milk", "Buy milk"),
+                case("  Buy   milk  ", "Buy milk"),
+                case("Log in to portal", "Log in to portal"),
+                case("Bake 3 cakes", "Bake 3 cakes"),
+                case("May the force be with you", "May the force be with you"),
+                case("Lunch with sun", "Lunch with sun"),
+                case("Wed planning", "Wed planning"),
+                case("Gym every 0 days", "Gym every 0 days"),
+                case("Ticket 13pm", "Ticket 13pm"),
+                case("Fix !urgent", "Fix !urgent"),
+                case("Issue #1", "Issue #1"),
+                case("Next steps", "Next steps"),
+                case("Hand in essay", "Hand in essay"),
+            ],
+        );
+    }
+
+    #[test]
+    fn dates() {
+        check(
+            saturday_morning(),
+            &[
+                case("Buy milk today", "Buy milk").due("2026-10-17T00:00:00+00:00"),
+                case("Call mom tomorrow", "Call mom").due("2026-10-18T00:00:00+00:00"),
+                case("Call mom tmrw", "Call mom").due("2026-10-18T00:00:00+00:00"),
+                case("PAY RENT Tomorrow", "PAY RENT").due("2026-10-18T00:00:00+00:00"),
+                case("Dentist friday", "Dentist").due("2026-10-23T00:00:00+00:00"),
+                case("Dentist saturday", "Dentist").due("2026-10-24T00:00:00+00:00"),
+                case("Dentist Monday", "Dentist").due("2026-10-19T00:00:00+00:00"),
+                case("Review on fri", "Review").due("2026-10-23T00:00:00+00:00"),
+                case("Report by thurs", "Report").due("2026-10-22T00:00:00+00:00"),
+                case("Taxes due wed", "Taxes").due("2026-10-21T00:00:00+00:00"),
+                case("Relax this sun", "Relax").due("2026-10-18T00:00:00+00:00"),
+                case("Plan next week", "Plan").due("2026-10-19T00:00:00+00:00"),
+                case("Budget next month", "Budget").due("2026-11-01T00:00:00+00:00"),
+                case("Taxes next year", "Taxes").due("2027-01-01T00:00:00+00:00"),
+                case("Report next tue", "Report").due("2026-10-20T00:00:00+00:00"),
+                case("Report next friday", "Report").due("2026-10-23T00:00:00+00:00"),
+                case("Renew passport in 3 months", "Renew passport")
+                    .due("2027-01-17T00:00:00+00:00"),
+                case("Check in 2 weeks", "Check").due("2026-10-31T00:00:00+00:00"),
+                case("Follow up in a day", "Follow up").due("2026-10-18T00:00:00+00:00"),
+                case("Follow up in 10 days", "Follow up").due("2026-10-27T00:00:00+00:00"),
+                case("Renew in 1 year", "Renew").due("2027-10-17T00:00:00+00:00"),
+                case("Launch 2026-12-01", "Launch").due("2026-12-01T00:00:00+00:00"),
+                case("Party nov 5", "Party").due("2026-11-05T00:00:00+00:00"),
+                case("Party November 5th, 2027", "Party").due("2027-11-05T00:00:00+00:00"),
+                case("Party on nov 5", "Party").due("2026-11-05T00:00:00+00:00"),
+                case("Trip 5 march", "Trip").due("2027-03-05T00:00:00+00:00"),
+                case("Trip 5th of march", "Trip").due("2027-03-05T00:00:00+00:00"),
+                case("Trip 17 oct", "Trip").due("2026-10-17T00:00:00+00:00"),
+                case("Trip 16 oct", "Trip").due("2027-10-16T00:00:00+00:00"),
+                case("Trip 16 oct 2026", "Trip").due("2026-10-16T00:00:00+00:00"),
+                case("Anniversary feb 29", "Anniversary").due("2028-02-29T00:00:00+00:00"),
+                case("Meet today or tomorrow", "Meet or tomorrow").due("2026-10-17T00:00:00+00:00"),
+            ],
+        );
+    }
+
+    #[test]
+    fn times() {
+        check(
+            saturday_morning(),
+            &[
+                case("Meet at 15", "Meet").due("2026-10-17T15:00:00+00:00"),
+                case("Meet at 3pm", "Meet").due("2026-10-17T15:00:00+00:00"),
+                case("Meet 3 pm", "Meet").due("2026-10-17T15:00:00+00:00"),
+                case("Standup 9:30am", "Standup").due("2026-10-18T09:30:00+00:00"),
+                case("Standup 9:30 PM", "Standup").due("2026-10-17T21:30:00+00:00"),
+                case("Lunch noon", "Lunch").due("2026-10-17T12:00:00+00:00"),
+                case("Deploy @ 18:15", "Deploy").due("2026-10-17T18:15:00+00:00"),
+                case("Call 12am", "Call").due("2026-10-18T00:00:00+00:00"),
+                case("Call 12pm", "Call").due("2026-10-17T12:00:00+00:00"),
+                case("Call 10:00", "Call").due("2026-10-18T10:00:00+00:00"),
+                case("Deploy 21:00 friday", "Deploy").due("2026-10-23T21:00:00+00:00"),
+                case("Deploy friday at 8", "Deploy").due("2026-10-23T08:00:00+00:00"),
+                case("Breakfast today 8am", "Breakfast").due("2026-10-17T08:00:00+00:00"),
+                case("Ticket 25:00", "Ticket 25:00"),
+                case("Ticket 9:5pm", "Ticket 9:5pm"),
+            ],
+        );
+    }
+
+    #[test]
+    fn recurrence() {
+        check(
+            saturday_morning(),
+            &[
+                case("Water plants daily", "Water plants")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=DAILY"),
+                case("Water plants daily 8am", "Water plants")
+                    .due("2026-10-18T08:00:00+00:00")
+                    .rrule("FREQ=DAILY"),
+                case("Review weekly", "Review")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY"),
+                case("Review annually", "Review")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=YEARLY"),
+                case("Gym every day", "Gym")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=DAILY"),
+                case("Gym every other day", "Gym")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=DAILY;INTERVAL=2"),
+                case("Backup every 3 weeks", "Backup")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;INTERVAL=3"),
+                case("Standup every weekday 9am", "Standup")
+                    .due("2026-10-19T09:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"),
+                case("Clean every weekend", "Clean")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=SA,SU"),
+                case("Yoga every mon, wed and fri", "Yoga")
+                    .due("2026-10-19T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=MO,WE,FR"),
+                case("Yoga every thu & tue", "Yoga")
+                    .due("2026-10-20T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=TU,TH"),
+                case("Shop every saturday", "Shop")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=SA"),
+                case("Shop every saturday 9am", "Shop")
+                    .due("2026-10-24T09:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=SA"),
+                case("Pay card every 15th", "Pay card")
+                    .due("2026-11-15T00:00:00+00:00")
+                    .rrule("FREQ=MONTHLY;BYMONTHDAY=15"),
+                case("Rent every month on the 1st", "Rent")
+                    .due("2026-11-01T00:00:00+00:00")
+                    .rrule("FREQ=MONTHLY;BYMONTHDAY=1"),
+                case("Rent every month from friday", "Rent from")
+                    .due("2026-10-23T00:00:00+00:00")
+                    .rrule("FREQ=MONTHLY"),
+                case("Sync every mon and tea", "Sync and tea")
+                    .due("2026-10-19T00:00:00+00:00")
+                    .rrule("FREQ=WEEKLY;BYDAY=MO"),
+            ],
+        );
+    }
+
+    #[test]
+    fn priorities_and_tags() {
+        check(
+            saturday_morning(),
+            &[
+                case("Fix bug !1", "Fix bug").priority(Priority::High),
+                case("Fix bug !med", "Fix bug").priority(Priority::Medium),
+                case("Fix bug !MEDIUM", "Fix bug").priority(Priority::Medium),
+                case("Fix bug !3", "Fix bug").priority(Priority::Low),
+                case("Task !high !low", "Task !low").priority(Priority::High),
+                case("Read #books #Books #fun", "Read").tags(&["books", "fun"]),
+                case("Plan #q4-goals, today", "Plan")
+                    .due("2026-10-17T00:00:00+00:00")
+                    .tags(&["q4-goals"]),
+                case("#work Write report", "Write report").tags(&["work"]),
+            ],
+        );
+    }
+
+    #[test]
+    fn quoted_text_stays_in_title() {
+        check(
+            saturday_morning(),
+            &[
+                case("\"Meet tomorrow\" prep", "Meet tomorrow prep"),
+                case(
+                    "Watch \"Friday Night Lights\" friday",
+                    "Watch Friday Night Lights",
+                )
+                .due("2026-10-23T00:00:00+00:00"),
+                case("Read \"#1 bestseller\" #books", "Read #1 bestseller").tags(&["books"]),
+                case("Open \"daily", "Open daily"),
+            ],
+        );
+    }
+
+    #[test]
+    fn times_are_in_the_users_time_zone() {
+        // 12:00 in Berlin (CEST, +02:00); clocks go back on 2026-10-25.
+        let noon = at(Berlin, "2026-10-17 12:00");
+        check(
+            noon,
+            &[
+                case("Call 11am", "Call").due("2026-10-18T11:00:00+02:00"),
+                case("Call 1pm", "Call").due("2026-10-17T13:00:00+02:00"),
+                case("Call 9am oct 26", "Call").due("2026-10-26T09:00:00+01:00"),
+                case("Jog 2:30am oct 25", "Jog").due("2026-10-25T02:30:00+02:00"),
+                case("Jog 2:30am 2027-03-28", "Jog").due("2027-03-28T03:30:00+02:00"),
+            ],
+        );
+
+        // Still the 17th in UTC, already the 18th in Berlin.
+        let after_midnight = at(Berlin, "2026-10-18 01:30");
+        check(
+            after_midnight,
+            &[
+                case("Call today", "Call").due("2026-10-18T00:00:00+02:00"),
+                case("Call tomorrow", "Call").due("2026-10-19T00:00:00+02:00"),
+                case("Call oct 26", "Call").due("2026-10-26T00:00:00+01:00"),
+                case("Call 1am", "Call").due("2026-10-19T01:00:00+02:00"),
+            ],
+        );
+
+        // Chile skips from midnight to 01:00 on 2026-09-06.
+        check(
+            at(Santiago, "2026-08-01 12:00"),
+            &[case("Trip 2026-09-06", "Trip").due("2026-09-06T01:00:00-03:00")],
+        );
+    }
+
+    #[test]
+    fn rejects_input_without_a_title() {
+        for text in ["tomorrow", "#work !high", "every day 9am", "\"\" today"] {
+            let err = QuickAdd::parse(text, saturday_morning()).unwrap_err();
+            assert_eq!(err, "Title is required", "input {:?}", text);
+        }
+    }
+
+    #[test]
+    fn validates_request() {
+        let request = |text: &str| QuickAddRequest {
+            text: text.to_string(),
+            save: false,
+        };
+        assert!(request("Buy milk").validate().is_ok());
+        assert!(request("   ").validate().is_err());
+        assert!(request(&"a".repeat(MAX_TEXT_LEN)).validate().is_ok());
+        assert!(request(&"a".repeat(MAX_TEXT_LEN + 1)).validate().is_err());
+    }
+
+    #[test]
+    fn builds_create_body() {
+        let parsed =
+            QuickAdd::parse("Pay rent tomorrow !high monthly", saturday_morning()).unwrap();
+        let create = parsed.to_create();
+        assert_eq!(create.title, "Pay rent");
+        assert_eq!(
+            create.due_date.as_deref(),
+            Some("2026-10-18T00:00:00+00:00")
+        );
+        assert_eq!(create.priority, Some(Priority::High));
+        assert_eq!(create.rrule.as_deref(), Some("FREQ=MONTHLY"));
+        assert!(create.validate().is_ok());
+    }
+}
@@ -0,0 +1,643 @@
+use crate::utils::time;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::fmt;
+
+const MAX_RRULE_LEN: usize = 200;
+const MAX_INTERVAL: u32 = 1000;
+/// Months/years scanned for a valid date before giving up (e.g. `BYMONTHDAY=31` every 12 months).
+const MAX_SCAN: u32 = 48;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The subset of an iCalendar RRULE (RFC 5545) supported for recurring todos:
+/// `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY`, `YEARLY`), `INTERVAL`, `COUNT`, `UNTIL`,
+/// `BYDAY` (weekly, without ordinals) and `BYMONTHDAY` (monthly, `-1` is the last day).
+///
+/// Occurrences are generated one at a time from the previous one, so `COUNT` is the number of
+/// occurrences left including the current one; each generated occurrence carries `COUNT - 1`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Recurrence {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+    /// Sorted Monday first.
+    pub by_day: Vec<Weekday>,
+    /// Sorted; `-1` sorts first and means the last day of the month.
+    pub by_month_day: Vec<i32>,
+}
+
+impl Recurrence {
+    /// Parses `FREQ=WEEKLY;BYDAY=MO,TH` (an `RRULE:` prefix is accepted). The error is a message
+    /// suitable for a 400 response.
+    pub fn parse(rrule: &str) -> Result<Self, String> {
+        let rrule = rrule.trim();
+        if rrule.len() > MAX_RRULE_LEN {
+            return Err(format!(
+                "rrule must be at most {} characters",
+                MAX_RRULE_LEN
+            ));
+        }
+        let upper = rrule.to_ascii_uppercase();
+        let body = upper.strip_prefix("RRULE:").unwrap_or(&upper);
+
+        let mut freq = None;
+        let mut rule = Recurrence {
+            freq: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+        };
+        for part in body.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid rrule part '{}'", part))?;
+            match key {
+                "FREQ" => {
+                    freq = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err("FREQ must be DAILY, WEEKLY, MONTHLY or YEARLY".into()),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| (1..=MAX_INTERVAL).contains(i))
+                        .ok_or_else(|| {
+                            format!("INTERVAL must be between 1 and {}", MAX_INTERVAL)
+                        })?;
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|c| *c >= 1)
+                            .ok_or("COUNT must be a positive number")?,
+                    );
+                }
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        let day = parse_weekday(day)
+                            .ok_or("BYDAY must list MO, TU, WE, TH, FR, SA or SU")?;
+                        if !rule.by_day.contains(&day) {
+                            rule.by_day.push(day);
+                        }
+                    }
+                    rule.by_day.sort_by_key(|d| d.num_days_from_monday());
+                }
+                "BYMONTHDAY" => {
+                    for day in value.split(',') {
+                        let day = day
+                            .parse()
+                            .ok()
+                            .filter(|d| (1..=31).contains(d) || *d == -1)
+                            .ok_or("BYMONTHDAY must be between 1 and 31, or -1")?;
+                        if !rule.by_month_day.contains(&day) {
+                            rule.by_month_day.push(day);
+                        }
+                    }
+                    rule.by_month_day.sort();
+                }
+                _ => return Err(format!("Unsupported rrule part '{}'", key)),
+            }
+        }
+
+        rule.freq = freq.ok_or("rrule must include FREQ")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("rrule cannot have both COUNT and UNTIL".into());
+        }
+        if !rule.by_day.is_empty() && rule.freq != Frequency::Weekly {
+            return Err("BYDAY is only supported with FREQ=WEEKLY".into());
+        }
+        if !rule.by_month_day.is_empty() && rule.freq != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported with FREQ=MONTHLY".into());
+        }
+        Ok(rule)
+    }
+
+    /// The rule carried by the next occurrence, or `None` if the series ends with this one.
+    pub fn advance(&self) -> Option<Recurrence> {
+        match self.count {
+            Some(1) => None,
+            count => Some(Recurrence {
+                count: count.map(|c| c - 1),
+                ..self.clone()
+            }),
+        }
+    }
+
+    /// Due date (RFC 3339) of the occurrence after one due at `due`, at the same time and offset.
+    /// Without a due date the series counts from the date of `now`, and the next occurrence is
+    /// due at midnight in `now`'s time zone. `None` once `UNTIL` has passed.
+    pub fn next_due(&self, due: Option<&str>, now: DateTime<Tz>) -> Option<String> {
+        match due.and_then(|d| DateTime::parse_from_rfc3339(d).ok()) {
+            Some(at) => {
+                let date = self.next_date(at.date_naive())?;
+                let next = NaiveDateTime::new(date, at.time())
+                    .and_local_timezone(*at.offset())
+                    .single()?;
+                Some(next.to_rfc3339())
+            }
+            None => {
+                let date = self.next_date(now.date_naive())?;
+                let next = time::local_datetime(now.timezone(), date, NaiveTime::MIN)?;
+                Some(next.to_rfc3339())
+            }
+        }
+    }
+
+    /// First date of the series strictly after `from`.
+    fn next_date(&self, from: NaiveDate) -> Option<NaiveDate> {
+        let next = match self.freq {
+            Frequency::Daily => from.checked_add_days(Days::new(self.interval.into()))?,
+            Frequency::Weekly => self.next_weekly(from)?,
+            Frequency::Monthly => self.next_monthly(from)?,
+            Frequency::Yearly => (1..=MAX_SCAN).find_map(|n| {
+                let year = from.year() + (n * self.interval) as i32;
+                NaiveDate::from_ymd_opt(year, from.month(), from.day())
+            })?,
+        };
+        match self.until {
+            Some(until) if next > until => None,
+            _ => Some(next),
+        }
+    }
+
+    fn next_weekly(&self, from: NaiveDate) -> Option<NaiveDate> {
+        let weekday = from.weekday().num_days_from_monday();
+        // Later day in the same week.
+        if let Some(day) = self
+            .by_day
+            .iter()
+            .find(|d| d.num_days_from_monday() > weekday)
+        {
+            return from.checked_add_days(Days::new((day.num_days_from_monday() - weekday).into()));
+        }
+        // Otherwise the first listed day `interval` weeks on.
+        let week_start = from.checked_sub_days(Days::new(weekday.into()))?;
+        let next_week = week_start.checked_add_days(Days::new(7 * u64::from(self.interval)))?;
+        let offset = self
+            .by_day
+            .first()
+            .map_or(weekday, |d| d.num_days_from_monday());
+        next_week.checked_add_days(Days::new(offset.into()))
+    }
+
+    fn next_monthly(&self, from: NaiveDate) -> Option<NaiveDate> {
+        let days = if self.by_month_day.is_empty() {
+            vec![from.day() as i32]
+        } else {
+            self.by_month_day.clone()
+        };
+        let first_of_month = from.with_day(1)?;
+        // Later day in the same month, then months `interval` apart; days a month lacks are skipped.
+        if let Some(date) = month_days(first_of_month, &days)
+            .into_iter()
+            .find(|d| *d > from)
+        {
+            return Some(date);
+        }
+        (1..=MAX_SCAN).find_map(|n| {
+            let month = first_of_month.checked_add_months(Months::new(n * self.interval))?;
+            month_days(month, &days).into_iter().next()
+        })
+    }
+}
+
+/// Moves `at` by the distance between two due dates (all RFC 3339), so a reminder stays the same
+/// time ahead of its occurrence's due date.
+pub fn shift_by_due(at: &str, from_due: &str, to_due: &str) -> Option<String> {
+    let at = DateTime::parse_from_rfc3339(at).ok()?;
+    let delta =
+        DateTime::parse_from_rfc3339(to_due).ok()? - DateTime::parse_from_rfc3339(from_due).ok()?;
+    Some((at + delta).to_rfc3339())
+}
+
+/// Valid dates of `days` in the month starting at `first`, ascending.
+fn month_days(first: NaiveDate, days: &[i32]) -> Vec<NaiveDate> {
+    let mut dates: Vec<NaiveDate> = days
+        .iter()
+        .filter_map(|&d| {
+            if d == -1 {
+                first
+                    .checked_add_months(Months::new(1))?
+                    .checked_sub_days(Days::new(1))
+            } else {
+                first.with_day(d as u32)
+            }
+        })
+        .collect();
+    dates.sort();
+    dates.dedup();
+    dates
+}
+
+impl fmt::Display for Recurrence {
+    /// Canonical form stored on the todo, e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let freq = match self.freq {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self.by_day.iter().map(|d| weekday_code(*d)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|d| d.to_string()).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// `UNTIL` as a date (`20261231`) or UTC date-time (`20261231T235959Z`); only the date is used.
+fn parse_until(value: &str) -> Result<NaiveDate, String> {
+    let date = value.split('T').next().unwrap_or(value);
+    let parsed = NaiveDate::parse_from_str(date, "%Y%m%d").ok();
+    let time_ok = match value.split_once('T') {
+        Some((_, t)) => NaiveTime::parse_from_str(t.trim_end_matches('Z'), "%H%M%S").is_ok(),
+        None => true,
+    };
+    parsed
+        .filter(|_| time_ok)
+        .ok_or_else(|| "UNTIL must be YYYYMMDD or YYYYMMDDTHHMMSSZ".into())
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    Some(match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// Body of `PATCH /api/todos/:id/series`.
+#[derive(Deserialize)]
+pub struct UpdateSeries {
+    pub rrule: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono_tz::{America::Santiago, UTC};
+
+    fn at(tz: Tz, local: &str) -> DateTime<Tz> {
+        let local = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").unwrap();
+        tz.from_local_datetime(&local).unwrap()
+    }
+
+    #[test]
+    fn parses_to_canonical_form() {
+        let cases = [
+            ("FREQ=DAILY", "FREQ=DAILY"),
+            (
+                "rrule:freq=weekly;byday=th,mo,mo",
+                "FREQ=WEEKLY;BYDAY=MO,TH",
+            ),
+            ("FREQ=DAILY;INTERVAL=1", "FREQ=DAILY"),
+            (
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=SU,MO",
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,SU",
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=15,-1",
+                "FREQ=MONTHLY;BYMONTHDAY=-1,15",
+            ),
+            ("FREQ=YEARLY;COUNT=5", "FREQ=YEARLY;COUNT=5"),
+            (
+                "FREQ=DAILY;UNTIL=20261231T235959Z",
+                "FREQ=DAILY;UNTIL=20261231",
+            ),
+            (" FREQ=DAILY;;UNTIL=20261231 ", "FREQ=DAILY;UNTIL=20261231"),
+        ];
+        for (input, expected) in cases {
+            let rule = Recurrence::parse(input).unwrap_or_else(|e| panic!("{:?}: {}", input, e));
+            assert_eq!(rule.to_string(), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_rules() {
+        let too_long = format!("FREQ=DAILY;{}", "BYDAY=MO;".repeat(30));
+        let cases = [
+            ("", "rrule must include FREQ"),
+            ("INTERVAL=2", "rrule must include FREQ"),
+            ("FREQ", "Invalid rrule part 'FREQ'"),
+            (
+                "FREQ=HOURLY",
+                "FREQ must be DAILY, WEEKLY, MONTHLY or YEARLY",
+            ),
+            (
+                "FREQ=DAILY;INTERVAL=0",
+                "INTERVAL must be between 1 and 1000",
+            ),
+            (
+                "FREQ=DAILY;INTERVAL=1001",
+                "INTERVAL must be between 1 and 1000",
+            ),
+            ("FREQ=DAILY;COUNT=0", "COUNT must be a positive number"),
+            (
+                "FREQ=DAILY;COUNT=2;UNTIL=20261231",
+                "rrule cannot have both COUNT and UNTIL",
+            ),
+            (
+                "FREQ=DAILY;UNTIL=2026-12-31",
+                "UNTIL must be YYYYMMDD or YYYYMMDDTHHMMSSZ",
+            ),
+            (
+                "FREQ=DAILY;UNTIL=20261231T2500Z",
+                "UNTIL must be YYYYMMDD or YYYYMMDDTHHMMSSZ",
+            ),
+            (
+                "FREQ=WEEKLY;BYDAY=1MO",
+                "BYDAY must list MO, TU, WE, TH, FR, SA or SU",
+            ),
+            (
+                "FREQ=DAILY;BYDAY=MO",
+                "BYDAY is only supported with FREQ=WEEKLY",
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=0",
+                "BYMONTHDAY must be between 1 and 31, or -1",
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=32",
+                "BYMONTHDAY must be between 1 and 31, or -1",
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=-2",
+                "BYMONTHDAY must be between 1 and 31, or -1",
+            ),
+            (
+                "FREQ=WEEKLY;BYMONTHDAY=1",
+                "BYMONTHDAY is only supported with FREQ=MONTHLY",
+            ),
+            ("FREQ=DAILY;BYSETPOS=1", "Unsupported rrule part 'BYSETPOS'"),
+            (too_long.as_str(), "rrule must be at most 200 characters"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                Recurrence::parse(input).unwrap_err(),
+                expected,
+                "input {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn next_due_dates() {
+        // 2026-10-17 is a Saturday.
+        let cases = [
+            ("FREQ=DAILY", "2026-10-17", Some("2026-10-18")),
+            ("FREQ=DAILY;INTERVAL=3", "2026-12-30", Some("2027-01-02")),
+            ("FREQ=WEEKLY", "2026-10-17", Some("2026-10-24")),
+            ("FREQ=WEEKLY;BYDAY=MO,TH", "2026-10-19", Some("2026-10-22")),
+            ("FREQ=WEEKLY;BYDAY=MO,TH", "2026-10-22", Some("2026-10-26")),
+            ("FREQ=WEEKLY;BYDAY=MO,TH", "2026-10-17", Some("2026-10-19")),
+            (
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH",
+                "2026-10-19",
+                Some("2026-10-22"),
+            ),
+            (
+                "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH",
+                "2026-10-22",
+                Some("2026-11-02"),
+            ),
+            ("FREQ=WEEKLY;BYDAY=SU", "2026-12-27", Some("2027-01-03")),
+            ("FREQ=MONTHLY", "2026-10-17", Some("2026-11-17")),
+            ("FREQ=MONTHLY", "2026-12-15", Some("2027-01-15")),
+            ("FREQ=MONTHLY", "2026-01-31", Some("2026-03-31")),
+            ("FREQ=MONTHLY;INTERVAL=2", "2026-11-15", Some("2027-01-15")),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=-1",
+                "2026-01-31",
+                Some("2026-02-28"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=-1",
+                "2026-02-28",
+                Some("2026-03-31"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=-1",
+                "2028-01-31",
+                Some("2028-02-29"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=1,15",
+                "2026-10-01",
+                Some("2026-10-15"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=1,15",
+                "2026-10-15",
+                Some("2026-11-01"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=1,15",
+                "2026-10-20",
+                Some("2026-11-01"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=30",
+                "2026-01-30",
+                Some("2026-03-30"),
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=31",
+                "2026-12-31",
+                Some("2027-01-31"),
+            ),
+            (
+                "FREQ=MONTHLY;INTERVAL=12;BYMONTHDAY=31",
+                "2026-01-31",
+                Some("2027-01-31"),
+            ),
+            // February never has a 31st; the scan gives up instead of looping.
+            ("FREQ=MONTHLY;INTERVAL=12;BYMONTHDAY=31", "2026-02-01", None),
+            ("FREQ=YEARLY", "2026-10-17", Some("2027-10-17")),
+            ("FREQ=YEARLY;INTERVAL=2", "2026-10-17", Some("2028-10-17")),
+            ("FREQ=YEARLY", "2028-02-29", Some("2032-02-29")),
+            (
+                "FREQ=DAILY;UNTIL=20261018",
+                "2026-10-17",
+                Some("2026-10-18"),
+            ),
+            ("FREQ=DAILY;UNTIL=20261018", "2026-10-18", None),
+            ("FREQ=WEEKLY;UNTIL=20261020", "2026-10-17", None),
+        ];
+        // The dates are due at 09:00 UTC; the time of day carries over.
+        let now = at(UTC, "2026-10-17 12:00");
+        for (rrule, due, expected) in cases {
+            let rule = Recurrence::parse(rrule).unwrap();
+            let next = rule.next_due(Some(&format!("{}T09:00:00+00:00", due)), now);
+            let expected = expected.map(|e| format!("{}T09:00:00+00:00", e));
+            assert_eq!(next, expected, "{} after {}", rrule, due);
+        }
+    }
+
+    #[test]
+    fn next_due_keeps_time_and_offset() {
+        let cases = [
+            (
+                "FREQ=DAILY",
+                "2026-10-17T09:30:00+02:00",
+                "2026-10-18T09:30:00+02:00",
+            ),
+            (
+                "FREQ=DAILY",
+                "2026-10-17T09:30:00Z",
+                "2026-10-18T09:30:00+00:00",
+            ),
+            (
+                "FREQ=WEEKLY;BYDAY=FR",
+                "2026-10-16T23:00:00-05:00",
+                "2026-10-23T23:00:00-05:00",
+            ),
+            (
+                "FREQ=MONTHLY;BYMONTHDAY=-1",
+                "2026-01-31T08:00:00+01:00",
+                "2026-02-28T08:00:00+01:00",
+            ),
+        ];
+        for (rrule, due, expected) in cases {
+            let rule = Recurrence::parse(rrule).unwrap();
+            let next = rule.next_due(Some(due), at(UTC, "2026-10-17 12:00"));
+            assert_eq!(next.as_deref(), Some(expected), "{} after {}", rrule, due);
+        }
+    }
+
+    #[test]
+    fn next_due_without_a_due_date_counts_from_today() {
+        let rule = Recurrence::parse("FREQ=WEEKLY").unwrap();
+        let now = at(UTC, "2026-10-17 12:00");
+        assert_eq!(
+            rule.next_due(None, now).as_deref(),
+            Some("2026-10-24T00:00:00+00:00")
+        );
+        assert_eq!(
+            rule.next_due(Some("soon"), now).as_deref(),
+            Some("2026-10-24T00:00:00+00:00")
+        );
+        // Today and midnight are the user's: late on Friday in Santiago is already Saturday in
+        // UTC.
+        let now = at(Santiago, "2026-10-16 22:00");
+        assert_eq!(
+            rule.next_due(None, now).as_deref(),
+            Some("2026-10-23T00:00:00-03:00")
+        );
+    }
+
+    #[test]
+    fn advance_counts_down() {
+        let cases = [
+            ("FREQ=DAILY;COUNT=3", Some("FREQ=DAILY;COUNT=2")),
+            ("FREQ=DAILY;COUNT=2", Some("FREQ=DAILY;COUNT=1")),
+            ("FREQ=DAILY;COUNT=1", None),
+            ("FREQ=DAILY", Some("FREQ=DAILY")),
+            (
+                "FREQ=DAILY;UNTIL=20261231",
+                Some("FREQ=DAILY;UNTIL=20261231"),
+            ),
+        ];
+        for (rrule, expected) in cases {
+            let next = Recurrence::parse(rrule).unwrap().advance();
+            assert_eq!(
+                next.map(|r| r.to_string()).as_deref(),
+                expected,
+                "{}",
+                rrule
+            );
+        }
+    }
+
+    #[test]
+    fn shifts_reminders_with_the_due_date() {
+        let cases = [
+            (
+                "2026-10-17T08:00:00+00:00",
+                "2026-10-17T09:00:00+00:00",
+                "2026-10-24T09:00:00+00:00",
+                Some("2026-10-24T08:00:00+00:00"),
+            ),
+            (
+                "2026-10-16T18:00:00+02:00",
+                "2026-10-17T00:00:00+00:00",
+                "2026-11-17T00:00:00+00:00",
+                Some("2026-11-16T18:00:00+02:00"),
+            ),
+            (
+                "2026-10-16T18:00:00+02:00",
+                "2026-10-17T00:00:00+00:00",
+                "2026-11-17",
+                None,
+            ),
+            (
+                "tomorrow",
+                "2026-10-17T00:00:00+00:00",
+                "2026-11-17T00:00:00+00:00",
+                None,
+            ),
+        ];
+        for (at, from, to, expected) in cases {
+            assert_eq!(
+                shift_by_due(at, from, to).as_deref(),
+                expected,
+                "{} {} {}",
+                at,
+                from,
+                to
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::utils::serde_helpers::nullable;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+const MAX_ARCHIVE_AFTER_DAYS: u32 = 3650;
+
+/// Response of `GET /api/settings`, with defaults applied.
+#[derive(Serialize, Clone)]
+pub struct UserSettings {
+    /// Completed todos are archived this many days after completion; `None` never archives.
+    pub archive_after_days: Option<u32>,
+    /// IANA time zone, e.g. `Europe/Berlin`, used to read dates in quick-add text and to
+    /// schedule recurring todos without a due date.
+    pub timezone: String,
+}
+
+impl UserSettings {
+    /// `timezone` as a `Tz`; UTC if the stored name is no longer known.
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+}
+
+/// Body of `PATCH /api/settings`. An absent field is left unchanged.
+#[derive(Deserialize, Default)]
+pub struct UpdateSettings {
+    /// Days, or `null` to never archive.
+    #[serde(default, deserialize_with = "nullable")]
+    pub archive_after_days: Option<Option<u32>>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl UpdateSettings {
+    pub fn is_empty(&self) -> bool {
+        self.archive_after_days.is_none() && self.timezone.is_none()
+    }
+
+    /// The error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(Some(days)) = self.archive_after_days {
+            if !(1..=MAX_ARCHIVE_AFTER_DAYS).contains(&days) {
+                return Err(format!(
+                    "archive_after_days must be between 1 and {}",
+                    MAX_ARCHIVE_AFTER_DAYS
+                ));
+            }
+        }
+        if let Some(timezone) = &self.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                return Err("timezone must be an IANA time zone such as Europe/Berlin".into());
+            }
+        }
+        Ok(())
+    }
+}
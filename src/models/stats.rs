@@ -0,0 +1,78 @@
+use crate::models::ListTodosQuery;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Days in the default `completions_per_day` range, ending today.
+const DEFAULT_RANGE_DAYS: i64 = 30;
+/// Longest accepted range, so the daily series stays small.
+const MAX_RANGE_DAYS: i64 = 366;
+
+/// Query parameters of `GET /api/todos/stats`.
+pub struct StatsQuery {
+    /// First day of `completions_per_day` (UTC).
+    pub from: NaiveDate,
+    /// Last day of `completions_per_day` (UTC), inclusive.
+    pub to: NaiveDate,
+    /// Which todos are counted. Only the filters that select ids apply: `list_id`, `tag` and
+    /// `assigned_to`.
+    pub scope: ListTodosQuery,
+}
+
+pub(crate) fn parse_date(name: &str, value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("{} must be YYYY-MM-DD", name))
+}
+
+impl StatsQuery {
+    /// Parses `?from=&to=` (default: the 30 days up to `today`) plus the scope filters of
+    /// `GET /api/todos`. The error is a message suitable for a 400 response.
+    pub fn from_url(url: &worker::Url, today: NaiveDate) -> Result<Self, String> {
+        let mut from = None;
+        let mut to = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "from" => from = Some(parse_date("from", &value)?),
+                "to" => to = Some(parse_date("to", &value)?),
+                _ => {}
+            }
+        }
+        let to = to.unwrap_or(today);
+        let from = from.unwrap_or(to - Duration::days(DEFAULT_RANGE_DAYS - 1));
+        if from > to {
+            return Err("from must not be after to".into());
+        }
+        if (to - from).num_days() >= MAX_RANGE_DAYS {
+            return Err(format!(
+                "The range can span at most {} days",
+                MAX_RANGE_DAYS
+            ));
+        }
+        Ok(StatsQuery {
+            from,
+            to,
+            scope: ListTodosQuery::from_url(url)?,
+        })
+    }
+}
+
+/// Todos completed on one day, by `completed_at` (UTC).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyCompletions {
+    pub date: String,
+    pub count: u64,
+}
+
+/// Response of `GET /api/todos/stats`, as computed by the `todo_stats` function in Supabase.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TodoStats {
+    /// Live todos in scope, archived ones included.
+    pub total: u64,
+    pub completed: u64,
+    pub open: u64,
+    /// One entry per day of the range, oldest first, zero days included.
+    pub completions_per_day: Vec<DailyCompletions>,
+    /// Consecutive days with at least one completion, ending today (or yesterday, if nothing has
+    /// been completed yet today).
+    pub current_streak: u64,
+    /// Mean time from creation to completion of the completed todos; `None` if there are none.
+    pub average_completion_seconds: Option<f64>,
+}
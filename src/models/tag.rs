@@ -0,0 +1,99 @@
+use crate::utils::serde_helpers::nullable;
+use serde::{Deserialize, Serialize};
+
+const MAX_TAG_NAME_LEN: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    /// Hex colour such as `#ff8800`.
+    pub color: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CreateTag {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl CreateTag {
+    /// Checks every field; the error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)?;
+        if let Some(c) = &self.color {
+            validate_color(c)?;
+        }
+        Ok(())
+    }
+}
+
+/// PATCH body. Absent leaves a field unchanged; `null` clears `color`.
+#[derive(Deserialize)]
+pub struct UpdateTag {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub color: Option<Option<String>>,
+}
+
+impl UpdateTag {
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.color.is_none()
+    }
+
+    /// Checks every present field; the error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(n) = &self.name {
+            validate_name(n)?;
+        }
+        if let Some(Some(c)) = &self.color {
+            validate_color(c)?;
+        }
+        Ok(())
+    }
+
+    /// Supabase PATCH body holding only the fields present in the request.
+    pub fn to_patch_body(&self) -> serde_json::Value {
+        let mut body = serde_json::Map::new();
+        if let Some(n) = &self.name {
+            body.insert("name".into(), serde_json::json!(n.trim()));
+        }
+        if let Some(c) = &self.color {
+            body.insert("color".into(), serde_json::json!(c));
+        }
+        serde_json::Value::Object(body)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AttachTag {
+    pub tag_id: i64,
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Name is required".into());
+    }
+    if name.chars().count() > MAX_TAG_NAME_LEN {
+        return Err(format!(
+            "Name must be at most {} characters",
+            MAX_TAG_NAME_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn validate_color(color: &str) -> Result<(), String> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if valid {
+        Ok(())
+    } else {
+        Err("Color must be a hex colour like #ff8800".into())
+    }
+}
@@ -0,0 +1,153 @@
+use crate::models::stats::parse_date;
+use crate::utils::serde_helpers::nullable;
+use chrono::{DateTime, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+const MAX_NOTE_LEN: usize = 500;
+/// Days in the default report range, ending today.
+const DEFAULT_REPORT_DAYS: i64 = 30;
+/// Longest accepted report range.
+const MAX_REPORT_DAYS: i64 = 366;
+
+/// Time one user spent on a todo. `ended_at` is `None` while the timer runs.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TimeEntry {
+    pub id: i64,
+    pub todo_id: i64,
+    /// Kratos identity id of the user who tracked the time.
+    pub user_id: String,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+/// Body of `PATCH /api/todos/:id/time-entries/:entry_id`. An absent field is left unchanged;
+/// setting `ended_at` on a running entry stops it.
+#[derive(Deserialize, Default)]
+pub struct UpdateTimeEntry {
+    #[serde(default)]
+    pub started_at: Option<String>,
+    #[serde(default)]
+    pub ended_at: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub note: Option<Option<String>>,
+}
+
+fn validate_timestamp(name: &str, value: &str) -> Result<(), String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|_| ())
+        .map_err(|_| format!("{} must be an RFC 3339 timestamp", name))
+}
+
+impl UpdateTimeEntry {
+    pub fn is_empty(&self) -> bool {
+        self.started_at.is_none() && self.ended_at.is_none() && self.note.is_none()
+    }
+
+    /// The error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(started_at) = &self.started_at {
+            validate_timestamp("started_at", started_at)?;
+        }
+        if let Some(ended_at) = &self.ended_at {
+            validate_timestamp("ended_at", ended_at)?;
+        }
+        if let Some(Some(note)) = &self.note {
+            if note.chars().count() > MAX_NOTE_LEN {
+                return Err(format!("Note must be at most {} characters", MAX_NOTE_LEN));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether an entry from `started_at` to `ended_at` (RFC 3339) runs forwards. Unparsable values
+/// are left to the database to reject.
+pub fn is_ordered(started_at: &str, ended_at: &str) -> bool {
+    match (
+        DateTime::parse_from_rfc3339(started_at),
+        DateTime::parse_from_rfc3339(ended_at),
+    ) {
+        (Ok(start), Ok(end)) => start <= end,
+        _ => true,
+    }
+}
+
+/// Query parameters of `GET /api/todos/time-report`.
+pub struct TimeReportQuery {
+    /// First day of the report (UTC).
+    pub from: NaiveDate,
+    /// Last day of the report (UTC), inclusive.
+    pub to: NaiveDate,
+    /// Only the caller's own entries (`user=me`) instead of everyone's.
+    pub only_mine: bool,
+}
+
+impl TimeReportQuery {
+    /// Parses `?from=&to=&user=` (default: the 30 days up to `today`, everyone's entries). The
+    /// error is a message suitable for a 400 response.
+    pub fn from_url(url: &worker::Url, today: NaiveDate) -> Result<Self, String> {
+        let mut from = None;
+        let mut to = None;
+        let mut only_mine = false;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "from" => from = Some(parse_date("from", &value)?),
+                "to" => to = Some(parse_date("to", &value)?),
+                "user" => {
+                    if value != "me" {
+                        return Err("user must be me".into());
+                    }
+                    only_mine = true;
+                }
+                _ => {}
+            }
+        }
+        let to = to.unwrap_or(today);
+        let from = from.unwrap_or(to - Duration::days(DEFAULT_REPORT_DAYS - 1));
+        if from > to {
+            return Err("from must not be after to".into());
+        }
+        if (to - from).num_days() >= MAX_REPORT_DAYS {
+            return Err(format!(
+                "The range can span at most {} days",
+                MAX_REPORT_DAYS
+            ));
+        }
+        Ok(TimeReportQuery {
+            from,
+            to,
+            only_mine,
+        })
+    }
+}
+
+/// Tracked time on one todo within the report range.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TodoTime {
+    pub todo_id: i64,
+    pub title: String,
+    pub seconds: u64,
+}
+
+/// Tracked time on one day (UTC).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DailyTime {
+    pub date: String,
+    pub seconds: u64,
+}
+
+/// Response of `GET /api/todos/time-report`, as computed by the `time_report` function in
+/// Supabase. Entries are clipped to the range and split at midnight UTC; running timers count up
+/// to now.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimeReport {
+    pub from: String,
+    pub to: String,
+    pub total_seconds: u64,
+    /// Most time first.
+    pub per_todo: Vec<TodoTime>,
+    /// One entry per day of the range, oldest first, zero days included.
+    pub per_day: Vec<DailyTime>,
+}
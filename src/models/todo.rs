@@ -1,29 +1,781 @@
+use crate::models::Recurrence;
+use crate::utils::serde_helpers::nullable;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const MAX_TITLE_LEN: usize = 500;
+const MAX_DESCRIPTION_LEN: usize = 10_000;
+const DEFAULT_PAGE_LIMIT: u32 = 50;
+const MAX_PAGE_LIMIT: u32 = 200;
+const MAX_SEARCH_LEN: usize = 200;
+const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Todo {
     pub id: i64,
     pub title: String,
+    pub description: Option<String>,
+    /// RFC 3339 timestamp.
+    pub due_date: Option<String>,
+    pub priority: Option<Priority>,
     pub completed: bool,
     pub created_at: String,
+    /// Set when the todo is in the trash; purged after the retention period.
+    pub deleted_at: Option<String>,
+    /// Parent todo when this is a subtask.
+    pub parent_id: Option<i64>,
+    /// List (project) the todo belongs to.
+    pub list_id: Option<i64>,
+    /// When to email the owner a reminder (RFC 3339). Sent once; changing it re-arms the reminder.
+    pub remind_at: Option<String>,
+    /// iCalendar RRULE; completing the todo creates the next occurrence, which takes over the rule.
+    pub rrule: Option<String>,
+    /// First todo of the recurring series this todo was generated from; `None` for the first.
+    pub series_id: Option<i64>,
+    /// Manual sort key (see `utils::position`); new todos go first.
+    pub position: Option<String>,
+    /// Bumped by the database when an editable field changes; served as the `ETag` (see
+    /// `utils::etag`).
+    pub version: i64,
+    /// Set by the database when `completed` becomes true, cleared when it becomes false.
+    pub completed_at: Option<String>,
+    /// Set when the todo was archived; archived todos are left out of `GET /api/todos`.
+    pub archived_at: Option<String>,
+}
+
+/// A todo with its subtasks nested, as returned by `GET /api/todos?tree=true`.
+#[derive(Serialize, Clone)]
+pub struct TodoNode {
+    #[serde(flatten)]
+    pub todo: Todo,
+    pub children: Vec<TodoNode>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AdminTodo {
-    pub id: i64,
-    pub title: String,
-    pub completed: bool,
-    pub created_at: String,
+    #[serde(flatten)]
+    pub todo: Todo,
     pub owner_id: Option<String>,
     pub owner_email: Option<String>,
 }
 
-#[derive(Deserialize)]
+/// One page of `GET /api/todos`. Pass `next_cursor` back as `?cursor=` to fetch the next page.
+#[derive(Serialize)]
+pub struct TodoPage<T = Todo> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Sort column accepted by `GET /api/todos?order=`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TodoOrder {
+    CreatedAt,
+    Title,
+    DueDate,
+    /// Manual order set with `POST /api/todos/:id/move`.
+    Position,
+}
+
+impl TodoOrder {
+    pub fn column(&self) -> &'static str {
+        match self {
+            TodoOrder::CreatedAt => "created_at",
+            TodoOrder::Title => "title",
+            TodoOrder::DueDate => "due_date",
+            TodoOrder::Position => "position",
+        }
+    }
+
+    /// The todo's value in this column, as PostgREST returned it.
+    pub fn value_of(&self, todo: &Todo) -> Option<String> {
+        match self {
+            TodoOrder::CreatedAt => Some(todo.created_at.clone()),
+            TodoOrder::Title => Some(todo.title.clone()),
+            TodoOrder::DueDate => todo.due_date.clone(),
+            TodoOrder::Position => todo.position.clone(),
+        }
+    }
+}
+
+/// Last row of a page; the next page starts right after it in the list order (keyset paging).
+#[derive(Clone, PartialEq, Debug)]
+pub struct PageAfter {
+    /// The row's value in the sort column; `None` if it has none (those rows sort last).
+    pub value: Option<String>,
+    pub id: i64,
+}
+
+/// Query parameters of `GET /api/todos`.
+pub struct ListTodosQuery {
+    pub limit: u32,
+    /// Where the page starts, decoded from the opaque `cursor` parameter.
+    pub after: Option<PageAfter>,
+    pub completed: Option<bool>,
+    /// Case-insensitive title substring.
+    pub search: Option<String>,
+    pub order: TodoOrder,
+    /// Defaults to descending, except for `order=position`.
+    pub ascending: bool,
+    /// Page over top-level todos and nest their subtasks.
+    pub tree: bool,
+    /// Only todos carrying this tag (one of the caller's).
+    pub tag: Option<i64>,
+    /// Only todos in this list (one the caller owns or is a member of).
+    pub list_id: Option<i64>,
+    /// Only todos assigned to the caller (`assigned_to=me`).
+    pub assigned_to_me: bool,
+}
+
+impl ListTodosQuery {
+    /// Parses `?limit=&cursor=&completed=&q=&order=&dir=&tree=&tag=&list_id=&assigned_to=`.
+    /// The error is a message suitable for a 400 response.
+    pub fn from_url(url: &worker::Url) -> Result<Self, String> {
+        let mut query = ListTodosQuery {
+            limit: DEFAULT_PAGE_LIMIT,
+            after: None,
+            completed: None,
+            search: None,
+            order: TodoOrder::CreatedAt,
+            ascending: false,
+            tree: false,
+            tag: None,
+            list_id: None,
+            assigned_to_me: false,
+        };
+        let mut dir_given = false;
+        let mut cursor = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "limit" => {
+                    query.limit = value
+                        .parse()
+                        .ok()
+                        .filter(|l| (1..=MAX_PAGE_LIMIT).contains(l))
+                        .ok_or_else(|| format!("limit must be between 1 and {}", MAX_PAGE_LIMIT))?;
+                }
+                "cursor" => cursor = Some(value.to_string()),
+                "completed" => query.completed = Some(parse_bool("completed", &value)?),
+                "q" => {
+                    let q = value.trim();
+                    if q.chars().count() > MAX_SEARCH_LEN {
+                        return Err(format!("q must be at most {} characters", MAX_SEARCH_LEN));
+                    }
+                    query.search = Some(q.to_string()).filter(|q| !q.is_empty());
+                }
+                "order" => {
+                    query.order = match value.as_ref() {
+                        "created_at" => TodoOrder::CreatedAt,
+                        "title" => TodoOrder::Title,
+                        "due_date" => TodoOrder::DueDate,
+                        "position" => TodoOrder::Position,
+                        _ => {
+                            return Err(
+                                "order must be created_at, title, due_date or position".into()
+                            )
+                        }
+                    };
+                }
+                "dir" => {
+                    dir_given = true;
+                    query.ascending = match value.as_ref() {
+                        "asc" => true,
+                        "desc" => false,
+                        _ => return Err("dir must be asc or desc".into()),
+                    };
+                }
+                "tree" => query.tree = parse_bool("tree", &value)?,
+                "tag" => {
+                    query.tag = Some(value.parse().map_err(|_| "tag must be a tag id")?);
+                }
+                "list_id" => {
+                    query.list_id = Some(value.parse().map_err(|_| "list_id must be a list id")?);
+                }
+                "assigned_to" => {
+                    if value != "me" {
+                        return Err("assigned_to must be me".into());
+                    }
+                    query.assigned_to_me = true;
+                }
+                _ => {}
+            }
+        }
+        if !dir_given && query.order == TodoOrder::Position {
+            query.ascending = true;
+        }
+        if let Some(cursor) = cursor {
+            query.after = Some(query.decode_cursor(&cursor)?);
+        }
+        Ok(query)
+    }
+
+    /// Cursor of the page that follows `last`. It carries `last`'s sort value and id, and a
+    /// fingerprint of the filters and order, so it cannot be replayed against another query.
+    pub fn encode_cursor(&self, last: &Todo) -> String {
+        let payload = serde_json::json!([self.fingerprint(), self.order.value_of(last), last.id]);
+        to_hex(payload.to_string().as_bytes())
+    }
+
+    fn decode_cursor(&self, cursor: &str) -> Result<PageAfter, String> {
+        let (fingerprint, value, id): (String, Option<String>, i64) = from_hex(cursor)
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or("Invalid cursor")?;
+        if fingerprint != self.fingerprint() {
+            return Err(
+                "cursor belongs to a different query; keep the same filters and order".into(),
+            );
+        }
+        Ok(PageAfter { value, id })
+    }
+
+    /// Short hash of everything that decides which rows a page holds, apart from the cursor.
+    fn fingerprint(&self) -> String {
+        let params = format!(
+            "{}|{}|{:?}|{:?}|{:?}|{:?}|{}|{}",
+            self.order.column(),
+            self.ascending,
+            self.completed,
+            self.search,
+            self.tag,
+            self.list_id,
+            self.assigned_to_me,
+            self.tree
+        );
+        to_hex(&Sha256::digest(params.as_bytes())[..8])
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("{} must be true or false", name)),
+    }
+}
+
+#[derive(Deserialize, Serialize)]
 pub struct CreateTodo {
+    /// Set by `POST /api/todos/:id/children`; never read from the request body.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
     pub title: String,
+    /// List to create the todo in; the caller must own it or be a member.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remind_at: Option<String>,
+    /// iCalendar RRULE making the todo recurring (see `Recurrence`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rrule: Option<String>,
+    /// Set when generating the next occurrence of a series; never read from the request body.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<i64>,
+    /// Set on insert; never read from the request body.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    /// Set by imports, which create completed todos as they are (without starting the next
+    /// occurrence of a recurring one); never read from the request body.
+    #[serde(skip_deserializing, skip_serializing_if = "std::ops::Not::not")]
+    pub completed: bool,
 }
 
-#[derive(Deserialize)]
+impl CreateTodo {
+    /// Checks every field; the error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_title(&self.title)?;
+        if let Some(d) = &self.description {
+            validate_description(d)?;
+        }
+        if let Some(d) = &self.due_date {
+            validate_due_date(d)?;
+        }
+        if let Some(r) = &self.remind_at {
+            validate_remind_at(r)?;
+        }
+        if let Some(r) = &self.rrule {
+            Recurrence::parse(r)?;
+        }
+        Ok(())
+    }
+}
+
+/// PATCH body. Every field is optional: absent leaves it unchanged, `null` clears it.
+/// `title` and `completed` cannot be cleared.
+#[derive(Deserialize, Default)]
 pub struct UpdateTodo {
-    pub completed: bool,
+    #[serde(default, deserialize_with = "nullable")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub due_date: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub priority: Option<Option<Priority>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub completed: Option<Option<bool>>,
+    /// Move under another todo, or `null` to make it top-level again.
+    #[serde(default, deserialize_with = "nullable")]
+    pub parent_id: Option<Option<i64>>,
+    /// Move to another list, or `null` to take it out of its list.
+    #[serde(default, deserialize_with = "nullable")]
+    pub list_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub remind_at: Option<Option<String>>,
+}
+
+/// Query parameters of `PATCH /api/todos/:id`.
+#[derive(Default)]
+pub struct UpdateTodoOptions {
+    /// When completing a todo, also complete all of its subtasks.
+    pub cascade: bool,
+    /// Version from the client's `If-Match`; the update fails with `Precondition Failed` unless the
+    /// todo is still at this version.
+    pub if_match: Option<i64>,
+    /// Complete the todo even if some of its blockers are still open.
+    pub force: bool,
+}
+
+impl UpdateTodoOptions {
+    /// Parses `?cascade=&force=`. The error is a message suitable for a 400 response.
+    pub fn from_url(url: &worker::Url) -> Result<Self, String> {
+        let mut options = UpdateTodoOptions::default();
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "cascade" => options.cascade = parse_bool("cascade", &value)?,
+                "force" => options.force = parse_bool("force", &value)?,
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+}
+
+impl UpdateTodo {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.due_date.is_none()
+            && self.priority.is_none()
+            && self.completed.is_none()
+            && self.parent_id.is_none()
+            && self.list_id.is_none()
+            && self.remind_at.is_none()
+    }
+
+    /// True if `completed` is the only field set, the one change an assignee may make.
+    pub fn only_completed(&self) -> bool {
+        self.completed.is_some()
+            && self.title.is_none()
+            && self.description.is_none()
+            && self.due_date.is_none()
+            && self.priority.is_none()
+            && self.parent_id.is_none()
+            && self.list_id.is_none()
+            && self.remind_at.is_none()
+    }
+
+    /// Checks every present field; the error is a message suitable for a 400 response.
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.title {
+            Some(Some(t)) => validate_title(t)?,
+            Some(None) => return Err("Title cannot be null".into()),
+            None => {}
+        }
+        if let Some(Some(d)) = &self.description {
+            validate_description(d)?;
+        }
+        if let Some(Some(d)) = &self.due_date {
+            validate_due_date(d)?;
+        }
+        if let Some(None) = self.completed {
+            return Err("Completed cannot be null".into());
+        }
+        if let Some(Some(r)) = &self.remind_at {
+            validate_remind_at(r)?;
+        }
+        Ok(())
+    }
+
+    /// Supabase PATCH body holding only the fields present in the request.
+    pub fn to_patch_body(&self) -> serde_json::Value {
+        let mut body = serde_json::Map::new();
+        if let Some(t) = &self.title {
+            body.insert(
+                "title".into(),
+                serde_json::json!(t.as_deref().map(str::trim)),
+            );
+        }
+        if let Some(d) = &self.description {
+            body.insert("description".into(), serde_json::json!(d));
+        }
+        if let Some(d) = &self.due_date {
+            body.insert("due_date".into(), serde_json::json!(d));
+        }
+        if let Some(p) = &self.priority {
+            body.insert("priority".into(), serde_json::json!(p));
+        }
+        if let Some(c) = &self.completed {
+            body.insert("completed".into(), serde_json::json!(c));
+        }
+        if let Some(p) = &self.parent_id {
+            body.insert("parent_id".into(), serde_json::json!(p));
+        }
+        if let Some(l) = &self.list_id {
+            body.insert("list_id".into(), serde_json::json!(l));
+        }
+        if let Some(r) = &self.remind_at {
+            body.insert("remind_at".into(), serde_json::json!(r));
+            // A new reminder time is a new reminder: let the job send it again.
+            body.insert("reminded_at".into(), serde_json::Value::Null);
+        }
+        serde_json::Value::Object(body)
+    }
+}
+
+/// Body of `POST /api/todos/:id/move`: place the todo right after `after` and/or right before
+/// `before`. Pass both (the neighbours above and below in the client's view) when known.
+#[derive(Deserialize)]
+pub struct MoveTodo {
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+}
+
+impl MoveTodo {
+    /// The error is a message suitable for a 400 response.
+    pub fn validate(&self, id: i64) -> Result<(), String> {
+        if self.before.is_none() && self.after.is_none() {
+            return Err("before or after is required".into());
+        }
+        if self.before == Some(id) || self.after == Some(id) {
+            return Err("A todo cannot be moved relative to itself".into());
+        }
+        if self.before.is_some() && self.before == self.after {
+            return Err("before and after must be different todos".into());
+        }
+        Ok(())
+    }
+}
+
+/// One operation of `POST /api/todos/batch`, e.g. `{ "op": "retitle", "id": 1, "title": "..." }`.
+#[derive(Deserialize, Clone)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BatchOperation {
+    Complete { id: i64 },
+    Uncomplete { id: i64 },
+    Delete { id: i64 },
+    Retitle { id: i64, title: String },
+}
+
+impl BatchOperation {
+    pub fn id(&self) -> i64 {
+        match self {
+            BatchOperation::Complete { id }
+            | BatchOperation::Uncomplete { id }
+            | BatchOperation::Delete { id }
+            | BatchOperation::Retitle { id, .. } => *id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BatchOperation::Complete { .. } => "complete",
+            BatchOperation::Uncomplete { .. } => "uncomplete",
+            BatchOperation::Delete { .. } => "delete",
+            BatchOperation::Retitle { .. } => "retitle",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BatchRequest {
+    pub operations: Vec<BatchOperation>,
+}
+
+impl BatchRequest {
+    /// Checks the batch as a whole; the error is a message suitable for a 400 response.
+    /// Each todo may appear at most once so the outcome does not depend on operation order.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.operations.is_empty() {
+            return Err("operations must not be empty".into());
+        }
+        if self.operations.len() > MAX_BATCH_SIZE {
+            return Err(format!("At most {} operations per batch", MAX_BATCH_SIZE));
+        }
+        let mut seen = std::collections::HashSet::new();
+        for op in &self.operations {
+            if !seen.insert(op.id()) {
+                return Err(format!("Todo {} appears more than once", op.id()));
+            }
+            if let BatchOperation::Retitle { title, .. } = op {
+                validate_title(title)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one batch operation. `status` is the HTTP status the single-todo route would have
+/// returned; `todo` is the updated todo for everything but `delete`.
+#[derive(Serialize)]
+pub struct BatchResult {
+    pub id: i64,
+    pub op: &'static str,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo: Option<Todo>,
+}
+
+fn validate_title(title: &str) -> Result<(), String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err("Title is required".into());
+    }
+    if title.chars().count() > MAX_TITLE_LEN {
+        return Err(format!(
+            "Title must be at most {} characters",
+            MAX_TITLE_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn validate_description(description: &str) -> Result<(), String> {
+    if description.chars().count() > MAX_DESCRIPTION_LEN {
+        return Err(format!(
+            "Description must be at most {} characters",
+            MAX_DESCRIPTION_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn validate_due_date(due_date: &str) -> Result<(), String> {
+    chrono::DateTime::parse_from_rfc3339(due_date)
+        .map(|_| ())
+        .map_err(|_| "Due date must be an RFC 3339 timestamp".into())
+}
+
+fn validate_remind_at(remind_at: &str) -> Result<(), String> {
+    chrono::DateTime::parse_from_rfc3339(remind_at)
+        .map(|_| ())
+        .map_err(|_| "Remind at must be an RFC 3339 timestamp".into())
+}
+
+/// Relation a todo can be shared with. Owners are set on create and cannot be granted here.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareRelation {
+    Viewer,
+    Editor,
+}
+
+impl ShareRelation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareRelation::Viewer => "viewer",
+            ShareRelation::Editor => "editor",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ShareTodo {
+    pub user_id: String,
+    pub relation: ShareRelation,
+}
+
+#[derive(Deserialize)]
+pub struct UnshareTodo {
+    pub user_id: String,
+    /// When omitted, every share relation the user holds on the todo is removed.
+    pub relation: Option<ShareRelation>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TodoShare {
+    pub todo_id: i64,
+    pub user_id: String,
+    pub relation: ShareRelation,
+}
+
+/// Body of `PUT /api/todos/:id/assignee`.
+#[derive(Deserialize)]
+pub struct AssignTodo {
+    pub user_id: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TodoAssignee {
+    pub todo_id: i64,
+    pub user_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(params: &str) -> Result<ListTodosQuery, String> {
+        let url = worker::Url::parse(&format!("https://example.com/api/todos?{}", params)).unwrap();
+        ListTodosQuery::from_url(&url)
+    }
+
+    fn todo(id: i64, title: &str, due_date: Option<&str>) -> Todo {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": title,
+            "description": null,
+            "due_date": due_date,
+            "priority": null,
+            "completed": false,
+            "created_at": "2026-10-01T12:00:00+00:00",
+            "deleted_at": null,
+            "parent_id": null,
+            "list_id": null,
+            "remind_at": null,
+            "rrule": null,
+            "series_id": null,
+            "position": null,
+            "version": 1,
+            "completed_at": null,
+            "archived_at": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let params = "order=title&dir=asc&q=rent&completed=false&limit=10";
+        let first = query(params).unwrap();
+        assert_eq!(first.after, None);
+
+        let cursor = first.encode_cursor(&todo(42, "Pay rent, (soon) \"really\"", None));
+        let next = query(&format!("{}&cursor={}", params, cursor)).unwrap();
+        assert_eq!(
+            next.after,
+            Some(PageAfter {
+                value: Some("Pay rent, (soon) \"really\"".into()),
+                id: 42,
+            })
+        );
+    }
+
+    #[test]
+    fn cursor_keeps_rows_without_a_sort_value() {
+        let params = "order=due_date";
+        let cursor = query(params)
+            .unwrap()
+            .encode_cursor(&todo(7, "No due date", None));
+        let next = query(&format!("{}&cursor={}", params, cursor)).unwrap();
+        assert_eq!(next.after, Some(PageAfter { value: None, id: 7 }));
+    }
+
+    #[test]
+    fn rows_with_the_same_sort_value_get_different_cursors() {
+        // Keyset paging breaks ties on id, so two rows with the same title resume at different
+        // places.
+        let q = query("order=title").unwrap();
+        let a = q.encode_cursor(&todo(1, "Same", None));
+        let b = q.encode_cursor(&todo(2, "Same", None));
+        assert_ne!(a, b);
+        let after = |cursor: &str| {
+            query(&format!("order=title&cursor={}", cursor))
+                .unwrap()
+                .after
+                .unwrap()
+        };
+        assert_eq!(after(&a).id, 1);
+        assert_eq!(after(&b).id, 2);
+        assert_eq!(after(&a).value, after(&b).value);
+    }
+
+    #[test]
+    fn rejects_tampered_cursors() {
+        let q = query("order=title").unwrap();
+        let cursor = q.encode_cursor(&todo(42, "Pay rent", None));
+        let payload: serde_json::Value =
+            serde_json::from_slice(&from_hex(&cursor).unwrap()).unwrap();
+        let reencode = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut payload = payload.clone();
+            edit(&mut payload);
+            to_hex(payload.to_string().as_bytes())
+        };
+
+        let forged_fingerprint = reencode(&|p| p[0] = "0000000000000000".into());
+        let id_as_text = reencode(&|p| p[2] = "42".into());
+        let missing_id = reencode(&|p| {
+            p.as_array_mut().unwrap().pop();
+        });
+        for bad in [
+            forged_fingerprint.as_str(),
+            &id_as_text,
+            &missing_id,
+            &cursor[..cursor.len() - 1],
+            &cursor[2..],
+            "zz",
+            "not hex at all",
+            "",
+        ] {
+            assert!(
+                query(&format!("order=title&cursor={}", bad)).is_err(),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_cursors_from_a_different_query() {
+        let cursor = query("order=title&q=rent")
+            .unwrap()
+            .encode_cursor(&todo(42, "Pay rent", None));
+        for other in [
+            "order=title&q=tax",
+            "order=title&q=rent&dir=asc",
+            "order=created_at&q=rent",
+            "order=title&q=rent&completed=true",
+            "order=title&q=rent&tag=3",
+            "order=title&q=rent&list_id=3",
+            "order=title&q=rent&assigned_to=me",
+            "order=title&q=rent&tree=true",
+        ] {
+            let err = query(&format!("{}&cursor={}", other, cursor)).err();
+            assert_eq!(
+                err.as_deref(),
+                Some("cursor belongs to a different query; keep the same filters and order"),
+                "{}",
+                other
+            );
+        }
+        // The page size is not part of the query's identity.
+        assert!(query(&format!("order=title&q=rent&limit=5&cursor={}", cursor)).is_ok());
+    }
 }
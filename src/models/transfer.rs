@@ -0,0 +1,384 @@
+use crate::models::{CreateTodo, Priority, Todo};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const MAX_IMPORT_ROWS: usize = 200;
+
+/// File format of `GET /api/todos/export` and `POST /api/todos/import`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TransferFormat {
+    Json,
+    Csv,
+}
+
+impl TransferFormat {
+    /// Parses `?format=json|csv` (default `json`). The error is a message suitable for a 400
+    /// response.
+    pub fn from_url(url: &worker::Url) -> Result<Self, String> {
+        match url.query_pairs().find(|(k, _)| k == "format") {
+            None => Ok(TransferFormat::Json),
+            Some((_, v)) if v == "json" => Ok(TransferFormat::Json),
+            Some((_, v)) if v == "csv" => Ok(TransferFormat::Csv),
+            Some(_) => Err("format must be json or csv".into()),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "application/json",
+            TransferFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TransferFormat::Json => "json",
+            TransferFormat::Csv => "csv",
+        }
+    }
+
+    /// Start of an export file: `[` or the CSV header row.
+    pub fn header(&self) -> Vec<u8> {
+        match self {
+            TransferFormat::Json => b"[".to_vec(),
+            TransferFormat::Csv => format!("{}\n", CSV_COLUMNS.join(",")).into_bytes(),
+        }
+    }
+
+    /// Encodes one page of an export. `first` is true if no record has been written before, so
+    /// JSON knows whether to lead with a comma.
+    pub fn encode(&self, records: &[TodoRecord], first: bool) -> Result<Vec<u8>, String> {
+        match self {
+            TransferFormat::Json => {
+                let mut out = Vec::new();
+                for (i, record) in records.iter().enumerate() {
+                    if !(first && i == 0) {
+                        out.push(b',');
+                    }
+                    out.push(b'\n');
+                    serde_json::to_writer(&mut out, record).map_err(|e| e.to_string())?;
+                }
+                Ok(out)
+            }
+            TransferFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                for record in records {
+                    writer.serialize(record).map_err(|e| e.to_string())?;
+                }
+                writer.into_inner().map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// End of an export file: `]` for JSON, nothing for CSV.
+    pub fn footer(&self) -> Vec<u8> {
+        match self {
+            TransferFormat::Json => b"\n]\n".to_vec(),
+            TransferFormat::Csv => Vec::new(),
+        }
+    }
+}
+
+/// CSV header, in the field order of `TodoRecord`.
+const CSV_COLUMNS: [&str; 9] = [
+    "id",
+    "title",
+    "description",
+    "due_date",
+    "priority",
+    "completed",
+    "parent_id",
+    "remind_at",
+    "rrule",
+];
+
+/// A todo as written by the export and read by the import. The columns are the same in both
+/// formats; in CSV an empty cell is `null`. `id` and `parent_id` are ids in the exporting account,
+/// only used to rebuild subtasks on import.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TodoRecord {
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub due_date: Option<String>,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub completed: bool,
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub remind_at: Option<String>,
+    #[serde(default)]
+    pub rrule: Option<String>,
+}
+
+impl TodoRecord {
+    pub fn from_todo(todo: &Todo) -> Self {
+        TodoRecord {
+            id: Some(todo.id),
+            title: todo.title.clone(),
+            description: todo.description.clone(),
+            due_date: todo.due_date.clone(),
+            priority: todo.priority,
+            completed: todo.completed,
+            parent_id: todo.parent_id,
+            remind_at: todo.remind_at.clone(),
+            rrule: todo.rrule.clone(),
+        }
+    }
+
+    /// Body for `TodoRepo::create`; `parent_id` is applied afterwards.
+    pub fn to_create(&self) -> CreateTodo {
+        CreateTodo {
+            parent_id: None,
+            title: self.title.clone(),
+            list_id: None,
+            description: self.description.clone(),
+            due_date: self.due_date.clone(),
+            priority: self.priority,
+            remind_at: self.remind_at.clone(),
+            rrule: self.rrule.clone(),
+            series_id: None,
+            position: None,
+            completed: self.completed,
+        }
+    }
+
+    /// Checks every field the same way `POST /api/todos` does.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.is_some() && self.parent_id == self.id {
+            return Err("A todo cannot be its own parent".into());
+        }
+        self.to_create().validate()
+    }
+
+    /// Identifies the row across uploads: the SHA-256 of its content, hex encoded. Importing a row
+    /// whose key the user has imported before is skipped.
+    pub fn import_key(&self) -> String {
+        let canonical = serde_json::to_vec(self).unwrap_or_default();
+        Sha256::digest(&canonical)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Reads an import file. Each row is parsed on its own so one bad row does not reject the
+    /// file; the outer error (unreadable file, too many rows) is a message for a 400 response.
+    pub fn parse_file(
+        format: TransferFormat,
+        body: &[u8],
+    ) -> Result<Vec<Result<TodoRecord, String>>, String> {
+        let rows: Vec<Result<TodoRecord, String>> = match format {
+            TransferFormat::Json => {
+                let values: Vec<serde_json::Value> = serde_json::from_slice(body)
+                    .map_err(|_| "Import must be a JSON array of todos".to_string())?;
+                values
+                    .into_iter()
+                    .map(|v| serde_json::from_value(v).map_err(|e| e.to_string()))
+                    .collect()
+            }
+            TransferFormat::Csv => csv::Reader::from_reader(body)
+                .deserialize()
+                .map(|r| r.map_err(|e| csv_error(&e)))
+                .collect(),
+        };
+        if rows.is_empty() {
+            return Err("Import file has no todos".into());
+        }
+        if rows.len() > MAX_IMPORT_ROWS {
+            return Err(format!("At most {} todos per import", MAX_IMPORT_ROWS));
+        }
+        Ok(rows)
+    }
+}
+
+/// CSV error without the position, which the report already gives as the row number.
+fn csv_error(e: &csv::Error) -> String {
+    match e.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+        _ => e.to_string(),
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    /// A new todo was created.
+    Created,
+    /// The row was imported before (or earlier in the same file); `todo_id` is that todo.
+    Skipped,
+    /// Nothing was created; see `error`.
+    Failed,
+}
+
+/// Outcome of one row. `row` counts from 1, not including a CSV header. A created row can still
+/// carry an `error` if its completion or parent could not be applied.
+#[derive(Serialize)]
+pub struct ImportRowResult {
+    pub row: usize,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response of `POST /api/todos/import`.
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub created: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRowResult>,
+}
+
+impl ImportReport {
+    pub fn new(rows: Vec<ImportRowResult>) -> Self {
+        let count = |s: ImportStatus| rows.iter().filter(|r| r.status == s).count();
+        ImportReport {
+            created: count(ImportStatus::Created),
+            skipped: count(ImportStatus::Skipped),
+            failed: count(ImportStatus::Failed),
+            rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records() -> Vec<TodoRecord> {
+        vec![
+            TodoRecord {
+                id: Some(7),
+                title: "Pay rent, \"on time\"".into(),
+                description: Some("Line one\nline two".into()),
+                due_date: Some("2026-11-01T09:00:00Z".into()),
+                priority: Some(Priority::High),
+                completed: false,
+                parent_id: None,
+                remind_at: Some("2026-10-31T09:00:00Z".into()),
+                rrule: Some("FREQ=MONTHLY".into()),
+            },
+            TodoRecord {
+                id: Some(8),
+                title: "Find the receipt".into(),
+                description: None,
+                due_date: None,
+                priority: None,
+                completed: true,
+                parent_id: Some(7),
+                remind_at: None,
+                rrule: None,
+            },
+        ]
+    }
+
+    /// A whole export file of `records`, written in two pages like the streamed export.
+    fn export(format: TransferFormat, records: &[TodoRecord]) -> Vec<u8> {
+        let (first, rest) = records.split_at(1);
+        let mut out = format.header();
+        out.extend(format.encode(first, true).unwrap());
+        out.extend(format.encode(rest, false).unwrap());
+        out.extend(format.footer());
+        out
+    }
+
+    fn import(format: TransferFormat, body: &[u8]) -> Vec<TodoRecord> {
+        TodoRecord::parse_file(format, body)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    fn as_json(records: &[TodoRecord]) -> serde_json::Value {
+        serde_json::to_value(records).unwrap()
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        for format in [TransferFormat::Json, TransferFormat::Csv] {
+            let original = records();
+            let imported = import(format, &export(format, &original));
+            assert_eq!(as_json(&imported), as_json(&original), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn import_key_is_stable_across_export_and_import() {
+        for format in [TransferFormat::Json, TransferFormat::Csv] {
+            let original = records();
+            let imported = import(format, &export(format, &original));
+            for (a, b) in original.iter().zip(&imported) {
+                assert_eq!(a.import_key(), b.import_key(), "{:?}", format);
+                assert_eq!(a.import_key().len(), 64);
+            }
+            assert_ne!(original[0].import_key(), original[1].import_key());
+        }
+    }
+
+    #[test]
+    fn empty_csv_cells_are_null() {
+        let body = b"id,title,description,due_date,priority,completed,parent_id,remind_at,rrule\n\
+                     ,Water plants,,,,false,,,\n";
+        let rows = import(TransferFormat::Csv, body);
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.title, "Water plants");
+        assert_eq!(row.id, None);
+        assert_eq!(row.description, None);
+        assert_eq!(row.due_date, None);
+        assert_eq!(row.priority, None);
+        assert_eq!(row.parent_id, None);
+        assert_eq!(row.remind_at, None);
+        assert_eq!(row.rrule, None);
+    }
+
+    #[test]
+    fn bad_rows_are_reported_one_by_one() {
+        let body = br#"[{"title": "Ok"}, {"description": "no title"}]"#;
+        let rows = TodoRecord::parse_file(TransferFormat::Json, body).unwrap();
+        assert!(rows[0].is_ok());
+        assert!(rows[1].is_err());
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_files() {
+        let empty = "Import file has no todos";
+        assert_eq!(
+            TodoRecord::parse_file(TransferFormat::Json, b"[]").unwrap_err(),
+            empty
+        );
+        let header = TransferFormat::Csv.header();
+        assert_eq!(
+            TodoRecord::parse_file(TransferFormat::Csv, &header).unwrap_err(),
+            empty
+        );
+        assert_eq!(
+            TodoRecord::parse_file(TransferFormat::Json, b"{}").unwrap_err(),
+            "Import must be a JSON array of todos"
+        );
+
+        let row = |i: usize| serde_json::json!({ "title": format!("Todo {}", i) });
+        let at_limit = serde_json::to_vec(&(0..MAX_IMPORT_ROWS).map(row).collect::<Vec<_>>());
+        let over = serde_json::to_vec(&(0..=MAX_IMPORT_ROWS).map(row).collect::<Vec<_>>());
+        assert_eq!(
+            TodoRecord::parse_file(TransferFormat::Json, &at_limit.unwrap())
+                .unwrap()
+                .len(),
+            MAX_IMPORT_ROWS
+        );
+        assert_eq!(
+            TodoRecord::parse_file(TransferFormat::Json, &over.unwrap()).unwrap_err(),
+            format!("At most {} todos per import", MAX_IMPORT_ROWS)
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use crate::db::SupabaseClient;
+use crate::middleware::logging;
+use crate::models::{Activity, NewActivity};
+use crate::repositories::TodoRepo;
+use crate::utils::context::AppContext;
+use worker::*;
+
+const ACTIVITY_COLUMNS: &str = "id,todo_id,actor_id,action,changes,created_at";
+
+/// Append-only history of changes to todos (`todo_activity`). Rows outlive their todo so admin
+/// deletes and purges stay on record.
+pub struct ActivityRepo;
+
+impl ActivityRepo {
+    /// Append activity entries in one insert. History is best effort: failures are logged and never
+    /// fail the change being recorded.
+    pub async fn record(ctx: &AppContext, entries: Vec<NewActivity>) {
+        if entries.is_empty() {
+            return;
+        }
+        let result = match (
+            SupabaseClient::from_env(ctx),
+            serde_json::to_value(&entries),
+        ) {
+            (Ok(db), Ok(body)) => db.post("todo_activity", body).await.map(|_| ()),
+            (Err(e), _) => Err(e),
+            (_, Err(e)) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            logging::log_error(&format!("record activity: {}", e));
+        }
+    }
+
+    /// History of a todo the user can read, newest first.
+    pub async fn list(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<Vec<Activity>> {
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select={}&todo_id=eq.{}&order=created_at.desc,id.desc",
+            ACTIVITY_COLUMNS, todo_id
+        );
+        match db.get("todo_activity", &query).await? {
+            serde_json::Value::Array(arr) => {
+                Ok(serde_json::from_value(serde_json::Value::Array(arr))?)
+            }
+            other => Err(Error::RustError(format!("Expected array, got: {}", other))),
+        }
+    }
+}
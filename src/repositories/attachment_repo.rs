@@ -0,0 +1,221 @@
+use crate::db::supabase::encode_value;
+use crate::db::SupabaseClient;
+use crate::middleware::logging;
+use crate::models::Attachment;
+use crate::repositories::common::parse_rows;
+use crate::repositories::TodoRepo;
+use crate::utils::context::AppContext;
+use sha2::{Digest, Sha256};
+use worker::*;
+
+/// R2 bucket binding in `wrangler.toml`.
+const BUCKET_BINDING: &str = "ATTACHMENTS";
+
+const ATTACHMENT_COLUMNS: &str =
+    "id,todo_id,file_name,content_type,size,checksum,uploaded_by,created_at,object_key";
+
+/// Most keys R2 deletes in one call.
+const R2_DELETE_BATCH: usize = 1000;
+
+const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_USER_QUOTA_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Byte limit read from the env var `name`, or `default` if unset or invalid.
+fn env_limit(ctx: &AppContext, name: &str, default: u64) -> u64 {
+    ctx.env
+        .var(name)
+        .ok()
+        .and_then(|v| v.to_string().parse().ok())
+        .unwrap_or(default)
+}
+
+fn bucket(ctx: &AppContext) -> Result<Bucket> {
+    ctx.env.bucket(BUCKET_BINDING)
+}
+
+async fn fetch_attachment(db: &SupabaseClient, todo_id: i64, id: i64) -> Result<Attachment> {
+    let query = format!(
+        "select={}&id=eq.{}&todo_id=eq.{}",
+        ATTACHMENT_COLUMNS, id, todo_id
+    );
+    parse_rows(db.get("todo_attachments", &query).await?)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::RustError("Attachment not found".into()))
+}
+
+/// Bytes the user has uploaded across all todos.
+async fn used_bytes(db: &SupabaseClient, user_id: &str) -> Result<u64> {
+    let query = format!("select=size&uploaded_by=eq.{}", encode_value(user_id));
+    Ok(db
+        .get("todo_attachments", &query)
+        .await?
+        .as_array()
+        .map(|rows| {
+            rows.iter()
+                .filter_map(|r| r.get("size").and_then(|s| s.as_u64()))
+                .sum()
+        })
+        .unwrap_or_default())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Files on todos. Content lives in R2, metadata in Supabase (`todo_attachments`). Every
+/// operation needs the same write access (owner or editor) as `TodoRepo::update`.
+pub struct AttachmentRepo;
+
+impl AttachmentRepo {
+    /// Largest accepted file, from `ATTACHMENT_MAX_BYTES` (default 10 MiB).
+    pub fn max_file_bytes(ctx: &AppContext) -> u64 {
+        env_limit(ctx, "ATTACHMENT_MAX_BYTES", DEFAULT_MAX_FILE_BYTES)
+    }
+
+    /// Total bytes a user may upload, from `ATTACHMENT_QUOTA_BYTES` (default 100 MiB).
+    fn user_quota_bytes(ctx: &AppContext) -> u64 {
+        env_limit(ctx, "ATTACHMENT_QUOTA_BYTES", DEFAULT_USER_QUOTA_BYTES)
+    }
+
+    /// Attachments of a todo, oldest first.
+    pub async fn list(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<Vec<Attachment>> {
+        TodoRepo::ensure_writable(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select={}&todo_id=eq.{}&order=created_at.asc,id.asc",
+            ATTACHMENT_COLUMNS, todo_id
+        );
+        parse_rows(db.get("todo_attachments", &query).await?)
+    }
+
+    /// Store a file on a todo. Rejected with `Payload Too Large` if it exceeds the per-file limit
+    /// or would take the uploader over their quota.
+    pub async fn upload(
+        ctx: &AppContext,
+        user_id: &str,
+        todo_id: i64,
+        file_name: &str,
+        content_type: &str,
+        content: Vec<u8>,
+    ) -> Result<Attachment> {
+        TodoRepo::ensure_writable(ctx, user_id, todo_id).await?;
+
+        let size = content.len() as u64;
+        let max = Self::max_file_bytes(ctx);
+        if size > max {
+            return Err(Error::RustError(format!(
+                "Payload Too Large: Attachments must be at most {} bytes",
+                max
+            )));
+        }
+        let db = SupabaseClient::from_env(ctx)?;
+        let quota = Self::user_quota_bytes(ctx);
+        if used_bytes(&db, user_id).await? + size > quota {
+            return Err(Error::RustError(format!(
+                "Payload Too Large: Upload would exceed your {} byte attachment quota",
+                quota
+            )));
+        }
+
+        let digest = Sha256::digest(&content);
+        let object_key = format!("todos/{}/{}", todo_id, uuid::Uuid::new_v4());
+        bucket(ctx)?
+            .put(&object_key, content)
+            .http_metadata(HttpMetadata {
+                content_type: Some(content_type.to_string()),
+                ..Default::default()
+            })
+            .sha256(digest.to_vec())
+            .execute()
+            .await?;
+
+        let row = serde_json::json!({
+            "todo_id": todo_id,
+            "file_name": file_name,
+            "content_type": content_type,
+            "size": size,
+            "checksum": to_hex(&digest),
+            "uploaded_by": user_id,
+            "object_key": object_key,
+        });
+        match db.post("todo_attachments", row).await {
+            Ok(json_value) => parse_rows(json_value)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::RustError("No attachment returned".into())),
+            Err(e) => {
+                // Do not leave an object behind that no row points to.
+                if let Err(e) = bucket(ctx)?.delete(&object_key).await {
+                    logging::log_error(&format!("r2 delete {}: {}", object_key, e));
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Metadata and content of an attachment.
+    pub async fn download(
+        ctx: &AppContext,
+        user_id: &str,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<(Attachment, Vec<u8>)> {
+        TodoRepo::ensure_writable(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let attachment = fetch_attachment(&db, todo_id, id).await?;
+        let object = bucket(ctx)?
+            .get(&attachment.object_key)
+            .execute()
+            .await?
+            .ok_or_else(|| Error::RustError("Attachment not found".into()))?;
+        let content = match object.body() {
+            Some(body) => body.bytes().await?,
+            None => Vec::new(),
+        };
+        Ok((attachment, content))
+    }
+
+    /// Delete an attachment's object and metadata.
+    pub async fn delete(ctx: &AppContext, user_id: &str, todo_id: i64, id: i64) -> Result<()> {
+        TodoRepo::ensure_writable(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let attachment = fetch_attachment(&db, todo_id, id).await?;
+        bucket(ctx)?.delete(&attachment.object_key).await?;
+        db.delete("todo_attachments", attachment.id).await
+    }
+
+    /// Delete the R2 objects of every attachment on `todo_ids`, before the todos are removed for
+    /// good (their metadata rows go with them via `on delete cascade`).
+    pub async fn delete_for_todos(ctx: &AppContext, todo_ids: &[i64]) -> Result<()> {
+        if todo_ids.is_empty() {
+            return Ok(());
+        }
+        let db = SupabaseClient::from_env(ctx)?;
+        let ids = todo_ids
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let query = format!("select=object_key&todo_id=in.({})", ids);
+        let keys: Vec<String> = db
+            .get("todo_attachments", &query)
+            .await?
+            .as_array()
+            .map(|rows| {
+                rows.iter()
+                    .filter_map(|r| r.get("object_key").and_then(|k| k.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let bucket = bucket(ctx)?;
+        for chunk in keys.chunks(R2_DELETE_BATCH) {
+            bucket.delete_multiple(chunk.to_vec()).await?;
+        }
+        Ok(())
+    }
+}
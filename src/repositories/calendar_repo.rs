@@ -0,0 +1,93 @@
+use crate::db::supabase::encode_value;
+use crate::db::SupabaseClient;
+use crate::utils::{context::AppContext, time};
+use sha2::{Digest, Sha256};
+use worker::*;
+
+/// Length of a feed token: 64 hex characters.
+const TOKEN_LEN: usize = 64;
+
+fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Compares without returning early, so the time taken does not depend on where the inputs differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Secret calendar feed URLs, one per user. Only the SHA-256 of a token is stored
+/// (`calendar_feeds.token_hash`); the token itself is shown once, when it is created.
+pub struct CalendarRepo;
+
+impl CalendarRepo {
+    /// Create the user's feed token, replacing (and so revoking) any previous one.
+    pub async fn rotate(ctx: &AppContext, user_id: &str) -> Result<String> {
+        // Two v4 UUIDs: 244 random bits from the platform CSPRNG.
+        let token = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let db = SupabaseClient::from_env(ctx)?;
+        db.upsert(
+            "calendar_feeds",
+            "user_id",
+            serde_json::json!({
+                "user_id": user_id,
+                "token_hash": sha256_hex(&token),
+                "created_at": time::now_rfc3339(),
+            }),
+        )
+        .await?;
+        Ok(token)
+    }
+
+    /// Turn the user's feed off. Not an error if there is none.
+    pub async fn revoke(ctx: &AppContext, user_id: &str) -> Result<()> {
+        let db = SupabaseClient::from_env(ctx)?;
+        db.delete_where(
+            "calendar_feeds",
+            &format!("user_id=eq.{}", encode_value(user_id)),
+        )
+        .await
+    }
+
+    /// The user a feed token belongs to, or `None` for an unknown token. The lookup is by the
+    /// token's hash, so its timing reveals nothing usable about valid tokens, and the stored hash
+    /// is compared in constant time.
+    pub async fn user_for_token(ctx: &AppContext, token: &str) -> Result<Option<String>> {
+        if token.len() != TOKEN_LEN || !token.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        let hash = sha256_hex(&token.to_ascii_lowercase());
+        let db = SupabaseClient::from_env(ctx)?;
+        let rows = db
+            .get(
+                "calendar_feeds",
+                &format!("select=user_id,token_hash&token_hash=eq.{}", hash),
+            )
+            .await?;
+        let row = match rows.as_array().and_then(|r| r.first()) {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let stored = row
+            .get("token_hash")
+            .and_then(|h| h.as_str())
+            .unwrap_or_default();
+        if !constant_time_eq(stored.as_bytes(), hash.as_bytes()) {
+            return Ok(None);
+        }
+        Ok(row
+            .get("user_id")
+            .and_then(|u| u.as_str())
+            .map(str::to_string))
+    }
+}
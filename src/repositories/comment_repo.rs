@@ -0,0 +1,127 @@
+use crate::db::SupabaseClient;
+use crate::models::{Comment, CommentBody};
+use crate::repositories::common::parse_rows;
+use crate::repositories::TodoRepo;
+use crate::utils::{context::AppContext, time};
+use worker::*;
+
+const COMMENT_COLUMNS: &str = "id,todo_id,author_id,body,created_at,updated_at";
+
+/// Fetch a comment on `todo_id`. `None` if it does not exist or belongs to another todo.
+async fn fetch_comment(db: &SupabaseClient, todo_id: i64, id: i64) -> Result<Option<Comment>> {
+    let query = format!(
+        "select={}&id=eq.{}&todo_id=eq.{}",
+        COMMENT_COLUMNS, id, todo_id
+    );
+    Ok(parse_rows(db.get("todo_comments", &query).await?)?
+        .into_iter()
+        .next())
+}
+
+/// Errors unless the user may change the comment: its author while they can still read the todo,
+/// or an admin.
+async fn ensure_can_modify(
+    ctx: &AppContext,
+    db: &SupabaseClient,
+    user_id: &str,
+    is_admin: bool,
+    todo_id: i64,
+    id: i64,
+) -> Result<()> {
+    if !is_admin {
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+    }
+    let comment = fetch_comment(db, todo_id, id)
+        .await?
+        .ok_or_else(|| Error::RustError("Comment not found".into()))?;
+    if is_admin || comment.author_id == user_id {
+        Ok(())
+    } else {
+        Err(Error::RustError("Forbidden".into()))
+    }
+}
+
+/// Comments have no Keto tuples of their own: reading and posting follow read access to the todo,
+/// and changing a comment is reserved to its author (or an admin).
+pub struct CommentRepo;
+
+impl CommentRepo {
+    /// Comments on a todo the user can read, oldest first.
+    pub async fn list(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<Vec<Comment>> {
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select={}&todo_id=eq.{}&order=created_at.asc,id.asc",
+            COMMENT_COLUMNS, todo_id
+        );
+        parse_rows(db.get("todo_comments", &query).await?)
+    }
+
+    /// Post a comment on a todo the user can read (owner, editor or viewer).
+    pub async fn create(
+        ctx: &AppContext,
+        user_id: &str,
+        todo_id: i64,
+        input: CommentBody,
+    ) -> Result<Comment> {
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let body = serde_json::json!({
+            "todo_id": todo_id,
+            "author_id": user_id,
+            "body": input.body.trim(),
+        });
+        parse_rows(db.post("todo_comments", body).await?)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("No comment returned".into()))
+    }
+
+    /// Edit a comment (author or admin).
+    pub async fn update(
+        ctx: &AppContext,
+        user_id: &str,
+        is_admin: bool,
+        todo_id: i64,
+        id: i64,
+        input: CommentBody,
+    ) -> Result<Comment> {
+        let db = SupabaseClient::from_env(ctx)?;
+        ensure_can_modify(ctx, &db, user_id, is_admin, todo_id, id).await?;
+
+        let json_value = db
+            .patch_where(
+                "todo_comments",
+                &format!("id=eq.{}&todo_id=eq.{}", id, todo_id),
+                serde_json::json!({
+                    "body": input.body.trim(),
+                    "updated_at": time::now_rfc3339(),
+                }),
+            )
+            .await?;
+        parse_rows(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Comment not found".into()))
+    }
+
+    /// Delete a comment (author or admin).
+    pub async fn delete(
+        ctx: &AppContext,
+        user_id: &str,
+        is_admin: bool,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<()> {
+        let db = SupabaseClient::from_env(ctx)?;
+        ensure_can_modify(ctx, &db, user_id, is_admin, todo_id, id).await?;
+
+        db.delete_where(
+            "todo_comments",
+            &format!("id=eq.{}&todo_id=eq.{}", id, todo_id),
+        )
+        .await
+    }
+}
@@ -0,0 +1,114 @@
+//! Helpers shared by the repositories: Keto subjects and relation checks, and PostgREST rows.
+
+use crate::db::{CheckParams, KetoClient, ListParams};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeSet;
+use worker::*;
+
+/// Page size used when walking Keto relation tuples.
+pub(crate) const KETO_PAGE_SIZE: u32 = 500;
+
+/// Keto subject id of a Kratos identity.
+pub(crate) fn subject_id(user_id: &str) -> String {
+    format!("user:{}", user_id)
+}
+
+/// Comma-separated ids for a short PostgREST `in.(...)` filter. Sets resolved from Keto go in a
+/// request body instead (see the `*_by_ids` functions).
+pub(crate) fn join_ids<'a>(ids: impl IntoIterator<Item = &'a i64>) -> String {
+    ids.into_iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Rows of a PostgREST array response.
+pub(crate) fn parse_rows<T: DeserializeOwned>(json_value: serde_json::Value) -> Result<Vec<T>> {
+    match json_value {
+        serde_json::Value::Array(arr) => Ok(serde_json::from_value(serde_json::Value::Array(arr))?),
+        _ => Err(Error::RustError(format!(
+            "Expected array, got: {}",
+            json_value
+        ))),
+    }
+}
+
+/// Values of the integer `column` in an array of rows.
+pub(crate) fn column_ids(json_value: &serde_json::Value, column: &str) -> Vec<i64> {
+    json_value
+        .as_array()
+        .map(|a| {
+            a.iter()
+                .filter_map(|r| r.get(column).and_then(|v| v.as_i64()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// True if the user holds any of `relations` on the object. Stops at the first allowed check.
+pub(crate) async fn has_any_relation(
+    keto: &KetoClient,
+    namespace: &str,
+    user_id: &str,
+    id: i64,
+    relations: &[&str],
+) -> Result<bool> {
+    for relation in relations {
+        let allowed = keto
+            .check(CheckParams {
+                namespace: namespace.to_string(),
+                object: id.to_string(),
+                relation: relation.to_string(),
+                subject_id: Some(subject_id(user_id)),
+                subject_set: None,
+                max_depth: None,
+            })
+            .await?;
+        if allowed {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Errors with `Forbidden` unless the user holds one of `relations` on the object.
+pub(crate) async fn ensure_any_relation(
+    keto: &KetoClient,
+    namespace: &str,
+    user_id: &str,
+    id: i64,
+    relations: &[&str],
+) -> Result<()> {
+    if has_any_relation(keto, namespace, user_id, id, relations).await? {
+        Ok(())
+    } else {
+        Err(Error::RustError("Forbidden".into()))
+    }
+}
+
+/// Ids of the objects in `namespace` on which the user holds `relation` directly, across every
+/// Keto page.
+pub(crate) async fn object_ids(
+    keto: &KetoClient,
+    namespace: &str,
+    user_id: &str,
+    relation: &str,
+) -> Result<BTreeSet<i64>> {
+    let tuples = keto
+        .list_all_relation_tuples(ListParams {
+            namespace: namespace.to_string(),
+            object: None,
+            relation: Some(relation.to_string()),
+            subject_id: Some(subject_id(user_id)),
+            subject_set: None,
+            page_size: Some(KETO_PAGE_SIZE),
+            page_token: None,
+        })
+        .await?;
+
+    Ok(tuples
+        .iter()
+        .filter_map(|t| t.get("object").and_then(|o| o.as_str()))
+        .filter_map(|s| s.parse::<i64>().ok())
+        .collect())
+}
@@ -0,0 +1,187 @@
+use crate::db::SupabaseClient;
+use crate::models::{ActivityAction, DependencyEdge, DependencyGraph, NewActivity};
+use crate::repositories::common::join_ids;
+use crate::repositories::todo_repo::{parse_todos, TODO_COLUMNS};
+use crate::repositories::{ActivityRepo, TodoRepo};
+use crate::utils::context::AppContext;
+use std::collections::BTreeSet;
+use worker::*;
+
+/// Links returned by `GET /api/todos/:id/dependencies`, so a huge graph cannot exhaust the worker.
+const MAX_GRAPH_EDGES: u32 = 500;
+
+fn parse_edges(json_value: serde_json::Value) -> Result<Vec<DependencyEdge>> {
+    Ok(serde_json::from_value(json_value)?)
+}
+
+/// Which of `ids` still have an open blocker: a live todo that is not completed. Blockers the
+/// caller cannot read count too.
+pub(crate) async fn blocked_ids(db: &SupabaseClient, ids: &[i64]) -> Result<BTreeSet<i64>> {
+    if ids.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+    let edges = parse_edges(
+        db.get(
+            "todo_dependencies",
+            &format!("select=todo_id,blocker_id&todo_id=in.({})", join_ids(ids)),
+        )
+        .await?,
+    )?;
+    if edges.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+
+    let blockers: BTreeSet<i64> = edges.iter().map(|e| e.blocker_id).collect();
+    let open = db
+        .get(
+            "todos",
+            &format!(
+                "select=id&id=in.({})&completed=eq.false&deleted_at=is.null",
+                join_ids(&blockers)
+            ),
+        )
+        .await?;
+    let open: BTreeSet<i64> = open
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|r| r.get("id").and_then(|v| v.as_i64()))
+        .collect();
+    Ok(edges
+        .iter()
+        .filter(|e| open.contains(&e.blocker_id))
+        .map(|e| e.todo_id)
+        .collect())
+}
+
+/// Blocked-by links between todos (`todo_dependencies`). A todo with an open blocker cannot be
+/// completed without `force`; see `TodoRepo::update`.
+pub struct DependencyRepo;
+
+impl DependencyRepo {
+    /// Mark `todo_id` as blocked by `blocker_id`. Requires write access to the blocked todo and
+    /// read access to the blocker. Rejected if the blocker already waits on the todo, directly or
+    /// through other links. Adding an existing link is not an error.
+    pub async fn add(
+        ctx: &AppContext,
+        user_id: &str,
+        todo_id: i64,
+        blocker_id: i64,
+    ) -> Result<DependencyEdge> {
+        if todo_id == blocker_id {
+            return Err(Error::RustError(
+                "Bad Request: A todo cannot block itself".into(),
+            ));
+        }
+        TodoRepo::ensure_writable(ctx, user_id, todo_id).await?;
+        TodoRepo::get(ctx, user_id, blocker_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let added = db
+            .rpc(
+                "add_todo_dependency",
+                serde_json::json!({ "dependent": todo_id, "blocker": blocker_id }),
+            )
+            .await?;
+        if added.as_bool() != Some(true) {
+            return Err(Error::RustError(
+                "Bad Request: This link would create a dependency cycle".into(),
+            ));
+        }
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(todo_id, Some(user_id), ActivityAction::BlockerAdded).change(
+                    "blocker_id",
+                    None::<i64>,
+                    blocker_id,
+                ),
+            ],
+        )
+        .await;
+        Ok(DependencyEdge {
+            todo_id,
+            blocker_id,
+        })
+    }
+
+    /// Remove a link; requires write access to the blocked todo.
+    pub async fn remove(
+        ctx: &AppContext,
+        user_id: &str,
+        todo_id: i64,
+        blocker_id: i64,
+    ) -> Result<()> {
+        TodoRepo::ensure_writable(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let filter = format!("todo_id=eq.{}&blocker_id=eq.{}", todo_id, blocker_id);
+        let existing = parse_edges(
+            db.get(
+                "todo_dependencies",
+                &format!("select=todo_id,blocker_id&{}", filter),
+            )
+            .await?,
+        )?;
+        if existing.is_empty() {
+            return Err(Error::RustError("Dependency not found".into()));
+        }
+        db.delete_where("todo_dependencies", &filter).await?;
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(todo_id, Some(user_id), ActivityAction::BlockerRemoved).change(
+                    "blocker_id",
+                    blocker_id,
+                    None::<i64>,
+                ),
+            ],
+        )
+        .await;
+        Ok(())
+    }
+
+    /// The todos `todo_id` waits on and holds up, transitively, with the links between them.
+    /// Trashed todos and todos the user cannot read are left out, with their links.
+    pub async fn graph(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<DependencyGraph> {
+        let root = TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let edges = parse_edges(
+            db.rpc(
+                "todo_dependency_graph",
+                serde_json::json!({ "root": todo_id, "max_edges": MAX_GRAPH_EDGES }),
+            )
+            .await?,
+        )?;
+        let blocked = blocked_ids(&db, &[todo_id]).await?.contains(&todo_id);
+
+        let linked: BTreeSet<i64> = edges
+            .iter()
+            .flat_map(|e| [e.todo_id, e.blocker_id])
+            .filter(|id| *id != todo_id)
+            .collect();
+        let visible = TodoRepo::visible_ids(ctx, user_id).await?;
+        let readable: Vec<i64> = linked.intersection(&visible).copied().collect();
+        let mut nodes = vec![root];
+        if !readable.is_empty() {
+            let query = format!(
+                "select={}&id=in.({})&deleted_at=is.null&order=id.asc",
+                TODO_COLUMNS,
+                join_ids(&readable)
+            );
+            nodes.extend(parse_todos(db.get("todos", &query).await?)?);
+        }
+
+        let shown: BTreeSet<i64> = nodes.iter().map(|t| t.id).collect();
+        Ok(DependencyGraph {
+            todo_id,
+            blocked,
+            nodes,
+            edges: edges
+                .into_iter()
+                .filter(|e| shown.contains(&e.todo_id) && shown.contains(&e.blocker_id))
+                .collect(),
+        })
+    }
+}
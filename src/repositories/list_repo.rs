@@ -0,0 +1,250 @@
+use crate::db::{KetoClient, ListParams, SupabaseClient};
+use crate::middleware::logging;
+use crate::models::{CreateList, ListMember, ListRole, TodoList, UpdateList};
+use crate::repositories::common::{
+    ensure_any_relation, has_any_relation, object_ids, parse_rows, subject_id, KETO_PAGE_SIZE,
+};
+use crate::utils::context::AppContext;
+use std::collections::BTreeSet;
+use worker::*;
+
+pub(crate) const KETO_NAMESPACE: &str = "lists";
+pub(crate) const KETO_RELATION_OWNER: &str = "owner";
+pub(crate) const KETO_RELATION_MEMBER: &str = "member";
+
+/// Relations that may see a list and the todos in it.
+const ACCESS_RELATIONS: [&str; 2] = [KETO_RELATION_OWNER, KETO_RELATION_MEMBER];
+
+const LIST_COLUMNS: &str = "id,name,created_at";
+
+/// Lists among `ids` by name. The ids go in the request body (see the `lists_by_ids` function),
+/// since a user's set can be too large for the query string.
+async fn fetch_by_ids(db: &SupabaseClient, ids: &BTreeSet<i64>) -> Result<Vec<TodoList>> {
+    parse_rows(
+        db.rpc_query(
+            "lists_by_ids",
+            &format!("select={}&order=name.asc", LIST_COLUMNS),
+            serde_json::json!({ "list_ids": ids }),
+        )
+        .await?,
+    )
+}
+
+/// Every tuple on a list, across all Keto pages.
+async fn list_tuples(keto: &KetoClient, id: i64) -> Result<Vec<serde_json::Value>> {
+    keto.list_all_relation_tuples(ListParams {
+        namespace: KETO_NAMESPACE.to_string(),
+        object: Some(id.to_string()),
+        relation: None,
+        subject_id: None,
+        subject_set: None,
+        page_size: Some(KETO_PAGE_SIZE),
+        page_token: None,
+    })
+    .await
+}
+
+pub struct ListRepo;
+
+impl ListRepo {
+    /// Ids of the lists the user owns or is a member of.
+    pub async fn accessible_ids(ctx: &AppContext, user_id: &str) -> Result<BTreeSet<i64>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let mut ids = BTreeSet::new();
+        for relation in ACCESS_RELATIONS {
+            ids.extend(object_ids(&keto, KETO_NAMESPACE, user_id, relation).await?);
+        }
+        Ok(ids)
+    }
+
+    /// Errors with `Forbidden` unless the user owns or is a member of the list.
+    pub async fn ensure_member(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &ACCESS_RELATIONS).await
+    }
+
+    /// Errors with `Forbidden` unless the user owns the list.
+    pub async fn ensure_owner(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await
+    }
+
+    /// Lists the user owns or is a member of, by name.
+    pub async fn list(ctx: &AppContext, user_id: &str) -> Result<Vec<TodoList>> {
+        let ids = Self::accessible_ids(ctx, user_id).await?;
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        fetch_by_ids(&db, &ids).await
+    }
+
+    /// Get a list the user owns or is a member of.
+    pub async fn get(ctx: &AppContext, user_id: &str, id: i64) -> Result<TodoList> {
+        let keto = KetoClient::from_env(ctx)?;
+        let allowed =
+            has_any_relation(&keto, KETO_NAMESPACE, user_id, id, &ACCESS_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!("select={}&id=eq.{}", LIST_COLUMNS, id);
+        match parse_rows(db.get("lists", &query).await?)?
+            .into_iter()
+            .next()
+        {
+            Some(list) if allowed => Ok(list),
+            Some(_) => Err(Error::RustError("Forbidden".into())),
+            None => Err(Error::RustError("List not found".into())),
+        }
+    }
+
+    /// Create a list and set the caller as owner in Keto.
+    pub async fn create(ctx: &AppContext, user_id: &str, input: CreateList) -> Result<TodoList> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let body = serde_json::json!({ "name": input.name.trim() });
+        let list = parse_rows::<TodoList>(db.post("lists", body).await?)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("No list returned".into()))?;
+
+        let keto = KetoClient::from_env(ctx)?;
+        keto.create_relation_tuple(
+            KETO_NAMESPACE,
+            &list.id.to_string(),
+            KETO_RELATION_OWNER,
+            &subject_id(user_id),
+        )
+        .await?;
+
+        Ok(list)
+    }
+
+    /// Rename a list (owner-only).
+    pub async fn update(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        input: UpdateList,
+    ) -> Result<TodoList> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let body = serde_json::json!({ "name": input.name.trim() });
+        parse_rows(
+            db.patch_where("lists", &format!("id=eq.{}", id), body)
+                .await?,
+        )?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::RustError("List not found".into()))
+    }
+
+    /// Delete a list (owner-only). Its todos are kept and detached from it (`on delete set null`);
+    /// access they inherited from the list goes away with the list's tuples.
+    pub async fn delete(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        db.delete("lists", id).await?;
+
+        // Remove tuples in Keto after successful Supabase delete. Log on failure; do not fail the request.
+        match list_tuples(&keto, id).await {
+            Ok(tuples) => {
+                for t in &tuples {
+                    let relation = t.get("relation").and_then(|r| r.as_str());
+                    let sub = t.get("subject_id").and_then(|s| s.as_str());
+                    if let (Some(relation), Some(sub)) = (relation, sub) {
+                        if let Err(e) = keto
+                            .delete_relation_tuple(KETO_NAMESPACE, &id.to_string(), relation, sub)
+                            .await
+                        {
+                            logging::log_error(&format!("keto delete relation tuple: {}", e));
+                        }
+                    }
+                }
+            }
+            Err(e) => logging::log_error(&format!("keto list relation tuples: {}", e)),
+        }
+
+        Ok(())
+    }
+
+    /// Owner and members of a list the user can see.
+    pub async fn members(ctx: &AppContext, user_id: &str, id: i64) -> Result<Vec<ListMember>> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &ACCESS_RELATIONS).await?;
+
+        let tuples = list_tuples(&keto, id).await?;
+        Ok(tuples
+            .iter()
+            .filter_map(|t| {
+                let role = match t.get("relation").and_then(|r| r.as_str())? {
+                    KETO_RELATION_OWNER => ListRole::Owner,
+                    KETO_RELATION_MEMBER => ListRole::Member,
+                    _ => return None,
+                };
+                let sub = t.get("subject_id").and_then(|s| s.as_str())?;
+                Some(ListMember {
+                    user_id: sub.strip_prefix("user:").unwrap_or(sub).to_string(),
+                    role,
+                })
+            })
+            .collect())
+    }
+
+    /// Add a member to a list (owner-only).
+    pub async fn add_member(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        member_id: &str,
+    ) -> Result<ListMember> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+
+        if member_id == user_id {
+            return Err(Error::RustError(
+                "Bad Request: The owner is already part of the list".into(),
+            ));
+        }
+
+        keto.create_relation_tuple(
+            KETO_NAMESPACE,
+            &id.to_string(),
+            KETO_RELATION_MEMBER,
+            &subject_id(member_id),
+        )
+        .await?;
+
+        Ok(ListMember {
+            user_id: member_id.to_string(),
+            role: ListRole::Member,
+        })
+    }
+
+    /// Remove a member from a list. The owner can remove anyone; a member can only leave.
+    pub async fn remove_member(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        member_id: &str,
+    ) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        if member_id == user_id {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_MEMBER])
+                .await?;
+        } else {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+        }
+
+        keto.delete_relation_tuple(
+            KETO_NAMESPACE,
+            &id.to_string(),
+            KETO_RELATION_MEMBER,
+            &subject_id(member_id),
+        )
+        .await
+    }
+}
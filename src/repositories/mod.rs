@@ -1,5 +1,26 @@
+pub(crate) mod common;
 pub mod user_repo;
 pub mod todo_repo;
+pub mod tag_repo;
+pub mod list_repo;
+pub mod comment_repo;
+pub mod attachment_repo;
+pub mod activity_repo;
+pub mod transfer_repo;
+pub mod calendar_repo;
+pub mod settings_repo;
+pub mod dependency_repo;
+pub mod time_repo;
 
 pub use user_repo::UserRepo;
 pub use todo_repo::TodoRepo;
+pub use tag_repo::TagRepo;
+pub use list_repo::ListRepo;
+pub use comment_repo::CommentRepo;
+pub use attachment_repo::AttachmentRepo;
+pub use activity_repo::ActivityRepo;
+pub use transfer_repo::TransferRepo;
+pub use calendar_repo::CalendarRepo;
+pub use settings_repo::SettingsRepo;
+pub use dependency_repo::DependencyRepo;
+pub use time_repo::TimeRepo;
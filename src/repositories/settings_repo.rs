@@ -0,0 +1,150 @@
+use crate::db::supabase::encode_list_value;
+use crate::db::SupabaseClient;
+use crate::models::{UpdateSettings, UserSettings};
+use crate::repositories::TodoRepo;
+use crate::utils::{context::AppContext, time};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::collections::HashMap;
+use worker::*;
+
+const DEFAULT_ARCHIVE_AFTER_DAYS: u32 = 30;
+const DEFAULT_TIMEZONE: &str = "UTC";
+
+/// Archive delay for users without a setting. Read from `ARCHIVE_AFTER_DAYS`; `0` never archives.
+fn default_archive_after_days(ctx: &AppContext) -> Option<u32> {
+    let days = ctx
+        .env
+        .var("ARCHIVE_AFTER_DAYS")
+        .ok()
+        .and_then(|v| v.to_string().parse().ok())
+        .unwrap_or(DEFAULT_ARCHIVE_AFTER_DAYS);
+    Some(days).filter(|d| *d > 0)
+}
+
+/// A `user_settings` row. `None` is a setting the user never made; `archive_after_days` is `0`
+/// when they chose to never archive.
+#[derive(Deserialize, Default, Clone)]
+struct StoredSettings {
+    archive_after_days: Option<u32>,
+    timezone: Option<String>,
+}
+
+impl StoredSettings {
+    fn archive_after_days(&self, default: Option<u32>) -> Option<u32> {
+        match self.archive_after_days {
+            None => default,
+            Some(0) => None,
+            Some(days) => Some(days),
+        }
+    }
+}
+
+/// Stored settings per user; users without a row are missing from the map.
+async fn stored_settings(
+    db: &SupabaseClient,
+    user_ids: &[String],
+) -> Result<HashMap<String, StoredSettings>> {
+    #[derive(Deserialize)]
+    struct Row {
+        user_id: String,
+        #[serde(flatten)]
+        settings: StoredSettings,
+    }
+
+    let query = format!(
+        "select=user_id,archive_after_days,timezone&user_id=in.({})",
+        user_ids
+            .iter()
+            .map(|u| encode_list_value(u))
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    let rows: Vec<Row> = serde_json::from_value(db.get("user_settings", &query).await?)?;
+    Ok(rows.into_iter().map(|r| (r.user_id, r.settings)).collect())
+}
+
+/// The user's time zone, for work done outside their requests (e.g. the next occurrence of a
+/// recurring todo completed by someone else). UTC without a setting.
+pub(crate) async fn timezone_of(db: &SupabaseClient, user_id: &str) -> Result<Tz> {
+    let stored = stored_settings(db, &[user_id.to_string()])
+        .await?
+        .remove(user_id)
+        .unwrap_or_default();
+    Ok(stored
+        .timezone
+        .and_then(|t| t.parse().ok())
+        .unwrap_or(Tz::UTC))
+}
+
+/// Per-user preferences (`user_settings`, at most one row per user). Without a row the defaults
+/// from the environment apply.
+pub struct SettingsRepo;
+
+impl SettingsRepo {
+    /// The user's settings, with defaults filled in.
+    pub async fn get(ctx: &AppContext, user_id: &str) -> Result<UserSettings> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let stored = stored_settings(&db, &[user_id.to_string()])
+            .await?
+            .remove(user_id)
+            .unwrap_or_default();
+        Ok(UserSettings {
+            archive_after_days: stored.archive_after_days(default_archive_after_days(ctx)),
+            timezone: stored
+                .timezone
+                .unwrap_or_else(|| DEFAULT_TIMEZONE.to_string()),
+        })
+    }
+
+    /// Apply the fields present in `input`. Changing the archive delay reschedules the user's
+    /// completed todos that are not archived yet.
+    pub async fn update(
+        ctx: &AppContext,
+        user_id: &str,
+        input: UpdateSettings,
+    ) -> Result<UserSettings> {
+        // Only the fields in `input` are sent, so concurrent updates of different settings do
+        // not overwrite each other.
+        let mut row = serde_json::json!({
+            "user_id": user_id,
+            "updated_at": time::now_rfc3339(),
+        });
+        if let Some(days) = input.archive_after_days {
+            row["archive_after_days"] = serde_json::json!(days.unwrap_or(0));
+        }
+        if let Some(timezone) = &input.timezone {
+            row["timezone"] = serde_json::json!(timezone);
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        db.upsert("user_settings", "user_id", row).await?;
+        if input.archive_after_days.is_some() {
+            TodoRepo::reschedule_archive(ctx, user_id).await?;
+        }
+        Self::get(ctx, user_id).await
+    }
+
+    /// Effective archive delay of each user, for the archive job. `None` never archives.
+    pub async fn archive_after_days_for(
+        ctx: &AppContext,
+        user_ids: &[String],
+    ) -> Result<HashMap<String, Option<u32>>> {
+        if user_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let db = SupabaseClient::from_env(ctx)?;
+        let stored = stored_settings(&db, user_ids).await?;
+        let default = default_archive_after_days(ctx);
+        Ok(user_ids
+            .iter()
+            .map(|u| {
+                let days = match stored.get(u) {
+                    Some(settings) => settings.archive_after_days(default),
+                    None => default,
+                };
+                (u.clone(), days)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,234 @@
+use crate::db::{KetoClient, SupabaseClient};
+use crate::middleware::logging;
+use crate::models::{CreateTag, Tag, UpdateTag};
+use crate::repositories::common::{
+    column_ids, has_any_relation, object_ids, parse_rows, subject_id,
+};
+use crate::repositories::TodoRepo;
+use crate::utils::context::AppContext;
+use std::collections::BTreeSet;
+use worker::*;
+
+const KETO_NAMESPACE: &str = "tags";
+const KETO_RELATION_OWNER: &str = "owner";
+
+const TAG_COLUMNS: &str = "id,name,color,created_at";
+
+/// Tags among `ids` by name. The ids go in the request body (see the `tags_by_ids` function),
+/// since a user's set can be too large for the query string.
+async fn fetch_by_ids(db: &SupabaseClient, ids: &BTreeSet<i64>) -> Result<Vec<Tag>> {
+    parse_rows(
+        db.rpc_query(
+            "tags_by_ids",
+            &format!("select={}&order=name.asc", TAG_COLUMNS),
+            serde_json::json!({ "tag_ids": ids }),
+        )
+        .await?,
+    )
+}
+
+/// Errors with `Tag not found` unless the user owns the tag. Tags are private, so a tag owned by
+/// someone else is reported exactly like a missing one.
+async fn ensure_owner(keto: &KetoClient, user_id: &str, tag_id: i64) -> Result<()> {
+    if has_any_relation(
+        keto,
+        KETO_NAMESPACE,
+        user_id,
+        tag_id,
+        &[KETO_RELATION_OWNER],
+    )
+    .await?
+    {
+        Ok(())
+    } else {
+        Err(Error::RustError("Tag not found".into()))
+    }
+}
+
+/// Ids of every tag the user owns.
+async fn owned_ids(keto: &KetoClient, user_id: &str) -> Result<BTreeSet<i64>> {
+    object_ids(keto, KETO_NAMESPACE, user_id, KETO_RELATION_OWNER).await
+}
+
+pub struct TagRepo;
+
+impl TagRepo {
+    /// List the user's tags by name. Uses Keto to resolve owned ids, then fetches from Supabase.
+    pub async fn list(ctx: &AppContext, user_id: &str) -> Result<Vec<Tag>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let ids = owned_ids(&keto, user_id).await?;
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        fetch_by_ids(&db, &ids).await
+    }
+
+    /// Create a tag and set the caller as owner in Keto. Names are unique per user, ignoring case.
+    pub async fn create(ctx: &AppContext, user_id: &str, input: CreateTag) -> Result<Tag> {
+        let input = CreateTag {
+            name: input.name.trim().to_string(),
+            ..input
+        };
+        Self::ensure_name_free(ctx, user_id, &input.name, None).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let tag = parse_rows::<Tag>(db.post("tags", serde_json::to_value(&input)?).await?)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("No tag returned".into()))?;
+
+        let keto = KetoClient::from_env(ctx)?;
+        keto.create_relation_tuple(
+            KETO_NAMESPACE,
+            &tag.id.to_string(),
+            KETO_RELATION_OWNER,
+            &subject_id(user_id),
+        )
+        .await?;
+
+        Ok(tag)
+    }
+
+    /// Rename or recolour a tag the user owns.
+    pub async fn update(ctx: &AppContext, user_id: &str, id: i64, patch: UpdateTag) -> Result<Tag> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_owner(&keto, user_id, id).await?;
+
+        if let Some(name) = &patch.name {
+            Self::ensure_name_free(ctx, user_id, name.trim(), Some(id)).await?;
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        parse_rows(
+            db.patch_where("tags", &format!("id=eq.{}", id), patch.to_patch_body())
+                .await?,
+        )?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::RustError("Tag not found".into()))
+    }
+
+    /// Delete a tag the user owns. Its links to todos go with it (`on delete cascade`).
+    pub async fn delete(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_owner(&keto, user_id, id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        db.delete("tags", id).await?;
+
+        // Remove ownership tuple in Keto after successful Supabase delete. Log on failure; do not fail the request.
+        if let Err(e) = keto
+            .delete_relation_tuple(
+                KETO_NAMESPACE,
+                &id.to_string(),
+                KETO_RELATION_OWNER,
+                &subject_id(user_id),
+            )
+            .await
+        {
+            logging::log_error(&format!("keto delete relation tuple: {}", e));
+        }
+
+        Ok(())
+    }
+
+    /// The user's tags on a todo they can read. Tags are personal, so other users' tags on the
+    /// same (shared) todo are not included.
+    pub async fn list_for_todo(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<Vec<Tag>> {
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let links = db
+            .get(
+                "todo_tags",
+                &format!("select=tag_id&todo_id=eq.{}", todo_id),
+            )
+            .await?;
+        let keto = KetoClient::from_env(ctx)?;
+        let owned = owned_ids(&keto, user_id).await?;
+        let ids: BTreeSet<i64> = column_ids(&links, "tag_id")
+            .into_iter()
+            .filter(|id| owned.contains(id))
+            .collect();
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        fetch_by_ids(&db, &ids).await
+    }
+
+    /// Attach one of the user's tags to a todo they can read. Attaching twice is a no-op.
+    pub async fn attach(ctx: &AppContext, user_id: &str, todo_id: i64, tag_id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_owner(&keto, user_id, tag_id).await?;
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let existing = db
+            .get(
+                "todo_tags",
+                &format!("select=tag_id&todo_id=eq.{}&tag_id=eq.{}", todo_id, tag_id),
+            )
+            .await?;
+        if column_ids(&existing, "tag_id").is_empty() {
+            db.post(
+                "todo_tags",
+                serde_json::json!({ "todo_id": todo_id, "tag_id": tag_id }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Detach one of the user's tags from a todo they can read.
+    pub async fn detach(ctx: &AppContext, user_id: &str, todo_id: i64, tag_id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_owner(&keto, user_id, tag_id).await?;
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        db.delete_where(
+            "todo_tags",
+            &format!("todo_id=eq.{}&tag_id=eq.{}", todo_id, tag_id),
+        )
+        .await
+    }
+
+    /// Ids of the todos carrying one of the user's tags (used by `GET /api/todos?tag=`).
+    pub async fn tagged_todo_ids(
+        ctx: &AppContext,
+        user_id: &str,
+        tag_id: i64,
+    ) -> Result<BTreeSet<i64>> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_owner(&keto, user_id, tag_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let links = db
+            .get("todo_tags", &format!("select=todo_id&tag_id=eq.{}", tag_id))
+            .await?;
+        Ok(column_ids(&links, "todo_id").into_iter().collect())
+    }
+
+    /// Errors with a 400 if the user already has another tag called `name` (ignoring case).
+    async fn ensure_name_free(
+        ctx: &AppContext,
+        user_id: &str,
+        name: &str,
+        except_id: Option<i64>,
+    ) -> Result<()> {
+        let taken = Self::list(ctx, user_id)
+            .await?
+            .iter()
+            .any(|t| Some(t.id) != except_id && t.name.to_lowercase() == name.to_lowercase());
+        if taken {
+            return Err(Error::RustError(format!(
+                "Bad Request: A tag named '{}' already exists",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
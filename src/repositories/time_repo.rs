@@ -0,0 +1,187 @@
+use crate::db::supabase::encode_value;
+use crate::db::SupabaseClient;
+use crate::models::{is_ordered, TimeEntry, TimeReport, TimeReportQuery, UpdateTimeEntry};
+use crate::repositories::common::parse_rows;
+use crate::repositories::TodoRepo;
+use crate::utils::{context::AppContext, time};
+use worker::*;
+
+const TIME_ENTRY_COLUMNS: &str = "id,todo_id,user_id,started_at,ended_at,note,created_at";
+
+/// Fetch an entry on `todo_id`. `None` if it does not exist or belongs to another todo.
+async fn fetch_entry(db: &SupabaseClient, todo_id: i64, id: i64) -> Result<Option<TimeEntry>> {
+    let query = format!(
+        "select={}&id=eq.{}&todo_id=eq.{}",
+        TIME_ENTRY_COLUMNS, id, todo_id
+    );
+    Ok(parse_rows(db.get("time_entries", &query).await?)?
+        .into_iter()
+        .next())
+}
+
+/// The entry, if the user may change it: they tracked it and may still work on the todo.
+async fn ensure_can_modify(
+    ctx: &AppContext,
+    db: &SupabaseClient,
+    user_id: &str,
+    todo_id: i64,
+    id: i64,
+) -> Result<TimeEntry> {
+    TodoRepo::ensure_completable(ctx, user_id, todo_id).await?;
+    let entry = fetch_entry(db, todo_id, id)
+        .await?
+        .ok_or_else(|| Error::RustError("Time entry not found".into()))?;
+    if entry.user_id == user_id {
+        Ok(entry)
+    } else {
+        Err(Error::RustError("Forbidden".into()))
+    }
+}
+
+/// Time tracking on todos (`time_entries`). Entries have no Keto tuples of their own: tracking time
+/// follows the right to complete the todo (owner, editor or assignee), reading it follows read
+/// access, and an entry can only be changed by the user who tracked it.
+pub struct TimeRepo;
+
+impl TimeRepo {
+    /// Entries on a todo the user can read, by everyone, oldest first.
+    pub async fn list(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<Vec<TimeEntry>> {
+        TodoRepo::get(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select={}&todo_id=eq.{}&order=started_at.asc,id.asc",
+            TIME_ENTRY_COLUMNS, todo_id
+        );
+        parse_rows(db.get("time_entries", &query).await?)
+    }
+
+    /// Start the user's timer on a todo, stopping their timer on any other todo first. If it is
+    /// already running on this todo, that entry is returned.
+    pub async fn start(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<TimeEntry> {
+        TodoRepo::ensure_completable(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .rpc(
+                "start_time_entry",
+                serde_json::json!({
+                    "todo": todo_id,
+                    "for_user": user_id,
+                    "at": time::now_rfc3339(),
+                }),
+            )
+            .await?;
+        parse_rows(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("No time entry returned".into()))
+    }
+
+    /// Stop the user's running timer on a todo. Errors with `Timer not found` if it is not running.
+    pub async fn stop(ctx: &AppContext, user_id: &str, todo_id: i64) -> Result<TimeEntry> {
+        TodoRepo::ensure_completable(ctx, user_id, todo_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .patch_where(
+                "time_entries",
+                &format!(
+                    "todo_id=eq.{}&user_id=eq.{}&ended_at=is.null",
+                    todo_id,
+                    encode_value(user_id)
+                ),
+                serde_json::json!({ "ended_at": time::now_rfc3339() }),
+            )
+            .await?;
+        parse_rows(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Timer not found".into()))
+    }
+
+    /// Correct an entry after the fact (the user who tracked it).
+    pub async fn update(
+        ctx: &AppContext,
+        user_id: &str,
+        todo_id: i64,
+        id: i64,
+        input: UpdateTimeEntry,
+    ) -> Result<TimeEntry> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let current = ensure_can_modify(ctx, &db, user_id, todo_id, id).await?;
+
+        let started_at = input.started_at.as_ref().unwrap_or(&current.started_at);
+        if let Some(ended_at) = input.ended_at.as_ref().or(current.ended_at.as_ref()) {
+            if !is_ordered(started_at, ended_at) {
+                return Err(Error::RustError(
+                    "Bad Request: ended_at must not be before started_at".into(),
+                ));
+            }
+        }
+
+        let mut body = serde_json::Map::new();
+        if let Some(started_at) = input.started_at {
+            body.insert("started_at".into(), started_at.into());
+        }
+        if let Some(ended_at) = input.ended_at {
+            body.insert("ended_at".into(), ended_at.into());
+        }
+        if let Some(note) = input.note {
+            body.insert(
+                "note".into(),
+                note.map(|n| n.trim().to_string())
+                    .filter(|n| !n.is_empty())
+                    .into(),
+            );
+        }
+        let json_value = db
+            .patch_where(
+                "time_entries",
+                &format!("id=eq.{}&todo_id=eq.{}", id, todo_id),
+                serde_json::Value::Object(body),
+            )
+            .await?;
+        parse_rows(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Time entry not found".into()))
+    }
+
+    /// Delete an entry (the user who tracked it).
+    pub async fn delete(ctx: &AppContext, user_id: &str, todo_id: i64, id: i64) -> Result<()> {
+        let db = SupabaseClient::from_env(ctx)?;
+        ensure_can_modify(ctx, &db, user_id, todo_id, id).await?;
+
+        db.delete_where(
+            "time_entries",
+            &format!("id=eq.{}&todo_id=eq.{}", id, todo_id),
+        )
+        .await
+    }
+
+    /// Time tracked on the todos the user can read (trashed ones included), summed per todo and
+    /// per day by the `time_report` function in Supabase.
+    pub async fn report(
+        ctx: &AppContext,
+        user_id: &str,
+        query: &TimeReportQuery,
+    ) -> Result<TimeReport> {
+        let ids = TodoRepo::visible_ids(ctx, user_id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .rpc(
+                "time_report",
+                serde_json::json!({
+                    "todo_ids": ids,
+                    "from_date": query.from.to_string(),
+                    "to_date": query.to.to_string(),
+                    "as_of": time::now_rfc3339(),
+                    "only_user": query.only_mine.then_some(user_id),
+                }),
+            )
+            .await?;
+        Ok(serde_json::from_value(json_value)?)
+    }
+}
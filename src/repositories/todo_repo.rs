@@ -1,285 +1,2359 @@
-use crate::db::{KetoClient, ListParams, SupabaseClient};
-use crate::models::{AdminTodo, Todo};
+use crate::db::supabase::{encode_list_value, encode_value, escape_like};
+use crate::db::{KetoClient, ListParams, SubjectSet, SupabaseClient};
 use crate::middleware::logging;
-use crate::utils::context::AppContext;
-use std::collections::HashMap;
+use crate::models::{
+    recurrence, ActivityAction, AdminTodo, BatchOperation, BatchResult, CreateTodo, ListTodosQuery,
+    MoveTodo, NewActivity, Recurrence, ShareRelation, StatsQuery, Todo, TodoAssignee, TodoNode,
+    TodoPage, TodoShare, TodoStats, UpdateTodo, UpdateTodoOptions,
+};
+use crate::repositories::common::{
+    column_ids, ensure_any_relation, has_any_relation, join_ids, object_ids, parse_rows,
+    subject_id, KETO_PAGE_SIZE,
+};
+use crate::repositories::{
+    dependency_repo, list_repo, settings_repo, ActivityRepo, AttachmentRepo, ListRepo, TagRepo,
+};
+use crate::utils::{context::AppContext, position, time};
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use futures::future::join_all;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use worker::*;
 
 const KETO_NAMESPACE: &str = "todos";
 const KETO_RELATION_OWNER: &str = "owner";
+const KETO_RELATION_EDITOR: &str = "editor";
+const KETO_RELATION_VIEWER: &str = "viewer";
+const KETO_RELATION_ASSIGNEE: &str = "assignee";
 
-fn subject_id(user_id: &str) -> String {
-    format!("user:{}", user_id)
+/// Columns selected for every `Todo` read.
+pub(crate) const TODO_COLUMNS: &str =
+    "id,title,description,due_date,priority,completed,created_at,deleted_at,parent_id,list_id,remind_at,rrule,series_id,position,version,completed_at,archived_at";
+
+/// Deepest allowed subtask level (top-level todos are level 0). Each level is one subject-set hop
+/// in Keto, which resolves at most 5 hops by default, so this must stay below that.
+const MAX_SUBTASK_DEPTH: usize = 4;
+
+/// Max rows purged from the trash per run; the rest wait for the next run.
+const PURGE_BATCH_SIZE: u32 = 200;
+
+/// Max position keys shortened per rebalance run; the rest wait for the next run.
+const REBALANCE_BATCH_SIZE: u32 = 200;
+
+/// Relations that may read a todo.
+const READ_RELATIONS: [&str; 4] = [
+    KETO_RELATION_OWNER,
+    KETO_RELATION_EDITOR,
+    KETO_RELATION_VIEWER,
+    KETO_RELATION_ASSIGNEE,
+];
+/// Relations a subtask inherits from its parent. An assignment is personal to one todo.
+const INHERITED_RELATIONS: [&str; 3] = [
+    KETO_RELATION_OWNER,
+    KETO_RELATION_EDITOR,
+    KETO_RELATION_VIEWER,
+];
+/// Relations that may change or delete a todo.
+const WRITE_RELATIONS: [&str; 2] = [KETO_RELATION_OWNER, KETO_RELATION_EDITOR];
+/// Relations that may toggle `completed`. Assignees may not change anything else.
+const COMPLETE_RELATIONS: [&str; 3] = [
+    KETO_RELATION_OWNER,
+    KETO_RELATION_EDITOR,
+    KETO_RELATION_ASSIGNEE,
+];
+
+/// Todo ids on which the user holds any of `relations` directly.
+async fn readable_ids(
+    keto: &KetoClient,
+    user_id: &str,
+    relations: &[&str],
+) -> Result<BTreeSet<i64>> {
+    let mut ids = BTreeSet::new();
+    for relation in relations {
+        ids.extend(object_ids(keto, KETO_NAMESPACE, user_id, relation).await?);
+    }
+    Ok(ids)
+}
+
+/// Users the todo is assigned to (at most one through the API).
+async fn assignee_ids(keto: &KetoClient, id: i64) -> Result<Vec<String>> {
+    let tuples = keto
+        .list_all_relation_tuples(ListParams {
+            namespace: KETO_NAMESPACE.to_string(),
+            object: Some(id.to_string()),
+            relation: Some(KETO_RELATION_ASSIGNEE.to_string()),
+            subject_id: None,
+            subject_set: None,
+            page_size: Some(KETO_PAGE_SIZE),
+            page_token: None,
+        })
+        .await?;
+    Ok(tuples
+        .iter()
+        .filter_map(|t| t.get("subject_id").and_then(|s| s.as_str()))
+        .map(|s| s.strip_prefix("user:").unwrap_or(s).to_string())
+        .collect())
+}
+
+/// PostgREST filter and order params for a list query. User input only ever reaches the query
+/// percent-encoded, and search terms have their `LIKE` wildcards escaped.
+fn list_filter_params(query: &ListTodosQuery) -> String {
+    let mut params = String::new();
+    if let Some(completed) = query.completed {
+        params.push_str(&format!("&completed=is.{}", completed));
+    }
+    if let Some(search) = &query.search {
+        let pattern = format!("*{}*", escape_like(search));
+        params.push_str(&format!("&title=ilike.{}", encode_value(&pattern)));
+    }
+    let dir = if query.ascending { "asc" } else { "desc" };
+    params.push_str(&format!(
+        "&order={}.{}.nullslast,id.{}",
+        query.order.column(),
+        dir,
+        dir
+    ));
+    params
+}
+
+/// PostgREST filter for the rows after `query.after` in the list order (keyset paging). Rows
+/// without a value in the sort column sort last in either direction.
+fn keyset_filter(query: &ListTodosQuery) -> String {
+    let after = match &query.after {
+        Some(after) => after,
+        None => return String::new(),
+    };
+    let column = query.order.column();
+    let op = if query.ascending { "gt" } else { "lt" };
+    match &after.value {
+        Some(value) => {
+            let value = encode_list_value(value);
+            format!(
+                "&or=({column}.{op}.{value},and({column}.eq.{value},id.{op}.{id}),{column}.is.null)",
+                column = column,
+                op = op,
+                value = value,
+                id = after.id
+            )
+        }
+        None => format!("&{}=is.null&id={}.{}", column, op, after.id),
+    }
+}
+
+/// Rows among `ids` matching the PostgREST `query`. The ids go in the request body (see the
+/// `todos_by_ids` function), since a user's set can be too large for the query string.
+async fn fetch_by_ids(db: &SupabaseClient, ids: &BTreeSet<i64>, query: &str) -> Result<Vec<Todo>> {
+    parse_todos(
+        db.rpc_query(
+            "todos_by_ids",
+            query,
+            serde_json::json!({ "todo_ids": ids }),
+        )
+        .await?,
+    )
+}
+
+/// `(id, position)` of each row in a `select=id,position` response.
+fn parse_todos_positions(json_value: serde_json::Value) -> Vec<(i64, String)> {
+    json_value
+        .as_array()
+        .map(|rows| {
+            rows.iter()
+                .filter_map(|r| {
+                    let id = r.get("id")?.as_i64()?;
+                    let position = r.get("position")?.as_str()?;
+                    Some((id, position.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn parse_todos(json_value: serde_json::Value) -> Result<Vec<Todo>> {
+    parse_rows(json_value)
+}
+
+/// Error for a write whose `If-Match` version is no longer current.
+fn precondition_failed() -> Error {
+    Error::RustError(
+        "Precondition Failed: The todo has changed since it was read (If-Match)".into(),
+    )
+}
+
+/// PostgREST filter for a live todo, restricted to the `If-Match` version if given, so a
+/// concurrent write between the read and the PATCH cannot be overwritten.
+fn live_version_filter(id: i64, if_match: Option<i64>) -> String {
+    match if_match {
+        Some(version) => format!("id=eq.{}&deleted_at=is.null&version=eq.{}", id, version),
+        None => format!("id=eq.{}&deleted_at=is.null", id),
+    }
+}
+
+/// Fetch a single live (not trashed) todo row by id. `None` if it does not exist.
+async fn fetch_todo(db: &SupabaseClient, id: i64) -> Result<Option<Todo>> {
+    let query = format!("select={}&id=eq.{}&deleted_at=is.null", TODO_COLUMNS, id);
+    let json_value = db.get("todos", &query).await?;
+    match json_value {
+        serde_json::Value::Array(arr) => {
+            let todos: Vec<Todo> = serde_json::from_value(serde_json::Value::Array(arr))?;
+            Ok(todos.into_iter().next())
+        }
+        _ => Err(Error::RustError(format!(
+            "Expected array, got: {}",
+            json_value
+        ))),
+    }
+}
+
+/// Remove every tuple on the todo (owner, shares and inherited parent access). Logs failures; never fails the caller.
+async fn delete_object_tuples(keto: &KetoClient, id: i64) {
+    let tuples = keto
+        .list_all_relation_tuples(ListParams {
+            namespace: KETO_NAMESPACE.to_string(),
+            object: Some(id.to_string()),
+            relation: None,
+            subject_id: None,
+            subject_set: None,
+            page_size: Some(KETO_PAGE_SIZE),
+            page_token: None,
+        })
+        .await;
+
+    match tuples {
+        Ok(tuples) => {
+            for t in &tuples {
+                let relation = match t.get("relation").and_then(|r| r.as_str()) {
+                    Some(r) => r,
+                    None => continue,
+                };
+                let result = if let Some(sub) = t.get("subject_id").and_then(|s| s.as_str()) {
+                    keto.delete_relation_tuple(KETO_NAMESPACE, &id.to_string(), relation, sub)
+                        .await
+                } else if let Some(ss) = t.get("subject_set") {
+                    let field = |k: &str| {
+                        ss.get(k)
+                            .and_then(|v| v.as_str())
+                            .unwrap_or_default()
+                            .to_string()
+                    };
+                    let subject_set = SubjectSet {
+                        namespace: field("namespace"),
+                        object: field("object"),
+                        relation: field("relation"),
+                    };
+                    keto.delete_relation_tuple_with_subject_set(
+                        KETO_NAMESPACE,
+                        &id.to_string(),
+                        relation,
+                        &subject_set,
+                    )
+                    .await
+                } else {
+                    continue;
+                };
+                if let Err(e) = result {
+                    logging::log_error(&format!("keto delete relation tuple: {}", e));
+                }
+            }
+        }
+        Err(e) => logging::log_error(&format!("keto list relation tuples: {}", e)),
+    }
+}
+
+/// Subject set pointing at `relation` on the parent todo.
+fn parent_subject_set(parent_id: i64, relation: &str) -> SubjectSet {
+    SubjectSet {
+        namespace: KETO_NAMESPACE.to_string(),
+        object: parent_id.to_string(),
+        relation: relation.to_string(),
+    }
+}
+
+/// Make every owner, editor and viewer of the parent hold the same relation on the child.
+async fn link_parent_tuples(keto: &KetoClient, child_id: i64, parent_id: i64) -> Result<()> {
+    for relation in INHERITED_RELATIONS {
+        keto.create_relation_tuple_with_subject_set(
+            KETO_NAMESPACE,
+            &child_id.to_string(),
+            relation,
+            &parent_subject_set(parent_id, relation),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn unlink_parent_tuples(keto: &KetoClient, child_id: i64, parent_id: i64) -> Result<()> {
+    for relation in INHERITED_RELATIONS {
+        keto.delete_relation_tuple_with_subject_set(
+            KETO_NAMESPACE,
+            &child_id.to_string(),
+            relation,
+            &parent_subject_set(parent_id, relation),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Todo relation granted by each list relation: list owners own the list's todos and members
+/// can edit them.
+const LIST_INHERITANCE: [(&str, &str); 2] = [
+    (KETO_RELATION_OWNER, list_repo::KETO_RELATION_OWNER),
+    (KETO_RELATION_EDITOR, list_repo::KETO_RELATION_MEMBER),
+];
+
+fn list_subject_set(list_id: i64, relation: &str) -> SubjectSet {
+    SubjectSet {
+        namespace: list_repo::KETO_NAMESPACE.to_string(),
+        object: list_id.to_string(),
+        relation: relation.to_string(),
+    }
+}
+
+/// Grant the list's owner and members access to the todo via `lists:<id>#...` subject sets.
+async fn link_list_tuples(keto: &KetoClient, todo_id: i64, list_id: i64) -> Result<()> {
+    for (relation, list_relation) in LIST_INHERITANCE {
+        keto.create_relation_tuple_with_subject_set(
+            KETO_NAMESPACE,
+            &todo_id.to_string(),
+            relation,
+            &list_subject_set(list_id, list_relation),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn unlink_list_tuples(keto: &KetoClient, todo_id: i64, list_id: i64) -> Result<()> {
+    for (relation, list_relation) in LIST_INHERITANCE {
+        keto.delete_relation_tuple_with_subject_set(
+            KETO_NAMESPACE,
+            &todo_id.to_string(),
+            relation,
+            &list_subject_set(list_id, list_relation),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Ids of the live todos in any of `list_ids`.
+async fn todo_ids_in_lists(db: &SupabaseClient, list_ids: &BTreeSet<i64>) -> Result<BTreeSet<i64>> {
+    if list_ids.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+    let rows = db
+        .rpc_query(
+            "todos_in_lists",
+            "select=id&deleted_at=is.null",
+            serde_json::json!({ "list_ids": list_ids }),
+        )
+        .await?;
+    Ok(column_ids(&rows, "id").into_iter().collect())
+}
+
+/// Ancestors of a todo, nearest first. Stops after `MAX_SUBTASK_DEPTH + 1` hops so a corrupt
+/// cycle in the data cannot loop forever.
+async fn ancestor_ids(db: &SupabaseClient, id: i64) -> Result<Vec<i64>> {
+    let mut ancestors = Vec::new();
+    let mut current = id;
+    while ancestors.len() <= MAX_SUBTASK_DEPTH {
+        let json_value = db
+            .get("todos", &format!("select=parent_id&id=eq.{}", current))
+            .await?;
+        let parent = json_value
+            .as_array()
+            .and_then(|a| a.first())
+            .and_then(|r| r.get("parent_id"))
+            .and_then(|p| p.as_i64());
+        match parent {
+            Some(p) => {
+                ancestors.push(p);
+                current = p;
+            }
+            None => break,
+        }
+    }
+    Ok(ancestors)
+}
+
+/// Live descendants of the given todos, level by level, down to `MAX_SUBTASK_DEPTH` levels.
+async fn fetch_descendants(db: &SupabaseClient, root_ids: &[i64]) -> Result<Vec<Todo>> {
+    let mut descendants = Vec::new();
+    let mut seen: BTreeSet<i64> = root_ids.iter().copied().collect();
+    let mut frontier: Vec<i64> = root_ids.to_vec();
+    for _ in 0..MAX_SUBTASK_DEPTH {
+        if frontier.is_empty() {
+            break;
+        }
+        let query = format!(
+            "select={}&parent_id=in.({})&deleted_at=is.null&order=created_at.asc",
+            TODO_COLUMNS,
+            join_ids(&frontier)
+        );
+        let level = parse_todos(db.get("todos", &query).await?)?;
+        frontier = level
+            .iter()
+            .map(|t| t.id)
+            .filter(|id| seen.insert(*id))
+            .collect();
+        descendants.extend(level.into_iter().filter(|t| frontier.contains(&t.id)));
+    }
+    Ok(descendants)
+}
+
+/// Number of levels below `id` (0 for a leaf).
+async fn subtree_height(db: &SupabaseClient, id: i64) -> Result<usize> {
+    let mut height = 0;
+    let mut frontier = vec![id];
+    while !frontier.is_empty() && height <= MAX_SUBTASK_DEPTH {
+        let json_value = db
+            .get(
+                "todos",
+                &format!(
+                    "select=id&parent_id=in.({})&deleted_at=is.null",
+                    join_ids(&frontier)
+                ),
+            )
+            .await?;
+        frontier = json_value
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|r| r.get("id").and_then(|v| v.as_i64()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !frontier.is_empty() {
+            height += 1;
+        }
+    }
+    Ok(height)
+}
+
+/// Nest `todos` under `roots` by `parent_id`.
+fn build_tree(roots: Vec<Todo>, todos: Vec<Todo>) -> Vec<TodoNode> {
+    let mut by_parent: HashMap<i64, Vec<Todo>> = HashMap::new();
+    for todo in todos {
+        if let Some(p) = todo.parent_id {
+            by_parent.entry(p).or_default().push(todo);
+        }
+    }
+
+    fn attach(todo: Todo, by_parent: &mut HashMap<i64, Vec<Todo>>) -> TodoNode {
+        let children = by_parent
+            .remove(&todo.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| attach(c, by_parent))
+            .collect();
+        TodoNode { todo, children }
+    }
+
+    roots
+        .into_iter()
+        .map(|r| attach(r, &mut by_parent))
+        .collect()
+}
+
+/// PATCH every live todo in `ids` with `body` in one request, keyed by id. Missing ids are absent.
+async fn patch_many(
+    db: &SupabaseClient,
+    ids: &[i64],
+    body: serde_json::Value,
+) -> Result<HashMap<i64, Todo>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let json_value = db
+        .patch_where(
+            "todos",
+            &format!("id=in.({})&deleted_at=is.null", join_ids(ids)),
+            body,
+        )
+        .await?;
+    Ok(parse_todos(json_value)?
+        .into_iter()
+        .map(|t| (t.id, t))
+        .collect())
+}
+
+/// Closest position after (`ascending`) or before `from` among the live todos in `scope` (the
+/// todos one user sees), skipping `exclude`. With `from` unset, the lowest (or highest) position.
+async fn neighbour_position(
+    db: &SupabaseClient,
+    scope: &BTreeSet<i64>,
+    from: Option<&str>,
+    ascending: bool,
+    exclude: Option<i64>,
+) -> Result<Option<String>> {
+    if scope.is_empty() {
+        return Ok(None);
+    }
+    let mut query = String::from("select=position&position=not.is.null&deleted_at=is.null");
+    if let Some(from) = from {
+        let op = if ascending { "gt" } else { "lt" };
+        query.push_str(&format!("&position={}.{}", op, encode_value(from)));
+    }
+    if let Some(id) = exclude {
+        query.push_str(&format!("&id=neq.{}", id));
+    }
+    let dir = if ascending { "asc" } else { "desc" };
+    query.push_str(&format!("&order=position.{}&limit=1", dir));
+    Ok(db
+        .rpc_query(
+            "todos_by_ids",
+            &query,
+            serde_json::json!({ "todo_ids": scope }),
+        )
+        .await?
+        .as_array()
+        .and_then(|rows| rows.first())
+        .and_then(|row| row.get("position"))
+        .and_then(|p| p.as_str())
+        .map(str::to_string))
 }
 
-pub struct TodoRepo;
+/// Position next to the row `(key, id)` in the order of every todo (`position`, then `id`), for
+/// the rebalance job, which must keep the order the same for all users at once.
+async fn adjacent_position(
+    db: &SupabaseClient,
+    key: &str,
+    id: i64,
+    ascending: bool,
+) -> Result<Option<String>> {
+    let (op, dir) = if ascending {
+        ("gt", "asc")
+    } else {
+        ("lt", "desc")
+    };
+    let key = encode_value(key);
+    let query = format!(
+        "select=position&or=(position.{op}.{key},and(position.eq.{key},id.{op}.{id}))&order=position.{dir},id.{dir}&limit=1",
+        op = op,
+        key = key,
+        id = id,
+        dir = dir
+    );
+    Ok(db
+        .get("todos", &query)
+        .await?
+        .as_array()
+        .and_then(|rows| rows.first())
+        .and_then(|row| row.get("position"))
+        .and_then(|p| p.as_str())
+        .map(str::to_string))
+}
+
+/// Highest position among the live subtasks of `parent_id` (live top-level todos for `None`).
+async fn last_sibling_position(
+    db: &SupabaseClient,
+    parent_id: Option<i64>,
+) -> Result<Option<String>> {
+    let parent = match parent_id {
+        Some(id) => format!("eq.{}", id),
+        None => "is.null".to_string(),
+    };
+    let query = format!(
+        "select=position&parent_id={}&position=not.is.null&deleted_at=is.null&order=position.desc&limit=1",
+        parent
+    );
+    Ok(db
+        .get("todos", &query)
+        .await?
+        .as_array()
+        .and_then(|rows| rows.first())
+        .and_then(|row| row.get("position"))
+        .and_then(|p| p.as_str())
+        .map(str::to_string))
+}
+
+/// Insert a todo row and make `owner_id` its owner in Keto, the same for every way a todo is created.
+/// With `list_id`, the list's owner and members inherit access. Callers check access and set
+/// `position` beforehand.
+async fn insert_todo(
+    db: &SupabaseClient,
+    keto: &KetoClient,
+    owner_id: &str,
+    input: &CreateTodo,
+) -> Result<Todo> {
+    let json_value = db.post("todos", serde_json::to_value(input)?).await?;
+    let todo: Todo = match json_value {
+        serde_json::Value::Array(arr) => {
+            let todos: Vec<Todo> = serde_json::from_value(serde_json::Value::Array(arr))?;
+            todos
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::RustError("No todo returned".into()))?
+        }
+        _ => {
+            return Err(Error::RustError(format!(
+                "Expected array, got: {}",
+                json_value
+            )))
+        }
+    };
+
+    keto.create_relation_tuple(
+        KETO_NAMESPACE,
+        &todo.id.to_string(),
+        KETO_RELATION_OWNER,
+        &subject_id(owner_id),
+    )
+    .await?;
+
+    if let Some(list_id) = todo.list_id {
+        link_list_tuples(keto, todo.id, list_id).await?;
+    }
+
+    Ok(todo)
+}
+
+/// Owner among a todo's relation tuples, as a Kratos identity id.
+fn direct_owner(tuples: &[serde_json::Value]) -> Option<&str> {
+    tuples
+        .iter()
+        .filter(|t| t.get("relation").and_then(|r| r.as_str()) == Some(KETO_RELATION_OWNER))
+        .filter_map(|t| t.get("subject_id")?.as_str())
+        .map(|subject| subject.strip_prefix("user:").unwrap_or(subject))
+        .next()
+}
+
+/// Create the next occurrence of a recurring todo that was just completed. The rule moves from
+/// `done` to the new todo, which is claimed by clearing it on `done` first, so completing the same
+/// occurrence twice (or concurrently) never creates two. If the new todo cannot be inserted the
+/// rule is put back on `done`, so a failed completion does not end the series. The new todo gets
+/// the same owner, shares, parent and list. Returns `None` if the series has ended or was already
+/// advanced.
+async fn spawn_next_occurrence(
+    db: &SupabaseClient,
+    keto: &KetoClient,
+    done: &Todo,
+) -> Result<Option<Todo>> {
+    let rule = match done.rrule.as_deref().map(Recurrence::parse) {
+        Some(Ok(rule)) => rule,
+        _ => return Ok(None),
+    };
+
+    // Everything that can fail is looked up before the claim.
+    let next = match rule.advance() {
+        Some(next_rule) => {
+            let tuples = keto
+                .list_all_relation_tuples(ListParams {
+                    namespace: KETO_NAMESPACE.to_string(),
+                    object: Some(done.id.to_string()),
+                    relation: None,
+                    subject_id: None,
+                    subject_set: None,
+                    page_size: Some(KETO_PAGE_SIZE),
+                    page_token: None,
+                })
+                .await?;
+            // Without a due date the series counts from today in the owner's time zone.
+            let tz = match (&done.due_date, direct_owner(&tuples)) {
+                (None, Some(owner)) => settings_repo::timezone_of(db, owner).await?,
+                _ => Tz::UTC,
+            };
+            let now = time::now().with_timezone(&tz);
+            match rule.next_due(done.due_date.as_deref(), now) {
+                Some(next_due) => {
+                    let last = last_sibling_position(db, done.parent_id).await?;
+                    let position = position::key_between(last.as_deref(), None);
+                    Some((next_rule, next_due, tuples, now, position))
+                }
+                None => None,
+            }
+        }
+        None => None,
+    };
+
+    let claimed = db
+        .patch_where(
+            "todos",
+            &format!("id=eq.{}&rrule=not.is.null", done.id),
+            serde_json::json!({ "rrule": null }),
+        )
+        .await?;
+    if parse_todos(claimed)?.is_empty() {
+        return Ok(None);
+    }
+    let Some((next_rule, next_due, tuples, now, position)) = next else {
+        return Ok(None);
+    };
+
+    let remind_at = done.remind_at.as_deref().and_then(|at| {
+        let from = match &done.due_date {
+            Some(due) => due.clone(),
+            None => {
+                time::local_datetime(now.timezone(), now.date_naive(), NaiveTime::MIN)?.to_rfc3339()
+            }
+        };
+        recurrence::shift_by_due(at, &from, &next_due)
+    });
+    let direct: Vec<(&str, &str)> = tuples
+        .iter()
+        .filter_map(|t| {
+            let relation = t.get("relation")?.as_str()?;
+            let subject = t.get("subject_id")?.as_str()?;
+            Some((relation, subject))
+        })
+        .collect();
+    let owner = direct_owner(&tuples);
+
+    let input = CreateTodo {
+        parent_id: done.parent_id,
+        title: done.title.clone(),
+        list_id: done.list_id,
+        description: done.description.clone(),
+        due_date: Some(next_due),
+        priority: done.priority,
+        remind_at,
+        rrule: Some(next_rule.to_string()),
+        series_id: Some(done.series_id.unwrap_or(done.id)),
+        // Last among its siblings in the manual order.
+        position,
+        completed: false,
+    };
+    let inserted = match owner {
+        Some(owner) => insert_todo(db, keto, owner, &input).await,
+        None => Err(Error::RustError(format!(
+            "Recurring todo {} has no owner",
+            done.id
+        ))),
+    };
+    let todo = match inserted {
+        Ok(todo) => todo,
+        Err(e) => {
+            let restored = db
+                .patch_where(
+                    "todos",
+                    &format!("id=eq.{}&rrule=is.null", done.id),
+                    serde_json::json!({ "rrule": done.rrule }),
+                )
+                .await;
+            if let Err(restore_error) = restored {
+                logging::log_error(&format!(
+                    "restore rule of recurring todo {}: {}",
+                    done.id, restore_error
+                ));
+            }
+            return Err(e);
+        }
+    };
+
+    // From here on the series lives on the new todo, so failures no longer end it.
+    for (relation, subject) in direct {
+        if relation != KETO_RELATION_OWNER {
+            keto.create_relation_tuple(KETO_NAMESPACE, &todo.id.to_string(), relation, subject)
+                .await?;
+        }
+    }
+    if let Some(parent_id) = todo.parent_id {
+        link_parent_tuples(keto, todo.id, parent_id).await?;
+    }
+
+    Ok(Some(todo))
+}
+
+/// The open occurrence of the series `series_id`: the live todo currently carrying its rule.
+async fn open_occurrence(db: &SupabaseClient, series_id: i64) -> Result<Option<Todo>> {
+    let query = format!(
+        "select={}&or=(id.eq.{},series_id.eq.{})&rrule=not.is.null&deleted_at=is.null&limit=1",
+        TODO_COLUMNS, series_id, series_id
+    );
+    Ok(parse_todos(db.get("todos", &query).await?)?
+        .into_iter()
+        .next())
+}
+
+/// Fetch one page of live todos among `ids`, applying the filters, order and cursor of `query`;
+/// with `roots_only`, only those whose parent is not among `ids`. Returns the rows and the cursor
+/// of the next page.
+async fn fetch_page(
+    db: &SupabaseClient,
+    ids: &BTreeSet<i64>,
+    query: &ListTodosQuery,
+    roots_only: bool,
+) -> Result<(Vec<Todo>, Option<String>)> {
+    // Fetch one extra row to learn whether another page follows.
+    let q = format!(
+        "select={}&deleted_at=is.null&archived_at=is.null{}{}&limit={}",
+        TODO_COLUMNS,
+        keyset_filter(query),
+        list_filter_params(query),
+        query.limit + 1
+    );
+    let args = serde_json::json!({ "todo_ids": ids, "roots_only": roots_only });
+    let mut items = parse_todos(db.rpc_query("todos_by_ids", &q, args).await?)?;
+
+    let next_cursor = if items.len() > query.limit as usize {
+        items.truncate(query.limit as usize);
+        items.last().map(|last| query.encode_cursor(last))
+    } else {
+        None
+    };
+    Ok((items, next_cursor))
+}
+
+pub struct TodoRepo;
+
+impl TodoRepo {
+    /// List one page of todos the user owns or has been shared (viewer/editor), filtered and sorted per `query`.
+    /// Walks every Keto page to resolve readable ids, then pages the Supabase rows after the cursor.
+    pub async fn list(ctx: &AppContext, user_id: &str, query: &ListTodosQuery) -> Result<TodoPage> {
+        let keto = KetoClient::from_env(ctx)?;
+        let ids = Self::listable_ids(ctx, &keto, user_id, query).await?;
+
+        if ids.is_empty() {
+            return Ok(TodoPage {
+                items: vec![],
+                next_cursor: None,
+            });
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let (items, next_cursor) = fetch_page(&db, &ids, query, false).await?;
+        Ok(TodoPage { items, next_cursor })
+    }
+
+    /// Every live todo the user can read (directly or through their lists), in id order. For
+    /// consumers that need the whole set rather than a page, like the calendar feed.
+    pub async fn list_all(ctx: &AppContext, user_id: &str) -> Result<Vec<Todo>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let db = SupabaseClient::from_env(ctx)?;
+        let mut ids = readable_ids(&keto, user_id, &READ_RELATIONS).await?;
+        let lists = ListRepo::accessible_ids(ctx, user_id).await?;
+        ids.extend(todo_ids_in_lists(&db, &lists).await?);
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let query = format!("select={}&deleted_at=is.null&order=id.asc", TODO_COLUMNS);
+        fetch_by_ids(&db, &ids, &query).await
+    }
+
+    /// Statistics over the todos `list` would return for `query.scope` (ignoring its paging and
+    /// row filters), aggregated by the `todo_stats` function in Supabase.
+    pub async fn stats(
+        ctx: &AppContext,
+        user_id: &str,
+        query: &StatsQuery,
+        today: NaiveDate,
+    ) -> Result<TodoStats> {
+        let keto = KetoClient::from_env(ctx)?;
+        let ids = Self::listable_ids(ctx, &keto, user_id, &query.scope).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .rpc(
+                "todo_stats",
+                serde_json::json!({
+                    "todo_ids": ids,
+                    "from_date": query.from.to_string(),
+                    "to_date": query.to.to_string(),
+                    "today": today.to_string(),
+                }),
+            )
+            .await?;
+        Ok(serde_json::from_value(json_value)?)
+    }
+
+    /// Ids of every todo the user can read, directly or through their lists, trashed or not.
+    pub async fn visible_ids(ctx: &AppContext, user_id: &str) -> Result<BTreeSet<i64>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let db = SupabaseClient::from_env(ctx)?;
+        let mut ids = readable_ids(&keto, user_id, &READ_RELATIONS).await?;
+        let lists = ListRepo::accessible_ids(ctx, user_id).await?;
+        ids.extend(todo_ids_in_lists(&db, &lists).await?);
+        Ok(ids)
+    }
+
+    /// Like `list`, but pages over top-level todos only (no parent, or a parent the user cannot see)
+    /// and nests every subtask beneath them. Subtasks are visible through the parent's access.
+    pub async fn list_tree(
+        ctx: &AppContext,
+        user_id: &str,
+        query: &ListTodosQuery,
+    ) -> Result<TodoPage<TodoNode>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let ids = Self::listable_ids(ctx, &keto, user_id, query).await?;
+
+        if ids.is_empty() {
+            return Ok(TodoPage {
+                items: vec![],
+                next_cursor: None,
+            });
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let (roots, next_cursor) = fetch_page(&db, &ids, query, true).await?;
+
+        let root_ids: Vec<i64> = roots.iter().map(|t| t.id).collect();
+        let descendants = fetch_descendants(&db, &root_ids).await?;
+
+        Ok(TodoPage {
+            items: build_tree(roots, descendants),
+            next_cursor,
+        })
+    }
+
+    /// Direct subtasks of a todo the user can read, oldest first.
+    pub async fn list_children(ctx: &AppContext, user_id: &str, id: i64) -> Result<Vec<Todo>> {
+        let parent = Self::get(ctx, user_id, id).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select={}&parent_id=eq.{}&deleted_at=is.null&order=created_at.asc",
+            TODO_COLUMNS, parent.id
+        );
+        parse_todos(db.get("todos", &query).await?)
+    }
+
+    /// Create a subtask under a todo the user can change (owner or editor). The caller owns the
+    /// subtask, and everyone with access to the parent inherits the same access via subject sets.
+    pub async fn create_child(
+        ctx: &AppContext,
+        user_id: &str,
+        parent_id: i64,
+        input: CreateTodo,
+    ) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, parent_id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        if fetch_todo(&db, parent_id).await?.is_none() {
+            return Err(Error::RustError("Todo not found".into()));
+        }
+        if ancestor_ids(&db, parent_id).await?.len() + 1 > MAX_SUBTASK_DEPTH {
+            return Err(Error::RustError(format!(
+                "Bad Request: Subtasks can be nested at most {} levels deep",
+                MAX_SUBTASK_DEPTH
+            )));
+        }
+
+        let todo = Self::create(
+            ctx,
+            user_id,
+            CreateTodo {
+                parent_id: Some(parent_id),
+                ..input
+            },
+        )
+        .await?;
+        link_parent_tuples(&keto, todo.id, parent_id).await?;
+
+        Ok(todo)
+    }
+
+    /// Ids a list query pages over: todos the user can read directly plus every todo in their lists,
+    /// or only the todos of `query.list_id` if set; then narrowed to `query.tag` and
+    /// `query.assigned_to_me` if set.
+    async fn listable_ids(
+        ctx: &AppContext,
+        keto: &KetoClient,
+        user_id: &str,
+        query: &ListTodosQuery,
+    ) -> Result<BTreeSet<i64>> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let ids = match query.list_id {
+            Some(list_id) => {
+                ListRepo::ensure_member(ctx, user_id, list_id).await?;
+                todo_ids_in_lists(&db, &BTreeSet::from([list_id])).await?
+            }
+            None => {
+                let mut ids = readable_ids(keto, user_id, &READ_RELATIONS).await?;
+                let lists = ListRepo::accessible_ids(ctx, user_id).await?;
+                ids.extend(todo_ids_in_lists(&db, &lists).await?);
+                ids
+            }
+        };
+        let ids = match query.tag {
+            Some(tag_id) => {
+                let tagged = TagRepo::tagged_todo_ids(ctx, user_id, tag_id).await?;
+                ids.intersection(&tagged).copied().collect()
+            }
+            None => ids,
+        };
+        if query.assigned_to_me {
+            let assigned =
+                object_ids(keto, KETO_NAMESPACE, user_id, KETO_RELATION_ASSIGNEE).await?;
+            return Ok(assigned.into_iter().filter(|id| ids.contains(id)).collect());
+        }
+        Ok(ids)
+    }
+
+    /// Get a single todo the user can read (owner, editor or viewer).
+    /// Errors with `Todo not found` if the row does not exist, otherwise `Forbidden` when access is denied.
+    pub async fn get(ctx: &AppContext, user_id: &str, id: i64) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        let allowed = has_any_relation(&keto, KETO_NAMESPACE, user_id, id, &READ_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        match fetch_todo(&db, id).await? {
+            Some(todo) if allowed => Ok(todo),
+            Some(_) => Err(Error::RustError("Forbidden".into())),
+            None => Err(Error::RustError("Todo not found".into())),
+        }
+    }
+
+    /// Errors unless the todo is live and the user may work on it (owner, editor or assignee), the
+    /// same check `update` makes for completing it.
+    pub async fn ensure_completable(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &COMPLETE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        match fetch_todo(&db, id).await? {
+            Some(_) => Ok(()),
+            None => Err(Error::RustError("Todo not found".into())),
+        }
+    }
+
+    /// Errors unless the todo is live and the user can change it (owner or editor), the same check
+    /// `update` makes. For related resources that follow the todo's write access.
+    pub async fn ensure_writable(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        match fetch_todo(&db, id).await? {
+            Some(_) => Ok(()),
+            None => Err(Error::RustError("Todo not found".into())),
+        }
+    }
+
+    /// List all todos with owner info (admin-only).
+    pub async fn list_all_with_owner(ctx: &AppContext) -> Result<Vec<AdminTodo>> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!("select={}&order=created_at.desc", TODO_COLUMNS);
+        let json_value = db.get("todos", &query).await?;
+        let todos: Vec<Todo> = match json_value {
+            serde_json::Value::Array(arr) => serde_json::from_value(serde_json::Value::Array(arr))?,
+            _ => {
+                return Err(Error::RustError(format!(
+                    "Expected array, got: {}",
+                    json_value
+                )))
+            }
+        };
+
+        let keto = KetoClient::from_env(ctx)?;
+        let tuples = keto
+            .list_all_relation_tuples(ListParams {
+                namespace: KETO_NAMESPACE.to_string(),
+                object: None,
+                relation: Some(KETO_RELATION_OWNER.to_string()),
+                subject_id: None,
+                subject_set: None,
+                page_size: Some(KETO_PAGE_SIZE),
+                page_token: None,
+            })
+            .await?;
+
+        let mut owners: HashMap<i64, String> = HashMap::new();
+        for t in &tuples {
+            let object = t.get("object").and_then(|o| o.as_str());
+            let subject_id = t.get("subject_id").and_then(|s| s.as_str());
+            if let (Some(obj), Some(sub)) = (object, subject_id) {
+                if let Ok(id) = obj.parse::<i64>() {
+                    let owner_id = sub.strip_prefix("user:").unwrap_or(sub).to_string();
+                    owners.insert(id, owner_id);
+                }
+            }
+        }
+
+        let admin_todos = todos
+            .into_iter()
+            .map(|t| AdminTodo {
+                owner_id: owners.get(&t.id).cloned(),
+                owner_email: None,
+                todo: t,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(admin_todos)
+    }
+
+    /// Position key that puts a new todo first in the user's manual order, ahead of every live
+    /// todo they can see. Todos created with the same key are ordered by id.
+    pub async fn first_position(ctx: &AppContext, user_id: &str) -> Result<String> {
+        Ok(Self::first_positions(ctx, user_id, 1).await?.remove(0))
+    }
+
+    /// `count` ascending position keys that put new todos first in the user's manual order, in
+    /// the order of the keys.
+    pub async fn first_positions(
+        ctx: &AppContext,
+        user_id: &str,
+        count: usize,
+    ) -> Result<Vec<String>> {
+        let scope = Self::visible_ids(ctx, user_id).await?;
+        let db = SupabaseClient::from_env(ctx)?;
+        let first = neighbour_position(&db, &scope, None, true, None).await?;
+        position::keys_between(None, first.as_deref(), count.max(1))
+            .ok_or_else(|| Error::RustError(format!("No position before {:?}", first)))
+    }
+
+    /// Create a todo and set the caller as owner in Keto. With `list_id`, the caller must own or be
+    /// a member of the list, and the list's owner and members inherit access to the todo. Unless
+    /// `input.position` is set, the todo goes first in the caller's manual order.
+    pub async fn create(ctx: &AppContext, user_id: &str, input: CreateTodo) -> Result<Todo> {
+        if let Some(list_id) = input.list_id {
+            ListRepo::ensure_member(ctx, user_id, list_id).await?;
+        }
+
+        let rrule = match input.rrule.as_deref().map(Recurrence::parse) {
+            Some(Ok(rule)) => Some(rule.to_string()),
+            Some(Err(e)) => return Err(Error::RustError(format!("Bad Request: {}", e))),
+            None => None,
+        };
+        let position = match input.position {
+            Some(position) => position,
+            None => Self::first_position(ctx, user_id).await?,
+        };
+        let input = CreateTodo {
+            title: input.title.trim().to_string(),
+            rrule,
+            position: Some(position),
+            ..input
+        };
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let keto = KetoClient::from_env(ctx)?;
+        let todo = insert_todo(&db, &keto, user_id, &input).await?;
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(todo.id, Some(user_id), ActivityAction::Created)
+                    .diff(None, Some(&todo)),
+            ],
+        )
+        .await;
+        Ok(todo)
+    }
+
+    /// Apply a partial update if the user is owner or editor. Only fields present in `patch` are
+    /// written. Moving a todo (`parent_id`) requires owning it and write access to the new parent,
+    /// and is rejected if it would create a cycle or exceed the nesting limit. Moving it into or
+    /// out of a list (`list_id`) requires owning the todo, and owning the target list. With
+    /// `options.cascade`, completing a todo also completes its subtasks. Completing a recurring
+    /// todo creates its next occurrence. With `options.if_match`, errors with `Precondition
+    /// Failed` unless the todo is at that version. The assignee may toggle `completed` (without
+    /// `cascade`) and nothing else. Completing a todo (or, with `cascade`, one of its subtasks)
+    /// with an open blocker is a `Conflict` unless `options.force` is set.
+    pub async fn update(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        patch: UpdateTodo,
+        options: UpdateTodoOptions,
+    ) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        if patch.only_completed() && !options.cascade {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &COMPLETE_RELATIONS).await?;
+        } else {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let current = fetch_todo(&db, id)
+            .await?
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        if options.if_match.is_some_and(|v| v != current.version) {
+            return Err(precondition_failed());
+        }
+        let completing = patch.completed == Some(Some(true));
+        // Open subtasks a cascade completes, looked up first so their blockers are checked too.
+        let cascaded: Vec<i64> = if options.cascade && completing {
+            fetch_descendants(&db, &[id])
+                .await?
+                .into_iter()
+                .filter(|t| !t.completed)
+                .map(|t| t.id)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if completing && !options.force {
+            let mut checked = cascaded.clone();
+            if !current.completed {
+                checked.push(id);
+            }
+            if !dependency_repo::blocked_ids(&db, &checked)
+                .await?
+                .is_empty()
+            {
+                return Err(Error::RustError(
+                    "Conflict: The todo or one of its subtasks is blocked by open todos; pass \
+                     force=true to complete it anyway"
+                        .into(),
+                ));
+            }
+        }
+
+        // The parent's owners become owners of the todo, so only an owner may re-parent it.
+        let new_parent = patch.parent_id.filter(|p| *p != current.parent_id);
+        if new_parent.is_some() {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+        }
+        if let Some(Some(parent_id)) = new_parent {
+            if parent_id == id {
+                return Err(Error::RustError(
+                    "Bad Request: A todo cannot be its own parent".into(),
+                ));
+            }
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, parent_id, &WRITE_RELATIONS)
+                .await?;
+            if fetch_todo(&db, parent_id).await?.is_none() {
+                return Err(Error::RustError("Todo not found".into()));
+            }
+            let ancestors = ancestor_ids(&db, parent_id).await?;
+            if ancestors.contains(&id) {
+                return Err(Error::RustError(
+                    "Bad Request: A todo cannot be moved under its own subtask".into(),
+                ));
+            }
+            if ancestors.len() + 1 + subtree_height(&db, id).await? > MAX_SUBTASK_DEPTH {
+                return Err(Error::RustError(format!(
+                    "Bad Request: Subtasks can be nested at most {} levels deep",
+                    MAX_SUBTASK_DEPTH
+                )));
+            }
+        }
+
+        // The list's owner and members gain access to the todo, so moving it between lists takes
+        // owning both the todo and the target list.
+        let new_list = patch.list_id.filter(|l| *l != current.list_id);
+        if new_list.is_some() {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+        }
+        if let Some(Some(list_id)) = new_list {
+            ListRepo::ensure_owner(ctx, user_id, list_id).await?;
+        }
+
+        let json_value = db
+            .patch_where(
+                "todos",
+                &live_version_filter(id, options.if_match),
+                patch.to_patch_body(),
+            )
+            .await?;
+        let mut todo = match parse_todos(json_value)?.into_iter().next() {
+            Some(todo) => todo,
+            None if options.if_match.is_some() => return Err(precondition_failed()),
+            None => return Err(Error::RustError("Todo not found".into())),
+        };
+
+        if let Some(parent) = new_parent {
+            if let Some(old) = current.parent_id {
+                unlink_parent_tuples(&keto, id, old).await?;
+            }
+            if let Some(new) = parent {
+                link_parent_tuples(&keto, id, new).await?;
+            }
+        }
+        if let Some(list) = new_list {
+            if let Some(old) = current.list_id {
+                unlink_list_tuples(&keto, id, old).await?;
+            }
+            if let Some(new) = list {
+                link_list_tuples(&keto, id, new).await?;
+            }
+        }
+
+        let mut activity = Vec::new();
+        if patch.completed == Some(Some(true)) && !current.completed && todo.rrule.is_some() {
+            if let Some(next) = spawn_next_occurrence(&db, &keto, &todo).await? {
+                activity.push(
+                    NewActivity::new(next.id, Some(user_id), ActivityAction::Created)
+                        .diff(None, Some(&next)),
+                );
+            }
+            todo.rrule = None;
+        }
+        activity.push(
+            NewActivity::new(id, Some(user_id), ActivityAction::Updated)
+                .diff(Some(&current), Some(&todo)),
+        );
+
+        if !cascaded.is_empty() {
+            let completed = parse_todos(
+                db.patch_where(
+                    "todos",
+                    &format!(
+                        "id=in.({})&completed=eq.false&deleted_at=is.null",
+                        join_ids(&cascaded)
+                    ),
+                    serde_json::json!({ "completed": true }),
+                )
+                .await?,
+            )?;
+            // Recurring subtasks advance their series, as when they are completed one by one.
+            for done in completed.iter().filter(|t| t.rrule.is_some()) {
+                if let Some(next) = spawn_next_occurrence(&db, &keto, done).await? {
+                    activity.push(
+                        NewActivity::new(next.id, Some(user_id), ActivityAction::Created)
+                            .diff(None, Some(&next)),
+                    );
+                }
+            }
+            activity.extend(completed.iter().map(|t| {
+                NewActivity::new(t.id, Some(user_id), ActivityAction::Updated).change(
+                    "completed",
+                    false,
+                    true,
+                )
+            }));
+        }
+
+        activity.retain(|a| !a.changes.is_empty());
+        ActivityRepo::record(ctx, activity).await;
+        Ok(todo)
+    }
+
+    /// Occurrences of the recurring series `id` belongs to that the user can see, oldest first.
+    pub async fn list_series(ctx: &AppContext, user_id: &str, id: i64) -> Result<Vec<Todo>> {
+        let todo = Self::get(ctx, user_id, id).await?;
+        let series_id = todo.series_id.unwrap_or(todo.id);
+
+        let keto = KetoClient::from_env(ctx)?;
+        let db = SupabaseClient::from_env(ctx)?;
+        let mut visible = readable_ids(&keto, user_id, &READ_RELATIONS).await?;
+        let lists = ListRepo::accessible_ids(ctx, user_id).await?;
+        visible.extend(todo_ids_in_lists(&db, &lists).await?);
+
+        let query = format!(
+            "select={}&or=(id.eq.{},series_id.eq.{})&deleted_at=is.null&order=created_at.asc",
+            TODO_COLUMNS, series_id, series_id
+        );
+        Ok(parse_todos(db.get("todos", &query).await?)?
+            .into_iter()
+            .filter(|t| visible.contains(&t.id))
+            .collect())
+    }
+
+    /// Replace the rule of the series `id` belongs to (owner or editor of its open occurrence).
+    /// If `id` is an open todo that is not recurring yet, this starts a series on it.
+    pub async fn update_series(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        rule: &Recurrence,
+    ) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let todo = fetch_todo(&db, id)
+            .await?
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        let target = match open_occurrence(&db, todo.series_id.unwrap_or(todo.id)).await? {
+            Some(open) => {
+                ensure_any_relation(&keto, KETO_NAMESPACE, user_id, open.id, &WRITE_RELATIONS)
+                    .await?;
+                open
+            }
+            None if !todo.completed => todo,
+            None => {
+                return Err(Error::RustError(
+                    "Bad Request: The series has ended; set a rule on an open todo instead".into(),
+                ))
+            }
+        };
+
+        let json_value = db
+            .patch_where(
+                "todos",
+                &format!("id=eq.{}&deleted_at=is.null", target.id),
+                serde_json::json!({ "rrule": rule.to_string() }),
+            )
+            .await?;
+        let updated = parse_todos(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(updated.id, Some(user_id), ActivityAction::Updated)
+                    .diff(Some(&target), Some(&updated)),
+            ],
+        )
+        .await;
+        Ok(updated)
+    }
+
+    /// Stop the series `id` belongs to: its open occurrence keeps its place but no further
+    /// occurrences are created. Past occurrences are left as they are.
+    pub async fn stop_series(ctx: &AppContext, user_id: &str, id: i64) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let todo = fetch_todo(&db, id)
+            .await?
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        let open = open_occurrence(&db, todo.series_id.unwrap_or(todo.id))
+            .await?
+            .ok_or_else(|| Error::RustError("Series not found".into()))?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, open.id, &WRITE_RELATIONS).await?;
+
+        let json_value = db
+            .patch_where(
+                "todos",
+                &format!("id=eq.{}", open.id),
+                serde_json::json!({ "rrule": null }),
+            )
+            .await?;
+        let stopped = parse_todos(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(stopped.id, Some(user_id), ActivityAction::Updated)
+                    .diff(Some(&open), Some(&stopped)),
+            ],
+        )
+        .await;
+        Ok(stopped)
+    }
+
+    /// Apply a batch of operations, each with the outcome its single-todo route would have.
+    /// Write access (owner or editor) is checked for all todos concurrently; completions, reopenings
+    /// and deletes are then written with one `id=in.(...)` request each, retitles one per todo.
+    /// Completing a recurring todo creates its next occurrence, as `update` does, and completing a
+    /// todo with an open blocker fails with 409.
+    pub async fn batch(
+        ctx: &AppContext,
+        user_id: &str,
+        operations: Vec<BatchOperation>,
+    ) -> Result<Vec<BatchResult>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let allowed = join_all(operations.iter().map(|op| {
+            let relations: &[&str] = match op {
+                BatchOperation::Complete { .. } | BatchOperation::Uncomplete { .. } => {
+                    &COMPLETE_RELATIONS
+                }
+                _ => &WRITE_RELATIONS,
+            };
+            has_any_relation(&keto, KETO_NAMESPACE, user_id, op.id(), relations)
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<bool>>>()?;
+
+        let mut complete = Vec::new();
+        let mut uncomplete = Vec::new();
+        let mut delete = Vec::new();
+        let mut retitle = Vec::new();
+        for (op, allowed) in operations.iter().zip(&allowed) {
+            if !allowed {
+                continue;
+            }
+            match op {
+                BatchOperation::Complete { id } => complete.push(*id),
+                BatchOperation::Uncomplete { id } => uncomplete.push(*id),
+                BatchOperation::Delete { id } => delete.push(*id),
+                BatchOperation::Retitle { id, title } => retitle.push((*id, title.trim())),
+            }
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        // Every todo the batch may change, read before the writes for the activity diff and to
+        // see which completions advance a recurring series.
+        let touched: Vec<i64> = operations
+            .iter()
+            .zip(&allowed)
+            .filter(|(_, allowed)| **allowed)
+            .map(|(op, _)| op.id())
+            .collect();
+        let before: HashMap<i64, Todo> = if touched.is_empty() {
+            HashMap::new()
+        } else {
+            let query = format!(
+                "select={}&id=in.({})&deleted_at=is.null",
+                TODO_COLUMNS,
+                join_ids(&touched)
+            );
+            parse_todos(db.get("todos", &query).await?)?
+                .into_iter()
+                .map(|t| (t.id, t))
+                .collect()
+        };
+        // Open todos with an open blocker are not completed; the batch has no `force`.
+        let blocked: BTreeSet<i64> = dependency_repo::blocked_ids(&db, &complete)
+            .await?
+            .into_iter()
+            .filter(|id| before.get(id).is_some_and(|t| !t.completed))
+            .collect();
+        complete.retain(|id| !blocked.contains(id));
+
+        let mut activity = Vec::new();
+        let mut done = patch_many(&db, &complete, serde_json::json!({ "completed": true })).await?;
+        for updated in done.values_mut() {
+            let advances = before
+                .get(&updated.id)
+                .is_some_and(|t| !t.completed && t.rrule.is_some());
+            if advances {
+                if let Some(next) = spawn_next_occurrence(&db, &keto, updated).await? {
+                    activity.push(
+                        NewActivity::new(next.id, Some(user_id), ActivityAction::Created)
+                            .diff(None, Some(&next)),
+                    );
+                }
+                updated.rrule = None;
+            }
+        }
+        let mut updated = done;
+        updated
+            .extend(patch_many(&db, &uncomplete, serde_json::json!({ "completed": false })).await?);
+        let deleted = patch_many(
+            &db,
+            &delete,
+            serde_json::json!({ "deleted_at": time::now_rfc3339() }),
+        )
+        .await?;
+        let retitled = join_all(retitle.iter().map(|(id, title)| {
+            patch_many(
+                &db,
+                std::slice::from_ref(id),
+                serde_json::json!({ "title": title }),
+            )
+        }))
+        .await;
+        for result in retitled {
+            updated.extend(result?);
+        }
+
+        for todo in updated.values() {
+            activity.push(
+                NewActivity::new(todo.id, Some(user_id), ActivityAction::Updated)
+                    .diff(before.get(&todo.id), Some(todo)),
+            );
+        }
+        for todo in deleted.values() {
+            activity.push(
+                NewActivity::new(todo.id, Some(user_id), ActivityAction::Deleted)
+                    .diff(before.get(&todo.id), Some(todo)),
+            );
+        }
+        activity.retain(|a| !a.changes.is_empty());
+        ActivityRepo::record(ctx, activity).await;
+
+        Ok(operations
+            .iter()
+            .zip(allowed)
+            .map(|(op, allowed)| {
+                let id = op.id();
+                let (status, error, todo) = if !allowed {
+                    (403, Some("Forbidden".to_string()), None)
+                } else if matches!(op, BatchOperation::Complete { .. }) && blocked.contains(&id) {
+                    (409, Some("Blocked by open todos".to_string()), None)
+                } else if let BatchOperation::Delete { .. } = op {
+                    match deleted.contains_key(&id) {
+                        true => (204, None, None),
+                        false => (404, Some("Todo not found".to_string()), None),
+                    }
+                } else {
+                    match updated.remove(&id) {
+                        Some(todo) => (200, None, Some(todo)),
+                        None => (404, Some("Todo not found".to_string()), None),
+                    }
+                };
+                BatchResult {
+                    id,
+                    op: op.name(),
+                    status,
+                    error,
+                    todo,
+                }
+            })
+            .collect())
+    }
+
+    /// Move a todo in the manual order (owner or editor), right after `after` and/or right before
+    /// `before`; the anchors must be todos the user can read. Writes only the moved row.
+    pub async fn move_todo(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        input: MoveTodo,
+    ) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let current = fetch_todo(&db, id)
+            .await?
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        let mut anchors = Vec::new();
+        for anchor in [input.after, input.before] {
+            let position = match anchor {
+                Some(anchor_id) => {
+                    ensure_any_relation(&keto, KETO_NAMESPACE, user_id, anchor_id, &READ_RELATIONS)
+                        .await?;
+                    let todo = fetch_todo(&db, anchor_id)
+                        .await?
+                        .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+                    Some(todo.position.ok_or_else(|| {
+                        Error::RustError(format!("Conflict: Todo {} has no position", anchor_id))
+                    })?)
+                }
+                None => None,
+            };
+            anchors.push(position);
+        }
+        let (after, before) = (anchors[0].take(), anchors[1].take());
+        // With one anchor, the other bound is the next todo this user sees, not anyone's.
+        let scope = match (&after, &before) {
+            (Some(lower), Some(upper)) if lower != upper => BTreeSet::new(),
+            _ => Self::visible_ids(ctx, user_id).await?,
+        };
+
+        let (lower, upper) = match (after, before) {
+            (Some(lower), Some(upper)) if lower < upper => (Some(lower), Some(upper)),
+            // Anchors created concurrently can share a key; place the todo after both.
+            (Some(lower), Some(upper)) if lower == upper => {
+                let upper = neighbour_position(&db, &scope, Some(&lower), true, Some(id)).await?;
+                (Some(lower), upper)
+            }
+            (Some(_), Some(_)) => {
+                return Err(Error::RustError(
+                    "Bad Request: after must come before before in the order".into(),
+                ))
+            }
+            (Some(lower), None) => {
+                let upper = neighbour_position(&db, &scope, Some(&lower), true, Some(id)).await?;
+                (Some(lower), upper)
+            }
+            (None, Some(upper)) => {
+                let lower = neighbour_position(&db, &scope, Some(&upper), false, Some(id)).await?;
+                (lower, Some(upper))
+            }
+            (None, None) => {
+                return Err(Error::RustError(
+                    "Bad Request: before or after is required".into(),
+                ))
+            }
+        };
+        let key = position::key_between(lower.as_deref(), upper.as_deref()).ok_or_else(|| {
+            Error::RustError(format!("No position between {:?} and {:?}", lower, upper))
+        })?;
+
+        let json_value = db
+            .patch_where(
+                "todos",
+                &format!("id=eq.{}&deleted_at=is.null", id),
+                serde_json::json!({ "position": key }),
+            )
+            .await?;
+        let moved = parse_todos(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        ActivityRepo::record(
+            ctx,
+            vec![NewActivity::new(id, Some(user_id), ActivityAction::Moved)
+                .diff(Some(&current), Some(&moved))],
+        )
+        .await;
+        Ok(moved)
+    }
+
+    /// Give todos whose position key is longer than `max_len` a new key between their neighbours,
+    /// so keys stay short however often todos are moved. Only those rows are rewritten, in order,
+    /// at most `REBALANCE_BATCH_SIZE` per run; a row moved meanwhile is left alone. Returns how
+    /// many todos were rewritten, or `None` if none needed it.
+    pub async fn rebalance_positions(ctx: &AppContext, max_len: usize) -> Result<Option<u64>> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select=id,position&position_len=gt.{}&order=position.asc,id.asc&limit={}",
+            max_len, REBALANCE_BATCH_SIZE
+        );
+        let too_long = parse_todos_positions(db.get("todos", &query).await?);
+        if too_long.is_empty() {
+            return Ok(None);
+        }
+
+        let mut rewritten = 0;
+        for (id, key) in too_long {
+            let lower = adjacent_position(&db, &key, id, false).await?;
+            let upper = adjacent_position(&db, &key, id, true).await?;
+            let shorter = position::key_between(lower.as_deref(), upper.as_deref())
+                .filter(|k| k.len() < key.len());
+            let Some(shorter) = shorter else {
+                continue;
+            };
+            let updated = db
+                .patch_where(
+                    "todos",
+                    &format!("id=eq.{}&position=eq.{}", id, encode_value(&key)),
+                    serde_json::json!({ "position": shorter }),
+                )
+                .await?;
+            if updated.as_array().is_some_and(|rows| !rows.is_empty()) {
+                rewritten += 1;
+            }
+        }
+        Ok(Some(rewritten))
+    }
+
+    /// Move a todo to the trash if the user is owner or editor. Keto tuples are kept so it can be
+    /// restored; the row and tuples are removed for good by `purge_deleted` after the retention period.
+    /// With `if_match`, errors with `Precondition Failed` unless the todo is at that version.
+    pub async fn delete(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        if_match: Option<i64>,
+    ) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .patch_where(
+                "todos",
+                &live_version_filter(id, if_match),
+                serde_json::json!({ "deleted_at": time::now_rfc3339() }),
+            )
+            .await?;
+        let deleted = match parse_todos(json_value)?.into_iter().next() {
+            Some(todo) => todo,
+            // Tell a stale version apart from a todo that is gone.
+            None if if_match.is_some() && fetch_todo(&db, id).await?.is_some() => {
+                return Err(precondition_failed())
+            }
+            None => return Err(Error::RustError("Todo not found".into())),
+        };
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(id, Some(user_id), ActivityAction::Deleted).change(
+                    "deleted_at",
+                    None::<String>,
+                    deleted.deleted_at,
+                ),
+            ],
+        )
+        .await;
+
+        Ok(())
+    }
 
-impl TodoRepo {
-    /// List todos owned by the user. Uses Keto to resolve owned ids, then fetches from Supabase.
-    pub async fn list(ctx: &AppContext, user_id: &str) -> Result<Vec<Todo>> {
+    /// Trashed todos the user could restore (owner or editor), most recently deleted first.
+    pub async fn list_trash(ctx: &AppContext, user_id: &str) -> Result<Vec<Todo>> {
         let keto = KetoClient::from_env(ctx)?;
-        let sub = subject_id(user_id);
+        let ids = readable_ids(&keto, user_id, &WRITE_RELATIONS).await?;
 
-        let list = keto
-            .list_relation_tuples(ListParams {
-                namespace: KETO_NAMESPACE.to_string(),
-                object: None,
-                relation: Some(KETO_RELATION_OWNER.to_string()),
-                subject_id: Some(sub),
-                subject_set: None,
-                page_size: Some(500),
-                page_token: None,
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select={}&deleted_at=not.is.null&order=deleted_at.desc",
+            TODO_COLUMNS
+        );
+        fetch_by_ids(&db, &ids, &query).await
+    }
+
+    /// Take a todo out of the trash if the user is owner or editor.
+    pub async fn restore(ctx: &AppContext, user_id: &str, id: i64) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .patch_where(
+                "todos",
+                &format!("id=eq.{}&deleted_at=not.is.null", id),
+                serde_json::json!({ "deleted_at": null }),
+            )
+            .await?;
+        let restored = parse_todos(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        ActivityRepo::record(
+            ctx,
+            vec![NewActivity::new(
+                id,
+                Some(user_id),
+                ActivityAction::Restored,
+            )],
+        )
+        .await;
+        Ok(restored)
+    }
+
+    /// Archived todos the user can read (directly or through their lists), most recently
+    /// archived first.
+    pub async fn list_archive(ctx: &AppContext, user_id: &str) -> Result<Vec<Todo>> {
+        let keto = KetoClient::from_env(ctx)?;
+        let db = SupabaseClient::from_env(ctx)?;
+        let mut ids = readable_ids(&keto, user_id, &READ_RELATIONS).await?;
+        let lists = ListRepo::accessible_ids(ctx, user_id).await?;
+        ids.extend(todo_ids_in_lists(&db, &lists).await?);
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let query = format!(
+            "select={}&archived_at=not.is.null&deleted_at=is.null&order=archived_at.desc",
+            TODO_COLUMNS
+        );
+        fetch_by_ids(&db, &ids, &query).await
+    }
+
+    /// Take a todo out of the archive if the user is owner or editor. It stays completed, and is
+    /// not archived again unless it is completed anew.
+    pub async fn unarchive(ctx: &AppContext, user_id: &str, id: i64) -> Result<Todo> {
+        let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &WRITE_RELATIONS).await?;
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .patch_where(
+                "todos",
+                &format!("id=eq.{}&archived_at=not.is.null&deleted_at=is.null", id),
+                serde_json::json!({ "archived_at": null, "archive_after": "infinity" }),
+            )
+            .await?;
+        let unarchived = parse_todos(json_value)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RustError("Todo not found".into()))?;
+        ActivityRepo::record(
+            ctx,
+            vec![NewActivity::new(
+                id,
+                Some(user_id),
+                ActivityAction::Unarchived,
+            )],
+        )
+        .await;
+        Ok(unarchived)
+    }
+
+    /// Completed todos the archive job has not scheduled yet, oldest completion first.
+    pub async fn unscheduled_archives(ctx: &AppContext, limit: u32) -> Result<Vec<i64>> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select=id&completed=eq.true&archived_at=is.null&deleted_at=is.null&archive_after=is.null&order=completed_at.asc&limit={}",
+            limit
+        );
+        Ok(db
+            .get("todos", &query)
+            .await?
+            .as_array()
+            .map(|a| {
+                a.iter()
+                    .filter_map(|r| r.get("id").and_then(|v| v.as_i64()))
+                    .collect()
             })
+            .unwrap_or_default())
+    }
+
+    /// Set when the archive job archives `ids`: `days` after their completion, or never for `None`.
+    pub async fn schedule_archive(ctx: &AppContext, ids: &[i64], days: Option<u32>) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let db = SupabaseClient::from_env(ctx)?;
+        db.rpc(
+            "schedule_todo_archive",
+            serde_json::json!({ "todo_ids": ids, "days": days }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Forget the archive schedule of the user's completed todos, so the archive job resolves it
+    /// again from their current setting.
+    pub async fn reschedule_archive(ctx: &AppContext, user_id: &str) -> Result<()> {
+        let ids = Self::owned_ids(ctx, user_id).await?;
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let db = SupabaseClient::from_env(ctx)?;
+        db.rpc(
+            "reset_todo_archive_schedule",
+            serde_json::json!({ "todo_ids": ids }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Archive every live completed todo whose scheduled time has passed. Returns how many.
+    pub async fn archive_due(ctx: &AppContext) -> Result<usize> {
+        let now = time::now_rfc3339();
+        let db = SupabaseClient::from_env(ctx)?;
+        let json_value = db
+            .patch_where(
+                "todos",
+                &format!(
+                    "archive_after=lte.{}&completed=eq.true&archived_at=is.null&deleted_at=is.null",
+                    encode_value(&now)
+                ),
+                serde_json::json!({ "archived_at": now }),
+            )
             .await?;
+        let archived = parse_todos(json_value)?;
+        ActivityRepo::record(
+            ctx,
+            archived
+                .iter()
+                .map(|t| {
+                    NewActivity::new(t.id, None, ActivityAction::Archived).change(
+                        "archived_at",
+                        None::<String>,
+                        t.archived_at.clone(),
+                    )
+                })
+                .collect(),
+        )
+        .await;
+        Ok(archived.len())
+    }
 
-        let ids: Vec<i64> = list
-            .get("relation_tuples")
-            .and_then(|a| a.as_array())
+    /// Permanently delete up to `PURGE_BATCH_SIZE` todos trashed before `cutoff` (RFC 3339),
+    /// along with their Keto tuples and attachment objects. Returns how many were purged.
+    pub async fn purge_deleted(ctx: &AppContext, cutoff: &str) -> Result<usize> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select=id&deleted_at=lt.{}&order=deleted_at.asc&limit={}",
+            encode_value(cutoff),
+            PURGE_BATCH_SIZE
+        );
+        let ids: Vec<i64> = db
+            .get("todos", &query)
+            .await?
+            .as_array()
             .map(|a| {
                 a.iter()
-                    .filter_map(|t| t.get("object").and_then(|o| o.as_str()))
-                    .filter_map(|s| s.parse::<i64>().ok())
+                    .filter_map(|r| r.get("id").and_then(|v| v.as_i64()))
                     .collect()
             })
             .unwrap_or_default();
 
         if ids.is_empty() {
-            return Ok(vec![]);
+            return Ok(0);
+        }
+
+        AttachmentRepo::delete_for_todos(ctx, &ids).await?;
+        db.delete_where("todos", &format!("id=in.({})", join_ids(&ids)))
+            .await?;
+
+        let keto = KetoClient::from_env(ctx)?;
+        for id in &ids {
+            delete_object_tuples(&keto, *id).await;
         }
+        ActivityRepo::record(
+            ctx,
+            ids.iter()
+                .map(|id| NewActivity::new(*id, None, ActivityAction::Purged))
+                .collect(),
+        )
+        .await;
 
+        Ok(ids.len())
+    }
+
+    /// Live, incomplete todos whose reminder is due at `now` (RFC 3339) and not yet sent, oldest first.
+    pub async fn due_reminders(ctx: &AppContext, now: &str, limit: u32) -> Result<Vec<Todo>> {
         let db = SupabaseClient::from_env(ctx)?;
-        let id_list = ids
-            .iter()
-            .map(|i| i.to_string())
-            .collect::<Vec<_>>()
-            .join(",");
         let query = format!(
-            "select=id,title,completed,created_at&id=in.({})&order=created_at.desc",
-            id_list
+            "select={}&remind_at=lte.{}&reminded_at=is.null&completed=eq.false&deleted_at=is.null&order=remind_at.asc&limit={}",
+            TODO_COLUMNS,
+            encode_value(now),
+            limit
         );
-        let json_value = db.get("todos", &query).await?;
-        match json_value {
-            serde_json::Value::Array(arr) => {
-                let todos: Vec<Todo> = serde_json::from_value(serde_json::Value::Array(arr))?;
-                Ok(todos)
-            }
-            _ => Err(Error::RustError(format!(
-                "Expected array, got: {}",
-                json_value
-            ))),
+        parse_todos(db.get("todos", &query).await?)
+    }
+
+    /// Mark a reminder as sent at `now`. Returns `false` if another run already claimed it, so
+    /// overlapping runs never send the same reminder twice.
+    pub async fn claim_reminder(ctx: &AppContext, id: i64, now: &str) -> Result<bool> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let updated = db
+            .patch_where(
+                "todos",
+                &format!("id=eq.{}&reminded_at=is.null", id),
+                serde_json::json!({ "reminded_at": now }),
+            )
+            .await?;
+        Ok(updated.as_array().is_some_and(|a| !a.is_empty()))
+    }
+
+    /// Undo `claim_reminder` after a failed send so the next run retries it.
+    pub async fn release_reminder(ctx: &AppContext, id: i64) -> Result<()> {
+        let db = SupabaseClient::from_env(ctx)?;
+        db.patch_where(
+            "todos",
+            &format!("id=eq.{}", id),
+            serde_json::json!({ "reminded_at": null }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Users the reminder for `remind_at` (as read from the todo) was already delivered to.
+    pub async fn reminder_recipients(
+        ctx: &AppContext,
+        id: i64,
+        remind_at: &str,
+    ) -> Result<HashSet<String>> {
+        #[derive(Deserialize)]
+        struct Row {
+            user_id: String,
         }
+
+        let db = SupabaseClient::from_env(ctx)?;
+        let query = format!(
+            "select=user_id&todo_id=eq.{}&remind_at=eq.{}",
+            id,
+            encode_value(remind_at)
+        );
+        let rows: Vec<Row> =
+            serde_json::from_value(db.get("todo_reminder_deliveries", &query).await?)?;
+        Ok(rows.into_iter().map(|r| r.user_id).collect())
     }
 
-    /// List all todos with owner info (admin-only).
-    pub async fn list_all_with_owner(ctx: &AppContext) -> Result<Vec<AdminTodo>> {
+    /// Record that the reminder for `remind_at` was delivered to `user_id`.
+    pub async fn record_reminder_recipient(
+        ctx: &AppContext,
+        id: i64,
+        remind_at: &str,
+        user_id: &str,
+    ) -> Result<()> {
         let db = SupabaseClient::from_env(ctx)?;
-        let query = "select=id,title,completed,created_at&order=created_at.desc";
-        let json_value = db.get("todos", query).await?;
-        let todos: Vec<Todo> = match json_value {
-            serde_json::Value::Array(arr) => {
-                serde_json::from_value(serde_json::Value::Array(arr))?
-            }
-            _ => {
-                return Err(Error::RustError(format!(
-                    "Expected array, got: {}",
-                    json_value
-                )))
-            }
-        };
+        db.post(
+            "todo_reminder_deliveries",
+            serde_json::json!({
+                "todo_id": id,
+                "remind_at": remind_at,
+                "user_id": user_id,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
 
+    /// Users holding the owner relation on the todo directly (not inherited from a parent or list).
+    pub async fn owner_ids(ctx: &AppContext, id: i64) -> Result<Vec<String>> {
         let keto = KetoClient::from_env(ctx)?;
         let tuples = keto
-            .list_relation_tuples(ListParams {
+            .list_all_relation_tuples(ListParams {
                 namespace: KETO_NAMESPACE.to_string(),
-                object: None,
+                object: Some(id.to_string()),
                 relation: Some(KETO_RELATION_OWNER.to_string()),
                 subject_id: None,
                 subject_set: None,
-                page_size: Some(1000),
+                page_size: Some(KETO_PAGE_SIZE),
                 page_token: None,
             })
             .await?;
+        Ok(tuples
+            .iter()
+            .filter_map(|t| t.get("subject_id").and_then(|s| s.as_str()))
+            .map(|s| s.strip_prefix("user:").unwrap_or(s).to_string())
+            .collect())
+    }
 
-        let mut owners: HashMap<i64, String> = HashMap::new();
-        if let Some(arr) = tuples.get("relation_tuples").and_then(|v| v.as_array()) {
-            for t in arr {
-                let object = t.get("object").and_then(|o| o.as_str());
-                let subject_id = t.get("subject_id").and_then(|s| s.as_str());
-                if let (Some(obj), Some(sub)) = (object, subject_id) {
-                    if let Ok(id) = obj.parse::<i64>() {
-                        let owner_id = sub.strip_prefix("user:").unwrap_or(sub).to_string();
-                        owners.insert(id, owner_id);
-                    }
-                }
-            }
-        }
-
-        let admin_todos = todos
-            .into_iter()
-            .map(|t| AdminTodo {
-                id: t.id,
-                title: t.title,
-                completed: t.completed,
-                created_at: t.created_at,
-                owner_id: owners.get(&t.id).cloned(),
-                owner_email: None,
-            })
-            .collect::<Vec<_>>();
-
-        Ok(admin_todos)
+    /// Ids of the todos the user owns directly (not through a list), trashed or not.
+    pub async fn owned_ids(ctx: &AppContext, user_id: &str) -> Result<Vec<i64>> {
+        let keto = KetoClient::from_env(ctx)?;
+        Ok(
+            object_ids(&keto, KETO_NAMESPACE, user_id, KETO_RELATION_OWNER)
+                .await?
+                .into_iter()
+                .collect(),
+        )
     }
 
-    /// Create a todo and set the caller as owner in Keto.
-    pub async fn create(ctx: &AppContext, user_id: &str, title: String) -> Result<Todo> {
+    /// Permanently delete any todo, trashed or not, with its attachment objects (admin-only). Attempts to remove owner and share tuples in Keto if available.
+    /// `admin_id` is recorded as the actor.
+    pub async fn delete_any(ctx: &AppContext, admin_id: &str, id: i64) -> Result<()> {
+        AttachmentRepo::delete_for_todos(ctx, &[id]).await?;
         let db = SupabaseClient::from_env(ctx)?;
-        let body = serde_json::json!({ "title": title });
+        db.delete("todos", id).await?;
 
-        let json_value = db.post("todos", body).await?;
-        let todo: Todo = match json_value {
-            serde_json::Value::Array(arr) => {
-                let todos: Vec<Todo> = serde_json::from_value(serde_json::Value::Array(arr))?;
-                todos.into_iter().next().ok_or_else(|| Error::RustError("No todo returned".into()))?
-            }
-            _ => {
-                return Err(Error::RustError(format!(
-                    "Expected array, got: {}",
-                    json_value
-                )))
-            }
-        };
+        if let Ok(keto) = KetoClient::from_env(ctx) {
+            delete_object_tuples(&keto, id).await;
+        }
+        ActivityRepo::record(
+            ctx,
+            vec![NewActivity::new(
+                id,
+                Some(admin_id),
+                ActivityAction::AdminDeleted,
+            )],
+        )
+        .await;
 
+        Ok(())
+    }
+
+    /// Grant `relation` on a todo to another user (owner-only). Replaces any other share relation
+    /// the target already holds, so a user is either a viewer or an editor, never both.
+    pub async fn share(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        target_user_id: &str,
+        relation: ShareRelation,
+    ) -> Result<TodoShare> {
         let keto = KetoClient::from_env(ctx)?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+
+        if target_user_id == user_id {
+            return Err(Error::RustError(
+                "Bad Request: Cannot share a todo with its owner".into(),
+            ));
+        }
+
         keto.create_relation_tuple(
             KETO_NAMESPACE,
-            &todo.id.to_string(),
-            KETO_RELATION_OWNER,
-            &subject_id(user_id),
+            &id.to_string(),
+            relation.as_str(),
+            &subject_id(target_user_id),
         )
         .await?;
 
-        Ok(todo)
+        for other in [ShareRelation::Viewer, ShareRelation::Editor] {
+            if other != relation {
+                keto.delete_relation_tuple(
+                    KETO_NAMESPACE,
+                    &id.to_string(),
+                    other.as_str(),
+                    &subject_id(target_user_id),
+                )
+                .await?;
+            }
+        }
+
+        ActivityRepo::record(
+            ctx,
+            vec![
+                NewActivity::new(id, Some(user_id), ActivityAction::Shared).change(
+                    relation.as_str(),
+                    None::<String>,
+                    target_user_id,
+                ),
+            ],
+        )
+        .await;
+
+        Ok(TodoShare {
+            todo_id: id,
+            user_id: target_user_id.to_string(),
+            relation,
+        })
     }
 
-    /// Update a todo if the user is owner.
-    pub async fn update(
+    /// Revoke a share (owner-only). With `relation` unset, both viewer and editor are removed.
+    pub async fn unshare(
         ctx: &AppContext,
         user_id: &str,
         id: i64,
-        completed: bool,
-    ) -> Result<Todo> {
+        target_user_id: &str,
+        relation: Option<ShareRelation>,
+    ) -> Result<()> {
         let keto = KetoClient::from_env(ctx)?;
-        let allowed = keto
-            .check(crate::db::CheckParams {
-                namespace: KETO_NAMESPACE.to_string(),
-                object: id.to_string(),
-                relation: KETO_RELATION_OWNER.to_string(),
-                subject_id: Some(subject_id(user_id)),
-                subject_set: None,
-                max_depth: None,
-            })
-            .await?;
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
 
-        if !allowed {
-            return Err(Error::RustError("Forbidden".into()));
+        let relations = match relation {
+            Some(r) => vec![r],
+            None => vec![ShareRelation::Viewer, ShareRelation::Editor],
+        };
+        let mut activity = NewActivity::new(id, Some(user_id), ActivityAction::Unshared);
+        for r in relations {
+            keto.delete_relation_tuple(
+                KETO_NAMESPACE,
+                &id.to_string(),
+                r.as_str(),
+                &subject_id(target_user_id),
+            )
+            .await?;
+            activity = activity.change(r.as_str(), target_user_id, None::<String>);
         }
+        ActivityRepo::record(ctx, vec![activity]).await;
 
-        let db = SupabaseClient::from_env(ctx)?;
-        let body = serde_json::json!({ "completed": completed });
-        let json_value = db.patch("todos", id, body).await?;
-        match json_value {
-            serde_json::Value::Array(arr) => {
-                let todos: Vec<Todo> = serde_json::from_value(serde_json::Value::Array(arr))?;
-                todos.into_iter().next().ok_or_else(|| Error::RustError("Todo not found".into()))
-            }
-            _ => Err(Error::RustError(format!(
-                "Expected array, got: {}",
-                json_value
-            ))),
-        }
+        Ok(())
     }
 
-    /// Delete a todo if the user is owner. Also removes the ownership tuple in Keto.
-    pub async fn delete(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+    /// Assign a live todo to `target_user_id` (owner-only), replacing the current assignee. The
+    /// assignee can read the todo and toggle `completed`.
+    pub async fn assign(
+        ctx: &AppContext,
+        user_id: &str,
+        id: i64,
+        target_user_id: &str,
+    ) -> Result<TodoAssignee> {
         let keto = KetoClient::from_env(ctx)?;
-        let allowed = keto
-            .check(crate::db::CheckParams {
-                namespace: KETO_NAMESPACE.to_string(),
-                object: id.to_string(),
-                relation: KETO_RELATION_OWNER.to_string(),
-                subject_id: Some(subject_id(user_id)),
-                subject_set: None,
-                max_depth: None,
-            })
+        ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+        let db = SupabaseClient::from_env(ctx)?;
+        if fetch_todo(&db, id).await?.is_none() {
+            return Err(Error::RustError("Todo not found".into()));
+        }
+
+        let current = assignee_ids(&keto, id).await?;
+        if !current.iter().any(|a| a == target_user_id) {
+            keto.create_relation_tuple(
+                KETO_NAMESPACE,
+                &id.to_string(),
+                KETO_RELATION_ASSIGNEE,
+                &subject_id(target_user_id),
+            )
             .await?;
+        }
+        for other in current.iter().filter(|a| *a != target_user_id) {
+            keto.delete_relation_tuple(
+                KETO_NAMESPACE,
+                &id.to_string(),
+                KETO_RELATION_ASSIGNEE,
+                &subject_id(other),
+            )
+            .await?;
+        }
 
-        if !allowed {
-            return Err(Error::RustError("Forbidden".into()));
+        let previous = current.into_iter().next();
+        if previous.as_deref() != Some(target_user_id) {
+            ActivityRepo::record(
+                ctx,
+                vec![
+                    NewActivity::new(id, Some(user_id), ActivityAction::Assigned).change(
+                        "assignee",
+                        previous,
+                        target_user_id,
+                    ),
+                ],
+            )
+            .await;
         }
 
-        let db = SupabaseClient::from_env(ctx)?;
-        db.delete("todos", id).await?;
+        Ok(TodoAssignee {
+            todo_id: id,
+            user_id: target_user_id.to_string(),
+        })
+    }
+
+    /// Remove the todo's assignee. Allowed for the owner and for the assignee themselves.
+    pub async fn unassign(ctx: &AppContext, user_id: &str, id: i64) -> Result<()> {
+        let keto = KetoClient::from_env(ctx)?;
+        let current = assignee_ids(&keto, id).await?;
+        if !current.iter().any(|a| a == user_id) {
+            ensure_any_relation(&keto, KETO_NAMESPACE, user_id, id, &[KETO_RELATION_OWNER]).await?;
+        }
 
-        // Remove ownership tuple in Keto after successful Supabase delete. Log on failure; do not fail the request.
-        if let Err(e) = keto
-            .delete_relation_tuple(
+        for assignee in &current {
+            keto.delete_relation_tuple(
                 KETO_NAMESPACE,
                 &id.to_string(),
-                KETO_RELATION_OWNER,
-                &subject_id(user_id),
+                KETO_RELATION_ASSIGNEE,
+                &subject_id(assignee),
+            )
+            .await?;
+        }
+        if let Some(previous) = current.into_iter().next() {
+            ActivityRepo::record(
+                ctx,
+                vec![
+                    NewActivity::new(id, Some(user_id), ActivityAction::Unassigned).change(
+                        "assignee",
+                        previous,
+                        None::<String>,
+                    ),
+                ],
             )
-            .await
-        {
-            logging::log_error(&format!("keto delete relation tuple: {}", e));
+            .await;
         }
 
         Ok(())
     }
+}
 
-    /// Delete any todo (admin-only). Attempts to remove owner tuples in Keto if available.
-    pub async fn delete_any(ctx: &AppContext, id: i64) -> Result<()> {
-        let db = SupabaseClient::from_env(ctx)?;
-        db.delete("todos", id).await?;
-
-        if let Ok(keto) = KetoClient::from_env(ctx) {
-            let tuples = keto
-                .list_relation_tuples(ListParams {
-                    namespace: KETO_NAMESPACE.to_string(),
-                    object: Some(id.to_string()),
-                    relation: Some(KETO_RELATION_OWNER.to_string()),
-                    subject_id: None,
-                    subject_set: None,
-                    page_size: Some(100),
-                    page_token: None,
-                })
-                .await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{PageAfter, TodoOrder};
 
-            match tuples {
-                Ok(json) => {
-                    if let Some(arr) = json.get("relation_tuples").and_then(|v| v.as_array()) {
-                        for t in arr {
-                            if let Some(sub) = t.get("subject_id").and_then(|s| s.as_str()) {
-                                if let Err(e) = keto
-                                    .delete_relation_tuple(
-                                        KETO_NAMESPACE,
-                                        &id.to_string(),
-                                        KETO_RELATION_OWNER,
-                                        sub,
-                                    )
-                                    .await
-                                {
-                                    logging::log_error(&format!(
-                                        "keto delete relation tuple (admin): {}",
-                                        e
-                                    ));
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => logging::log_error(&format!("keto list relation tuples: {}", e)),
-            }
+    fn query(order: TodoOrder, ascending: bool, after: Option<PageAfter>) -> ListTodosQuery {
+        ListTodosQuery {
+            limit: 50,
+            after,
+            completed: None,
+            search: None,
+            order,
+            ascending,
+            tree: false,
+            tag: None,
+            list_id: None,
+            assigned_to_me: false,
         }
+    }
 
-        Ok(())
+    #[test]
+    fn keyset_breaks_ties_on_id() {
+        let after = PageAfter {
+            value: Some("Pay rent".into()),
+            id: 42,
+        };
+        assert_eq!(
+            keyset_filter(&query(TodoOrder::Title, true, Some(after.clone()))),
+            "&or=(title.gt.%22Pay%20rent%22,and(title.eq.%22Pay%20rent%22,id.gt.42),title.is.null)"
+        );
+        assert_eq!(
+            keyset_filter(&query(TodoOrder::Title, false, Some(after))),
+            "&or=(title.lt.%22Pay%20rent%22,and(title.eq.%22Pay%20rent%22,id.lt.42),title.is.null)"
+        );
+    }
+
+    #[test]
+    fn keyset_after_a_row_without_a_value_pages_by_id() {
+        let after = PageAfter { value: None, id: 7 };
+        assert_eq!(
+            keyset_filter(&query(TodoOrder::DueDate, true, Some(after))),
+            "&due_date=is.null&id=gt.7"
+        );
+        assert_eq!(keyset_filter(&query(TodoOrder::DueDate, true, None)), "");
+    }
+
+    #[test]
+    fn keyset_value_cannot_break_out_of_the_filter() {
+        let after = PageAfter {
+            value: Some("a,b).or=(id.gt.0\"\\".into()),
+            id: 1,
+        };
+        let filter = keyset_filter(&query(TodoOrder::Title, true, Some(after)));
+        let value = "%22a%2Cb%29.or%3D%28id.gt.0%5C%22%5C%5C%22";
+        assert_eq!(
+            filter,
+            format!(
+                "&or=(title.gt.{v},and(title.eq.{v},id.gt.1),title.is.null)",
+                v = value
+            )
+        );
+    }
+
+    #[test]
+    fn list_order_ends_with_id() {
+        let params = list_filter_params(&query(TodoOrder::Title, true, None));
+        assert!(
+            params.ends_with("&order=title.asc.nullslast,id.asc"),
+            "{}",
+            params
+        );
+        let params = list_filter_params(&query(TodoOrder::CreatedAt, false, None));
+        assert!(
+            params.ends_with("&order=created_at.desc.nullslast,id.desc"),
+            "{}",
+            params
+        );
     }
 }
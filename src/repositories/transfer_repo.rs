@@ -0,0 +1,291 @@
+use crate::db::supabase::{encode_list_value, encode_value};
+use crate::db::SupabaseClient;
+use crate::middleware::logging;
+use crate::models::{
+    CreateTodo, ImportReport, ImportRowResult, ImportStatus, TodoRecord, TransferFormat,
+    UpdateTodo, UpdateTodoOptions,
+};
+use crate::repositories::todo_repo::{parse_todos, TODO_COLUMNS};
+use crate::repositories::TodoRepo;
+use crate::utils::context::AppContext;
+use futures::future::join_all;
+use futures::stream::{self, LocalBoxStream, StreamExt};
+use std::collections::HashMap;
+use worker::*;
+
+/// Todos fetched from Supabase per chunk of the export body.
+const EXPORT_PAGE_SIZE: usize = 200;
+
+/// Rows created at once during an import.
+const IMPORT_CONCURRENCY: usize = 10;
+
+/// Import keys looked up per `todo_imports` query (64 hex characters each).
+const KEY_LOOKUP_CHUNK: usize = 50;
+
+/// Export body in progress: the pages of ids still to fetch.
+struct ExportState {
+    db: SupabaseClient,
+    format: TransferFormat,
+    pages: std::vec::IntoIter<Vec<i64>>,
+    written: usize,
+}
+
+impl ExportState {
+    /// Fetch the next page and encode it, or `None` when every page has been written.
+    async fn next_chunk(&mut self) -> Option<Result<Vec<u8>>> {
+        let page = self.pages.next()?;
+        let query = format!(
+            "select={}&id=in.({})&deleted_at=is.null&order=id.asc",
+            TODO_COLUMNS,
+            page.iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let records = match self.db.get("todos", &query).await.and_then(parse_todos) {
+            Ok(todos) => todos.iter().map(TodoRecord::from_todo).collect::<Vec<_>>(),
+            Err(e) => return Some(Err(e)),
+        };
+        let chunk = self
+            .format
+            .encode(&records, self.written == 0)
+            .map_err(Error::RustError);
+        self.written += records.len();
+        Some(chunk)
+    }
+}
+
+/// Import keys (see `TodoRecord::import_key`) the user has imported before, with the todo each
+/// one created. Todos purged since have lost their key (`on delete cascade`).
+async fn imported_todos<'a>(
+    db: &SupabaseClient,
+    user_id: &str,
+    keys: impl Iterator<Item = &'a String>,
+) -> Result<HashMap<String, i64>> {
+    let keys = keys.collect::<Vec<_>>();
+    let mut imported = HashMap::new();
+    for chunk in keys.chunks(KEY_LOOKUP_CHUNK) {
+        let query = format!(
+            "select=import_key,todo_id&user_id=eq.{}&import_key=in.({})",
+            encode_value(user_id),
+            chunk
+                .iter()
+                .map(|k| encode_list_value(k))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let rows = db.get("todo_imports", &query).await?;
+        for row in rows.as_array().into_iter().flatten() {
+            let key = row.get("import_key").and_then(|k| k.as_str());
+            let todo_id = row.get("todo_id").and_then(|t| t.as_i64());
+            if let (Some(key), Some(todo_id)) = (key, todo_id) {
+                imported.insert(key.to_string(), todo_id);
+            }
+        }
+    }
+    Ok(imported)
+}
+
+/// Create the todo for one row at `position` and remember its key. The key is written after the
+/// todo, so a row whose key could not be saved is created again by the next upload; that is logged.
+async fn create_row(
+    ctx: &AppContext,
+    db: &SupabaseClient,
+    user_id: &str,
+    record: &TodoRecord,
+    key: &str,
+    position: &str,
+) -> Result<i64> {
+    let input = CreateTodo {
+        position: Some(position.to_string()),
+        ..record.to_create()
+    };
+    let todo = TodoRepo::create(ctx, user_id, input).await?;
+    let row = serde_json::json!({
+        "user_id": user_id,
+        "import_key": key,
+        "todo_id": todo.id,
+    });
+    if let Err(e) = db.post("todo_imports", row).await {
+        logging::log_error(&format!("import key for todo {}: {}", todo.id, e));
+    }
+    Ok(todo.id)
+}
+
+/// Error message for a row, hiding internal errors.
+fn row_error(e: Error) -> String {
+    let msg = e.to_string();
+    match msg.strip_prefix("Bad Request: ") {
+        Some(reason) => reason.to_string(),
+        None => {
+            logging::log_error(&format!("import row: {}", msg));
+            "Could not import this todo".into()
+        }
+    }
+}
+
+/// Backup and transfer of a user's todos between accounts.
+pub struct TransferRepo;
+
+impl TransferRepo {
+    /// Every live todo the user owns, in id order, as a `format` file. Todos are fetched a page at
+    /// a time while the body is streamed, so the export size is not bounded by memory.
+    pub async fn export(
+        ctx: &AppContext,
+        user_id: &str,
+        format: TransferFormat,
+    ) -> Result<LocalBoxStream<'static, Result<Vec<u8>>>> {
+        let mut ids = TodoRepo::owned_ids(ctx, user_id).await?;
+        ids.sort_unstable();
+        let state = ExportState {
+            db: SupabaseClient::from_env(ctx)?,
+            format,
+            pages: ids
+                .chunks(EXPORT_PAGE_SIZE)
+                .map(<[i64]>::to_vec)
+                .collect::<Vec<_>>()
+                .into_iter(),
+            written: 0,
+        };
+
+        let pages = stream::unfold(state, |mut state| async move {
+            let chunk = state.next_chunk().await?;
+            Some((chunk, state))
+        });
+        Ok(stream::once(async move { Ok(format.header()) })
+            .chain(pages)
+            .chain(stream::once(async move { Ok(format.footer()) }))
+            .boxed_local())
+    }
+
+    /// Create a todo for each valid row through `TodoRepo::create`, completed if the row is, then
+    /// link it to its parent (a row of this or an earlier import with that `id`). Rows imported before,
+    /// or repeated in the file, are skipped, so uploading the same file twice creates nothing the
+    /// second time.
+    pub async fn import(
+        ctx: &AppContext,
+        user_id: &str,
+        rows: Vec<std::result::Result<TodoRecord, String>>,
+    ) -> Result<ImportReport> {
+        let db = SupabaseClient::from_env(ctx)?;
+        let records = rows
+            .into_iter()
+            .map(|r| r.and_then(|record| record.validate().map(|_| record)))
+            .collect::<Vec<_>>();
+        let keys = records
+            .iter()
+            .map(|r| r.as_ref().ok().map(TodoRecord::import_key))
+            .collect::<Vec<_>>();
+        let imported = imported_todos(&db, user_id, keys.iter().flatten()).await?;
+
+        let mut results = Vec::with_capacity(records.len());
+        let mut first_with_key: HashMap<&str, usize> = HashMap::new();
+        let mut duplicates = Vec::new();
+        let mut to_create = Vec::new();
+        for (i, (record, key)) in records.iter().zip(&keys).enumerate() {
+            let mut result = ImportRowResult {
+                row: i + 1,
+                status: ImportStatus::Failed,
+                todo_id: None,
+                error: None,
+            };
+            match (record, key) {
+                (Err(e), _) => result.error = Some(e.clone()),
+                (Ok(record), Some(key)) => {
+                    if let Some(todo_id) = imported.get(key) {
+                        result.status = ImportStatus::Skipped;
+                        result.todo_id = Some(*todo_id);
+                    } else if let Some(first) = first_with_key.get(key.as_str()) {
+                        duplicates.push((i, *first));
+                    } else {
+                        first_with_key.insert(key, i);
+                        to_create.push((i, record, key.as_str()));
+                    }
+                }
+                (Ok(_), None) => {}
+            }
+            results.push(result);
+        }
+
+        // Imported todos go first in the manual order, with ascending keys in file order.
+        let positions = if to_create.is_empty() {
+            vec![]
+        } else {
+            TodoRepo::first_positions(ctx, user_id, to_create.len()).await?
+        };
+        for (chunk, positions) in to_create
+            .chunks(IMPORT_CONCURRENCY)
+            .zip(positions.chunks(IMPORT_CONCURRENCY))
+        {
+            let created = join_all(chunk.iter().zip(positions).map(
+                |(&(_, record, key), position)| {
+                    create_row(ctx, &db, user_id, record, key, position)
+                },
+            ))
+            .await;
+            for (&(i, _, _), outcome) in chunk.iter().zip(created) {
+                match outcome {
+                    Ok(todo_id) => {
+                        results[i].status = ImportStatus::Created;
+                        results[i].todo_id = Some(todo_id);
+                    }
+                    Err(e) => results[i].error = Some(row_error(e)),
+                }
+            }
+        }
+        for (i, first) in duplicates {
+            results[i].todo_id = results[first].todo_id;
+            if results[first].todo_id.is_some() {
+                results[i].status = ImportStatus::Skipped;
+            }
+            results[i].error = Some(format!("Same as row {}", first + 1));
+        }
+
+        let todo_ids = imported_sources(&records, &results);
+        // One at a time: linking parents concurrently could slip a cycle past the checks.
+        for (record, result) in records.iter().zip(results.iter_mut()) {
+            let (Ok(record), Some(todo_id)) = (record, result.todo_id) else {
+                continue;
+            };
+            if result.status != ImportStatus::Created {
+                continue;
+            }
+            let Some(parent) = record.parent_id else {
+                continue;
+            };
+            let Some(parent_id) = todo_ids.get(&parent) else {
+                result.error = Some(format!(
+                    "Parent {} is not in the import; created as a top-level todo",
+                    parent
+                ));
+                continue;
+            };
+            let patch = UpdateTodo {
+                parent_id: Some(Some(*parent_id)),
+                ..UpdateTodo::default()
+            };
+            if let Err(e) =
+                TodoRepo::update(ctx, user_id, todo_id, patch, UpdateTodoOptions::default()).await
+            {
+                result.error = Some(row_error(e));
+            }
+        }
+
+        Ok(ImportReport::new(results))
+    }
+}
+
+/// Exported `id` of each row that has a todo in this account, mapped to that todo.
+fn imported_sources(
+    records: &[std::result::Result<TodoRecord, String>],
+    results: &[ImportRowResult],
+) -> HashMap<i64, i64> {
+    records
+        .iter()
+        .zip(results)
+        .filter_map(|(record, result)| {
+            let source = record.as_ref().ok()?.id?;
+            Some((source, result.todo_id?))
+        })
+        .collect()
+}
@@ -13,7 +13,7 @@ impl AppContext {
         Self {
             env,
             request_id: Uuid::new_v4().to_string(),
-            start_time: Date::now().as_millis() as u64,
+            start_time: Date::now().as_millis(),
         }
     }
 }
@@ -1,4 +1,4 @@
-use crate::middleware::cors;
+use crate::middleware::{cors, logging};
 use worker::*;
 
 pub fn json_error(msg: &str, status: u16) -> Result<Response> {
@@ -15,3 +15,26 @@ pub fn json_server_error(msg: &str) -> Result<Response> {
 pub fn forbidden() -> Result<Response> {
     json_error("Forbidden", 403)
 }
+
+/// Maps a repository error to a response. `Forbidden` becomes 403, `<Thing> not found` 404,
+/// `Bad Request: <reason>` 400, `Conflict: <reason>` 409, `Precondition Failed: <reason>` 412 and
+/// `Payload Too Large: <reason>` 413 with the reason as message; anything else is logged as a 500.
+pub fn from_repo_error(scope: &str, e: Error) -> Result<Response> {
+    let msg = e.to_string();
+    if msg == "Forbidden" {
+        forbidden()
+    } else if let Some(reason) = msg.strip_prefix("Bad Request: ") {
+        json_error(reason, 400)
+    } else if let Some(reason) = msg.strip_prefix("Conflict: ") {
+        json_error(reason, 409)
+    } else if let Some(reason) = msg.strip_prefix("Precondition Failed: ") {
+        json_error(reason, 412)
+    } else if let Some(reason) = msg.strip_prefix("Payload Too Large: ") {
+        json_error(reason, 413)
+    } else if msg.ends_with(" not found") {
+        json_error(&msg, 404)
+    } else {
+        logging::log_error(&format!("{}: {}", scope, e));
+        json_server_error("Internal server error")
+    }
+}
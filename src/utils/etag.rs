@@ -0,0 +1,24 @@
+//! Entity tags for optimistic concurrency. A todo's `ETag` is its `version` column, which a trigger
+//! bumps whenever an editable field changes; clients send it back as `If-Match` to make a write
+//! conditional.
+
+/// Strong `ETag` value for a version, e.g. `"3"`.
+pub fn format(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Parses an `If-Match` header. `None` (no header or `*`) means the write is unconditional.
+/// Only a single strong tag produced by `format` can ever match; anything else is an error with a
+/// message suitable for a 412 response.
+pub fn parse_if_match(header: Option<&str>) -> Result<Option<i64>, String> {
+    let value = match header.map(str::trim) {
+        None | Some("*") => return Ok(None),
+        Some(v) => v,
+    };
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .and_then(|v| v.parse().ok())
+        .map(Some)
+        .ok_or_else(|| "If-Match does not match the current version".to_string())
+}
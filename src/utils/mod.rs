@@ -1,2 +1,6 @@
 pub mod context;
 pub mod errors;
+pub mod etag;
+pub mod position;
+pub mod serde_helpers;
+pub mod time;
@@ -0,0 +1,207 @@
+//! Fractional indexing for manual ordering. A position is a string of base-62 digits compared
+//! byte-wise (the column uses the `C` collation), read as a fraction `0.<digits>`. A key can always
+//! be generated strictly between two others, so moving a todo rewrites only that todo's row.
+//! Keys never end in the smallest digit `0`, otherwise `a1` and `a10` would be equal fractions with
+//! no key between them.
+
+/// Digits in ascending byte order.
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+fn digit(c: u8) -> Option<usize> {
+    DIGITS.iter().position(|d| *d == c)
+}
+
+/// True if `key` can be used as a position: non-empty base-62 digits not ending in `0`.
+pub fn is_valid(key: &str) -> bool {
+    !key.is_empty() && !key.ends_with('0') && key.bytes().all(|c| digit(c).is_some())
+}
+
+/// A key strictly between `before` and `after`; `None` bounds are open ends. Returns `None` if the
+/// bounds are not valid keys in ascending order.
+pub fn key_between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    let a = before.unwrap_or("");
+    if !a.is_empty() && !is_valid(a) {
+        return None;
+    }
+    if let Some(b) = after {
+        if !is_valid(b) || a >= b {
+            return None;
+        }
+    }
+    Some(midpoint(a.as_bytes(), after.map(str::as_bytes)))
+}
+
+/// `count` ascending keys strictly between `before` and `after`, split evenly so their length grows
+/// with the logarithm of `count`. Returns `None` if the bounds are not valid keys in ascending order.
+pub fn keys_between(
+    before: Option<&str>,
+    after: Option<&str>,
+    count: usize,
+) -> Option<Vec<String>> {
+    key_between(before, after)?;
+    let mut keys = Vec::with_capacity(count);
+    fill_between(before, after, count, &mut keys);
+    Some(keys)
+}
+
+fn fill_between(before: Option<&str>, after: Option<&str>, count: usize, keys: &mut Vec<String>) {
+    if count == 0 {
+        return;
+    }
+    let Some(middle) = key_between(before, after) else {
+        return;
+    };
+    let left = count / 2;
+    fill_between(before, Some(&middle), left, keys);
+    keys.push(middle.clone());
+    fill_between(Some(&middle), after, count - left - 1, keys);
+}
+
+/// Midpoint of `a < b` (`b = None` is 1). `a` may be empty (0).
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> String {
+    if let Some(b) = b {
+        // Shared prefix, reading missing digits of `a` as `0`.
+        let n = b
+            .iter()
+            .enumerate()
+            .take_while(|(i, c)| a.get(*i).copied().unwrap_or(b'0') == **c)
+            .count();
+        if n > 0 {
+            let rest = midpoint(a.get(n..).unwrap_or(&[]), Some(&b[n..]));
+            return format!("{}{}", String::from_utf8_lossy(&b[..n]), rest);
+        }
+    }
+
+    let digit_a = a.first().and_then(|c| digit(*c)).unwrap_or(0);
+    let digit_b = b
+        .and_then(|b| b.first())
+        .and_then(|c| digit(*c))
+        .unwrap_or(DIGITS.len());
+    if digit_b - digit_a > 1 {
+        return (DIGITS[(digit_a + digit_b).div_ceil(2)] as char).to_string();
+    }
+    // Consecutive first digits: keep `b`'s first digit if it has more, else extend `a`.
+    match b {
+        Some(b) if b.len() > 1 => (b[0] as char).to_string(),
+        _ => format!(
+            "{}{}",
+            DIGITS[digit_a] as char,
+            midpoint(a.get(1..).unwrap_or(&[]), None)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts `key` is a valid key strictly between the bounds.
+    fn assert_between(before: Option<&str>, key: &str, after: Option<&str>) {
+        assert!(is_valid(key), "{:?} is not a valid key", key);
+        if let Some(before) = before {
+            assert!(before < key, "{:?} is not after {:?}", key, before);
+        }
+        if let Some(after) = after {
+            assert!(key < after, "{:?} is not before {:?}", key, after);
+        }
+    }
+
+    #[test]
+    fn validates_keys() {
+        for key in ["V", "a", "a1", "zz", "0V", "Az9"] {
+            assert!(is_valid(key), "{:?}", key);
+        }
+        for key in ["", "a0", "0", "a-", "a b", "é"] {
+            assert!(!is_valid(key), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn key_between_known_values() {
+        let cases: &[(Option<&str>, Option<&str>, &str)] = &[
+            (None, None, "V"),
+            (None, Some("V"), "G"),
+            (Some("V"), None, "l"),
+            (Some("a"), Some("c"), "b"),
+            (Some("a"), Some("b"), "aV"),
+            (Some("a1"), Some("a2"), "a1V"),
+            (Some("a"), Some("a1"), "a0V"),
+            (Some("az"), Some("b"), "azV"),
+            (Some("z"), None, "zV"),
+            (None, Some("1"), "0V"),
+            (Some("a"), Some("b1"), "b"),
+        ];
+        for (before, after, expected) in cases {
+            let key = key_between(*before, *after).unwrap();
+            assert_eq!(key, *expected, "between {:?} and {:?}", before, after);
+            assert_between(*before, &key, *after);
+        }
+    }
+
+    #[test]
+    fn key_between_rejects_bad_bounds() {
+        let cases: &[(Option<&str>, Option<&str>)] = &[
+            (Some("b"), Some("a")),
+            (Some("a"), Some("a")),
+            (Some("a0"), None),
+            (None, Some("a0")),
+            (Some("a-"), Some("b")),
+            (None, Some("")),
+        ];
+        for (before, after) in cases {
+            assert_eq!(
+                key_between(*before, *after),
+                None,
+                "{:?} {:?}",
+                before,
+                after
+            );
+        }
+    }
+
+    #[test]
+    fn repeated_inserts_stay_ordered() {
+        // Always at the front, always at the back, and always into the same gap.
+        let mut first = key_between(None, None).unwrap();
+        let mut last = first.clone();
+        let (lower, mut upper) = (first.clone(), key_between(Some(&first), None).unwrap());
+        for _ in 0..500 {
+            let front = key_between(None, Some(&first)).unwrap();
+            assert_between(None, &front, Some(&first));
+            first = front;
+
+            let back = key_between(Some(&last), None).unwrap();
+            assert_between(Some(&last), &back, None);
+            last = back;
+
+            let middle = key_between(Some(&lower), Some(&upper)).unwrap();
+            assert_between(Some(&lower), &middle, Some(&upper));
+            upper = middle;
+        }
+    }
+
+    #[test]
+    fn keys_grow_slowly() {
+        // About one digit per six halvings of the same gap.
+        let (lower, mut upper) = ("a".to_string(), "b".to_string());
+        for _ in 0..60 {
+            upper = key_between(Some(&lower), Some(&upper)).unwrap();
+        }
+        assert!(upper.len() <= 12, "{:?}", upper);
+    }
+
+    #[test]
+    fn keys_between_are_ascending_and_short() {
+        for (before, after) in [(None, None), (None, Some("V")), (Some("a"), Some("b"))] {
+            let keys = keys_between(before, after, 1000).unwrap();
+            assert_eq!(keys.len(), 1000);
+            assert_between(before, &keys[0], Some(&keys[1]));
+            for pair in keys.windows(2) {
+                assert_between(Some(&pair[0]), &pair[1], after);
+            }
+            assert!(keys.iter().all(|k| k.len() <= 4), "{:?}", keys.last());
+        }
+        assert_eq!(keys_between(None, Some("V"), 0), Some(vec![]));
+        assert_eq!(keys_between(Some("b"), Some("a"), 3), None);
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes a PATCH field so that an absent key and an explicit `null` can be told apart.
+/// Use with `#[serde(default, deserialize_with = "nullable")]` on an `Option<Option<T>>`:
+/// absent is `None`, `null` is `Some(None)` and a value is `Some(Some(v))`.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,32 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use worker::Date;
+
+/// Current time from the Workers clock.
+pub fn now() -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(Date::now().as_millis() as i64).unwrap_or_default()
+}
+
+/// Formats a timestamp the way it is written to Supabase (`2026-01-16T09:30:00.000Z`).
+pub fn to_rfc3339(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn now_rfc3339() -> String {
+    to_rfc3339(now())
+}
+
+/// Timestamp `days` days before now, e.g. a retention cutoff.
+pub fn days_ago_rfc3339(days: i64) -> String {
+    to_rfc3339(now() - Duration::days(days))
+}
+
+/// `date` at the wall-clock `time` in `tz`. A time skipped by a DST change is moved forward by the
+/// length of the gap (an hour, in practice); an ambiguous one takes the earlier.
+pub fn local_datetime(tz: Tz, date: NaiveDate, time: NaiveTime) -> Option<DateTime<Tz>> {
+    let local = date.and_time(time);
+    tz.from_local_datetime(&local).earliest().or_else(|| {
+        tz.from_local_datetime(&(local + Duration::hours(1)))
+            .earliest()
+    })
+}
@@ -0,0 +1,6 @@
+-- Rich todo fields: description, due date and priority.
+alter table public.todos
+  add column if not exists description text,
+  add column if not exists due_date timestamptz,
+  add column if not exists priority text
+    check (priority in ('low', 'medium', 'high'));
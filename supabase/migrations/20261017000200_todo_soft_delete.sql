@@ -0,0 +1,7 @@
+-- Soft delete: trashed todos keep their row until the daily purge job removes them.
+alter table public.todos
+  add column if not exists deleted_at timestamptz;
+
+create index if not exists todos_deleted_at_idx
+  on public.todos (deleted_at)
+  where deleted_at is not null;
@@ -0,0 +1,9 @@
+-- Subtasks: a todo may point at a parent todo. Purging a parent turns its children into
+-- top-level todos rather than deleting them.
+alter table public.todos
+  add column if not exists parent_id bigint
+    references public.todos (id) on delete set null;
+
+create index if not exists todos_parent_id_idx
+  on public.todos (parent_id)
+  where parent_id is not null;
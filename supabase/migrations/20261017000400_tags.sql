@@ -0,0 +1,15 @@
+-- Per-user tags. Ownership lives in Keto (`tags:<id>#owner@user:<id>`), not in these tables.
+create table if not exists public.tags (
+  id bigint generated by default as identity primary key,
+  name text not null check (char_length(name) between 1 and 50),
+  color text check (color ~ '^#[0-9a-fA-F]{6}$'),
+  created_at timestamptz not null default now()
+);
+
+create table if not exists public.todo_tags (
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  tag_id bigint not null references public.tags (id) on delete cascade,
+  primary key (todo_id, tag_id)
+);
+
+create index if not exists todo_tags_tag_id_idx on public.todo_tags (tag_id);
@@ -0,0 +1,14 @@
+-- Named lists (projects). Access lives in Keto (`lists:<id>#owner|member@user:<id>`).
+create table if not exists public.lists (
+  id bigint generated by default as identity primary key,
+  name text not null check (char_length(name) between 1 and 100),
+  created_at timestamptz not null default now()
+);
+
+alter table public.todos
+  add column if not exists list_id bigint
+    references public.lists (id) on delete set null;
+
+create index if not exists todos_list_id_idx
+  on public.todos (list_id)
+  where list_id is not null;
@@ -0,0 +1,8 @@
+-- Due-date reminders: remind_at is set by the user, reminded_at by the reminder job once sent.
+alter table public.todos
+  add column if not exists remind_at timestamptz,
+  add column if not exists reminded_at timestamptz;
+
+create index if not exists todos_pending_reminder_idx
+  on public.todos (remind_at)
+  where reminded_at is null and completed = false and deleted_at is null;
@@ -0,0 +1,9 @@
+-- Recurring todos: the open occurrence carries the RRULE; every generated occurrence points at the
+-- first todo of its series.
+alter table public.todos
+  add column if not exists rrule text,
+  add column if not exists series_id bigint;
+
+create index if not exists todos_series_id_idx
+  on public.todos (series_id)
+  where series_id is not null;
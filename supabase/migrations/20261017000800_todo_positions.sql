@@ -0,0 +1,36 @@
+-- Manual ordering: position is a fractional-index key (base-62 digits) compared byte-wise, so it
+-- must use the C collation. Existing todos keep their newest-first order.
+alter table public.todos
+  add column if not exists position text collate "C",
+  add column if not exists position_len integer generated always as (length(position)) stored;
+
+-- Evenly spaced keys in the current order. Called by the daily job once keys grow too long;
+-- keep the key format in sync with src/utils/position.rs (base-62 digits, never ending in 0).
+create or replace function public.rebalance_todo_positions()
+returns integer
+language sql
+as $$
+  with ordered as (
+    select id, row_number() over (order by position, id) as n
+    from public.todos
+  ), updated as (
+    update public.todos t
+       set position = 'a' || lpad(o.n::text, 10, '0') || 'V'
+      from ordered o
+     where t.id = o.id
+    returning 1
+  )
+  select count(*)::integer from updated;
+$$;
+
+with ordered as (
+  select id, row_number() over (order by created_at desc, id desc) as n
+  from public.todos
+  where position is null
+)
+update public.todos t
+   set position = 'a' || lpad(o.n::text, 10, '0') || 'V'
+  from ordered o
+ where t.id = o.id;
+
+create index if not exists todos_position_idx on public.todos (position);
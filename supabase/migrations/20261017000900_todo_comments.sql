@@ -0,0 +1,11 @@
+-- Discussion threads on todos. Access follows the todo in Keto; author_id is the Kratos identity id.
+create table if not exists public.todo_comments (
+  id bigint generated by default as identity primary key,
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  author_id text not null,
+  body text not null check (char_length(body) between 1 and 5000),
+  created_at timestamptz not null default now(),
+  updated_at timestamptz
+);
+
+create index if not exists todo_comments_todo_id_idx on public.todo_comments (todo_id, created_at);
@@ -0,0 +1,16 @@
+-- Attachment metadata. File content lives in the R2 bucket bound as ATTACHMENTS under object_key;
+-- the worker deletes objects before their todo is purged (rows follow via the cascade).
+create table if not exists public.todo_attachments (
+  id bigint generated by default as identity primary key,
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  file_name text not null check (char_length(file_name) between 1 and 255),
+  content_type text not null,
+  size bigint not null check (size >= 0),
+  checksum text not null,
+  uploaded_by text not null,
+  object_key text not null unique,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists todo_attachments_todo_id_idx on public.todo_attachments (todo_id);
+create index if not exists todo_attachments_uploaded_by_idx on public.todo_attachments (uploaded_by);
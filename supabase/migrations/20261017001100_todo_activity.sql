@@ -0,0 +1,12 @@
+-- Append-only history of todo changes. No foreign key to todos: entries are kept after a todo is
+-- purged or deleted by an admin. actor_id is the Kratos identity id (null for scheduled jobs).
+create table if not exists public.todo_activity (
+  id bigint generated by default as identity primary key,
+  todo_id bigint not null,
+  actor_id text,
+  action text not null,
+  changes jsonb not null default '{}'::jsonb,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists todo_activity_todo_id_idx on public.todo_activity (todo_id, created_at desc);
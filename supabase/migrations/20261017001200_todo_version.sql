@@ -0,0 +1,18 @@
+-- Optimistic concurrency: every update bumps version, which the API serves as the ETag and checks
+-- against If-Match (PATCH ... &version=eq.<n>).
+alter table public.todos add column if not exists version bigint not null default 1;
+
+create or replace function public.bump_todo_version()
+returns trigger
+language plpgsql
+as $$
+begin
+  new.version := old.version + 1;
+  return new;
+end;
+$$;
+
+drop trigger if exists todos_bump_version on public.todos;
+create trigger todos_bump_version
+  before update on public.todos
+  for each row execute function public.bump_todo_version();
@@ -0,0 +1,11 @@
+-- Rows imported through POST /api/todos/import, keyed by the SHA-256 of the row so importing the
+-- same file again is a no-op. The key goes with the todo when it is purged.
+create table if not exists public.todo_imports (
+  user_id text not null,
+  import_key text not null,
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  created_at timestamptz not null default now(),
+  primary key (user_id, import_key)
+);
+
+create index if not exists todo_imports_todo_id_idx on public.todo_imports (todo_id);
@@ -0,0 +1,7 @@
+-- Secret iCalendar feed per user. Only the SHA-256 (hex) of the token is stored; rotating the feed
+-- replaces the row.
+create table if not exists public.calendar_feeds (
+  user_id text primary key,
+  token_hash text not null unique,
+  created_at timestamptz not null default now()
+);
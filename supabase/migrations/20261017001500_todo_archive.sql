@@ -0,0 +1,63 @@
+-- Auto-archive of completed todos.
+--   completed_at:  when the todo was completed (maintained by the trigger below).
+--   archived_at:   set by the daily archive job; archived todos are left out of GET /api/todos.
+--   archive_after: when the job will archive the todo, resolved from the owner's setting the
+--                  first time the job sees it; 'infinity' if it must never be archived (the owner
+--                  turned archiving off, or the todo was unarchived). Reset whenever `completed`
+--                  changes or the owner changes their setting.
+alter table public.todos
+  add column if not exists completed_at timestamptz,
+  add column if not exists archived_at timestamptz,
+  add column if not exists archive_after timestamptz;
+
+-- Todos completed before this migration count as completed now.
+update public.todos set completed_at = now() where completed and completed_at is null;
+
+create or replace function public.set_todo_completed_at()
+returns trigger
+language plpgsql
+as $$
+begin
+  if tg_op = 'INSERT' then
+    if new.completed then
+      new.completed_at := coalesce(new.completed_at, now());
+    end if;
+  elsif new.completed is distinct from old.completed then
+    new.completed_at := case when new.completed then now() end;
+    new.archive_after := null;
+    if not new.completed then
+      new.archived_at := null;
+    end if;
+  end if;
+  return new;
+end;
+$$;
+
+drop trigger if exists todos_set_completed_at on public.todos;
+create trigger todos_set_completed_at
+  before insert or update on public.todos
+  for each row execute function public.set_todo_completed_at();
+
+-- Completed todos the archive job has not scheduled yet, and scheduled ones that are not archived.
+create index if not exists todos_archive_pending_idx on public.todos (completed_at)
+  where completed and archived_at is null and deleted_at is null;
+
+-- Schedule archiving for completed todos: `days` after completion, or never if `days` is null.
+create or replace function public.schedule_todo_archive(todo_ids bigint[], days integer)
+returns void
+language sql
+as $$
+  update public.todos
+     set archive_after = case
+           when days is null then 'infinity'::timestamptz
+           else coalesce(completed_at, now()) + make_interval(days => days)
+         end
+   where id = any(todo_ids) and completed and archived_at is null;
+$$;
+
+-- Per-user preferences. No row means the defaults; archive_after_days null means never archive.
+create table if not exists public.user_settings (
+  user_id text primary key,
+  archive_after_days integer check (archive_after_days between 1 and 3650),
+  updated_at timestamptz not null default now()
+);
@@ -0,0 +1,52 @@
+-- Productivity statistics for GET /api/todos/stats, aggregated here instead of in the worker.
+-- todo_ids are the todos the caller can read; days are UTC dates of completed_at.
+create or replace function public.todo_stats(
+  todo_ids bigint[],
+  from_date date,
+  to_date date,
+  today date
+)
+returns json
+language sql
+stable
+as $$
+  with scoped as (
+    select completed, created_at, completed_at
+      from public.todos
+     where id = any(todo_ids) and deleted_at is null
+  ),
+  completion_days as (
+    select (completed_at at time zone 'UTC')::date as day, count(*) as n
+      from scoped
+     where completed and completed_at is not null
+     group by 1
+  ),
+  -- Consecutive days share day + rank when ranked newest first.
+  runs as (
+    select day, day + (row_number() over (order by day desc))::integer as run
+      from completion_days
+     where day <= today
+  )
+  select json_build_object(
+    'total', (select count(*) from scoped),
+    'completed', (select count(*) from scoped where completed),
+    'open', (select count(*) from scoped where not completed),
+    'completions_per_day', (
+      select coalesce(json_agg(json_build_object('date', d.day, 'count', coalesce(c.n, 0))
+                               order by d.day), '[]'::json)
+        from (select generate_series(from_date, to_date, interval '1 day')::date as day) d
+        left join completion_days c using (day)
+    ),
+    'current_streak', (
+      select count(*)
+        from runs
+       where run = (select max(day) + 1 from runs)
+         and (select max(day) from runs) >= today - 1
+    ),
+    'average_completion_seconds', (
+      select avg(extract(epoch from completed_at - created_at))::float8
+        from scoped
+       where completed and completed_at is not null
+    )
+  );
+$$;
@@ -0,0 +1,66 @@
+-- Blocked-by links: todo_id cannot be completed while blocker_id is open. Access is checked in
+-- Keto by the worker; links go with either todo when it is purged.
+create table if not exists public.todo_dependencies (
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  blocker_id bigint not null references public.todos (id) on delete cascade,
+  created_at timestamptz not null default now(),
+  primary key (todo_id, blocker_id),
+  check (todo_id <> blocker_id)
+);
+
+create index if not exists todo_dependencies_blocker_id_idx on public.todo_dependencies (blocker_id);
+
+-- Adds `dependent` blocked by `blocker` unless that would close a cycle, i.e. `dependent` is
+-- already (transitively) blocking `blocker`. Returns false in that case. The table lock makes
+-- concurrent calls take turns, so two links cannot form a cycle between them.
+create or replace function public.add_todo_dependency(dependent bigint, blocker bigint)
+returns boolean
+language plpgsql
+as $$
+begin
+  lock table public.todo_dependencies in share row exclusive mode;
+  if exists (
+    with recursive upstream (id) as (
+      select blocker
+      union
+      select d.blocker_id
+        from public.todo_dependencies d
+        join upstream u on d.todo_id = u.id
+    )
+    select 1 from upstream where id = dependent
+  ) then
+    return false;
+  end if;
+  insert into public.todo_dependencies (todo_id, blocker_id)
+  values (dependent, blocker)
+  on conflict do nothing;
+  return true;
+end;
+$$;
+
+-- Links reachable from `root` in either direction: what blocks it, transitively, and what it
+-- blocks, transitively. At most `max_edges` rows.
+create or replace function public.todo_dependency_graph(root bigint, max_edges integer)
+returns table (todo_id bigint, blocker_id bigint)
+language sql
+stable
+as $$
+  with recursive upstream (todo_id, blocker_id) as (
+    select d.todo_id, d.blocker_id from public.todo_dependencies d where d.todo_id = root
+    union
+    select d.todo_id, d.blocker_id
+      from public.todo_dependencies d
+      join upstream u on d.todo_id = u.blocker_id
+  ),
+  downstream (todo_id, blocker_id) as (
+    select d.todo_id, d.blocker_id from public.todo_dependencies d where d.blocker_id = root
+    union
+    select d.todo_id, d.blocker_id
+      from public.todo_dependencies d
+      join downstream u on d.blocker_id = u.todo_id
+  )
+  select todo_id, blocker_id from upstream
+  union
+  select todo_id, blocker_id from downstream
+  limit max_edges;
+$$;
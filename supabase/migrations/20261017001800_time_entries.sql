@@ -0,0 +1,99 @@
+-- Time tracked on todos. A running timer is an entry without ended_at; each user has at most one.
+-- Access follows the todo and is checked in Keto by the worker.
+create table if not exists public.time_entries (
+  id bigint generated by default as identity primary key,
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  user_id text not null,
+  started_at timestamptz not null,
+  ended_at timestamptz,
+  note text check (char_length(note) <= 500),
+  created_at timestamptz not null default now(),
+  check (ended_at is null or ended_at >= started_at)
+);
+
+create index if not exists time_entries_todo_id_idx on public.time_entries (todo_id, started_at);
+
+create unique index if not exists time_entries_running_idx
+  on public.time_entries (user_id)
+  where ended_at is null;
+
+-- Starts the user's timer on `todo` at `at`, stopping their timer on any other todo. If it is
+-- already running on `todo`, that entry is returned unchanged. The advisory lock makes concurrent
+-- starts by one user take turns.
+create or replace function public.start_time_entry(todo bigint, for_user text, at timestamptz)
+returns setof public.time_entries
+language plpgsql
+as $$
+begin
+  perform pg_advisory_xact_lock(hashtext('time_entries:' || for_user));
+  update public.time_entries
+     set ended_at = greatest(at, started_at)
+   where user_id = for_user and ended_at is null and todo_id <> todo;
+  return query
+    select * from public.time_entries where user_id = for_user and ended_at is null;
+  if not found then
+    return query
+      insert into public.time_entries (todo_id, user_id, started_at)
+      values (todo, for_user, at)
+      returning *;
+  end if;
+end;
+$$;
+
+-- Tracked seconds per todo and per UTC day between from_date and to_date (inclusive), for
+-- GET /api/todos/time-report. Entries are clipped to the range and split at midnight; a running
+-- timer counts up to `as_of`. `only_user` limits the report to one user's entries.
+create or replace function public.time_report(
+  todo_ids bigint[],
+  from_date date,
+  to_date date,
+  as_of timestamptz,
+  only_user text default null
+)
+returns json
+language sql
+stable
+as $$
+  with days as (
+    select day::date as day,
+           day::timestamp at time zone 'UTC' as day_start,
+           (day + interval '1 day')::timestamp at time zone 'UTC' as day_end
+      from generate_series(from_date, to_date, interval '1 day') day
+  ),
+  entries as (
+    select e.todo_id, e.started_at, coalesce(e.ended_at, as_of) as ended_at
+      from public.time_entries e
+     where e.todo_id = any(todo_ids)
+       and (only_user is null or e.user_id = only_user)
+  ),
+  slices as (
+    select e.todo_id, d.day,
+           extract(epoch from least(e.ended_at, d.day_end) - greatest(e.started_at, d.day_start))
+             as seconds
+      from entries e
+      join days d on e.started_at < d.day_end and e.ended_at > d.day_start
+  ),
+  per_todo as (
+    select s.todo_id, t.title, sum(s.seconds) as seconds
+      from slices s
+      join public.todos t on t.id = s.todo_id
+     group by s.todo_id, t.title
+  )
+  select json_build_object(
+    'from', from_date,
+    'to', to_date,
+    'total_seconds', (select coalesce(sum(seconds), 0)::bigint from slices),
+    'per_todo', (
+      select coalesce(json_agg(json_build_object(
+               'todo_id', todo_id, 'title', title, 'seconds', seconds::bigint)
+             order by seconds desc, todo_id), '[]'::json)
+        from per_todo
+    ),
+    'per_day', (
+      select json_agg(json_build_object('date', d.day, 'seconds', coalesce(s.seconds, 0)::bigint)
+             order by d.day)
+        from days d
+        left join (select day, sum(seconds) as seconds from slices group by day) s using (day)
+    )
+  );
+$$;
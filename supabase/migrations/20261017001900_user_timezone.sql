@@ -0,0 +1,14 @@
+-- IANA time zone used to read dates typed into POST /api/todos/quick; null means UTC.
+alter table public.user_settings
+  add column if not exists timezone text;
+
+-- A settings row can now exist for the time zone alone, so archive_after_days null has to mean
+-- "use the default" and "never archive" becomes 0.
+alter table public.user_settings
+  drop constraint if exists user_settings_archive_after_days_check;
+
+update public.user_settings set archive_after_days = 0 where archive_after_days is null;
+
+alter table public.user_settings
+  add constraint user_settings_archive_after_days_check
+  check (archive_after_days between 0 and 3650);
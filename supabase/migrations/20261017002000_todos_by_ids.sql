@@ -0,0 +1,30 @@
+-- Id sets resolved from Keto can hold thousands of todos, too many for an `id=in.(...)` query
+-- string, so they are sent in the body of these functions instead.
+
+-- Todo rows among `todo_ids`. Called through PostgREST, which applies the usual `select`,
+-- filters, `order` and `limit` to the result; the function is inlined, so they reach the indexes.
+-- With roots_only, only todos whose parent is not among `todo_ids` (top-level for the caller).
+create or replace function public.todos_by_ids(todo_ids bigint[], roots_only boolean default false)
+returns setof public.todos
+language sql
+stable
+as $$
+  select t.*
+    from public.todos t
+   where t.id = any(todo_ids)
+     and (not roots_only or t.parent_id is null or not (t.parent_id = any(todo_ids)));
+$$;
+
+-- Forget the archive schedule of the completed todos among `todo_ids` (see todo_archive).
+create or replace function public.reset_todo_archive_schedule(todo_ids bigint[])
+returns integer
+language sql
+as $$
+  with reset as (
+    update public.todos
+       set archive_after = null
+     where id = any(todo_ids) and completed and archived_at is null
+    returning 1
+  )
+  select count(*)::integer from reset;
+$$;
@@ -0,0 +1,5 @@
+-- Over-long position keys are now shortened row by row by the daily job
+-- (TodoRepo::rebalance_positions), which leaves every other row untouched.
+drop function if exists public.rebalance_todo_positions();
+
+create index if not exists todos_position_len_idx on public.todos (position_len);
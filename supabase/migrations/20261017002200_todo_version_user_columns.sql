@@ -0,0 +1,20 @@
+-- Only bump version when a column a user edits changes. Bookkeeping done by the worker and its
+-- jobs (position rebalancing, archive scheduling and archiving, reminder claims, advancing a
+-- recurring series) must not invalidate the ETags clients hold.
+create or replace function public.bump_todo_version()
+returns trigger
+language plpgsql
+as $$
+begin
+  if (old.title, old.description, old.due_date, old.priority, old.completed, old.parent_id,
+      old.list_id, old.remind_at, old.deleted_at)
+     is distinct from
+     (new.title, new.description, new.due_date, new.priority, new.completed, new.parent_id,
+      new.list_id, new.remind_at, new.deleted_at) then
+    new.version := old.version + 1;
+  else
+    new.version := old.version;
+  end if;
+  return new;
+end;
+$$;
@@ -0,0 +1,10 @@
+-- Owners a reminder was delivered to. When a run fails part way the todo is released for the next
+-- run, which skips these owners so nobody gets the same reminder twice. Keyed by remind_at so a
+-- rescheduled reminder goes out again.
+create table if not exists public.todo_reminder_deliveries (
+  todo_id bigint not null references public.todos (id) on delete cascade,
+  user_id text not null,
+  remind_at timestamptz not null,
+  sent_at timestamptz not null default now(),
+  primary key (todo_id, remind_at, user_id)
+);
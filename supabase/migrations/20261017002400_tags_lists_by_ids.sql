@@ -0,0 +1,29 @@
+-- Tag and list id sets come from Keto and can be as large as a user's todo set, so they are sent
+-- in the request body like `todos_by_ids` rather than in an `id=in.(...)` query string.
+
+-- Tag rows among `tag_ids`; PostgREST applies `select`, filters and `order` to the result.
+create or replace function public.tags_by_ids(tag_ids bigint[])
+returns setof public.tags
+language sql
+stable
+as $$
+  select t.* from public.tags t where t.id = any(tag_ids);
+$$;
+
+-- List rows among `list_ids`.
+create or replace function public.lists_by_ids(list_ids bigint[])
+returns setof public.lists
+language sql
+stable
+as $$
+  select l.* from public.lists l where l.id = any(list_ids);
+$$;
+
+-- Todo rows in any of `list_ids`.
+create or replace function public.todos_in_lists(list_ids bigint[])
+returns setof public.todos
+language sql
+stable
+as $$
+  select t.* from public.todos t where t.list_id = any(list_ids);
+$$;